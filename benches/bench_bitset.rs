@@ -1,52 +1,95 @@
-use aoc_2024::bitset::{BitsetOps, BitsetOpsUnsafe, FixedSizeBitset};
+#[cfg(feature = "exact")]
+use aoc_2024::bitset::Bitset;
+use aoc_2024::bitset::{
+    ArraySparseU16Bitset, ArraySparseU64Bitset, AtomicBitset, AutoBitset, BitsetOps,
+    BitsetOpsUnsafe, BitsetRangeOps, BitwiseOps, DynBitset, FixedSizeBitset, HierarchicalBitset,
+    PackedU128Bitset, PackedU16Bitset, PackedU32Bitset, PackedU64Bitset, PackedU8Bitset,
+    SimdU16Bitset16, SimdU16Bitset2, SimdU16Bitset4, SimdU16Bitset8, SimdU32Bitset2,
+    SimdU32Bitset4, SimdU32Bitset8, SimdU64Bitset2, SimdU64Bitset4, SimdU8Bitset16, SimdU8Bitset2,
+    SimdU8Bitset32, SimdU8Bitset4, SimdU8Bitset8, SparseU16Bitset, SparseU64Bitset, U128Bitset,
+    U16Bitset, U32Bitset, U64Bitset, U8Bitset,
+};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use rand::{self, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-fn benchmark_set_get_unset_bit<BS: BitsetOps + FixedSizeBitset>(c: &mut Criterion, name: &str) {
-    let mut rng = rand::thread_rng();
+// Seeded, so that runs are comparable with each other.
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(2024)
+}
 
-    c.bench_function(format!("set_get_unset_bit {}", name).as_str(), |b| {
-        let i = rng.gen_range(0..BS::fixed_capacity());
+// About half full, with elements anywhere below the capacity.
+fn random_bitset<BS: BitsetOps>(rng: &mut StdRng, capacity: usize) -> BS {
+    let mut bitset = BS::empty();
+    for i in 0..capacity {
+        if rng.gen_bool(0.5) {
+            bitset.insert(i);
+        }
+    }
+    bitset
+}
+
+// Run a benchmark for each bitset type, naming it after the type.
+// Each type comes with the capacity to use, as the dynamic and sparse bitsets don't have one.
+macro_rules! bench_each {
+    ($c:expr, $bench:ident, $($bs:ty => $capacity:expr),* $(,)?) => {
+        $(
+            $bench::<$bs>($c, stringify!($bs), $capacity);
+        )*
+    };
+}
+
+fn benchmark_insert_contains_remove<BS: BitsetOps>(c: &mut Criterion, name: &str, capacity: usize) {
+    let mut rng = rng();
+
+    c.bench_function(format!("insert_contains_remove {}", name).as_str(), |b| {
+        let i = rng.gen_range(0..capacity);
         let mut bs = BS::empty();
         b.iter(move || {
-            bs.set(i);
-            let a = bs.get(i);
-            bs.unset(i);
+            bs.insert(black_box(i));
+            let a = bs.contains(black_box(i));
+            bs.remove(black_box(i));
             a
         })
     });
 }
 
-fn benchmark_unsafe_set_get_unset_bit<BS: BitsetOps + BitsetOpsUnsafe + FixedSizeBitset>(
+fn benchmark_unsafe_insert_contains_remove<BS: BitsetOps + BitsetOpsUnsafe>(
     c: &mut Criterion,
     name: &str,
+    capacity: usize,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = rng();
 
-    c.bench_function(format!("unsafe_set_get_unset_bit {}", name).as_str(), |b| {
-        let i = rng.gen_range(0..BS::fixed_capacity());
-        let mut bs = BS::empty();
-        b.iter(move || unsafe {
-            bs.set_unchecked(i);
-            let a = bs.get_unchecked(i);
-            bs.unset_unchecked(i);
-            a
-        })
-    });
+    c.bench_function(
+        format!("unsafe_insert_contains_remove {}", name).as_str(),
+        |b| {
+            let i = rng.gen_range(0..capacity);
+            // The unchecked operations won't grow a dynamic bitset, so make room first.
+            let mut bs = BS::empty();
+            bs.insert(capacity - 1);
+            bs.remove(capacity - 1);
+            b.iter(move || unsafe {
+                bs.insert_unchecked(black_box(i));
+                let a = bs.contains_unchecked(black_box(i));
+                bs.remove_unchecked(black_box(i));
+                a
+            })
+        },
+    );
 }
 
 fn benchmark_matrix_access<BS: BitsetOps + FixedSizeBitset + Copy>(c: &mut Criterion, name: &str) {
-    let mut rng = rand::thread_rng();
+    let mut rng = rng();
 
     c.bench_function(format!("matrix_access {}", name).as_str(), |b| {
         let i = rng.gen_range(0..BS::fixed_capacity());
         let mut bs = [BS::empty(); 130];
         b.iter(move || {
             let mut a = false;
-            for j in 0..130 {
-                bs[j].set(i);
-                a = bs[j].get(i);
-                bs[j].unset(i);
+            for row in bs.iter_mut() {
+                row.insert(black_box(i));
+                a = row.contains(black_box(i));
+                row.remove(black_box(i));
             }
             a
         })
@@ -57,7 +100,7 @@ fn benchmark_unsafe_matrix_access<BS: BitsetOps + BitsetOpsUnsafe + FixedSizeBit
     c: &mut Criterion,
     name: &str,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = rng();
 
     c.bench_function(format!("unsafe_matrix_access {}", name).as_str(), |b| {
         let i = rng.gen_range(0..BS::fixed_capacity());
@@ -65,249 +108,390 @@ fn benchmark_unsafe_matrix_access<BS: BitsetOps + BitsetOpsUnsafe + FixedSizeBit
         b.iter(move || unsafe {
             let mut a = false;
             for j in 0..130 {
-                bs.get_unchecked_mut(j).set_unchecked(i);
-                a = bs.get_unchecked(j).get_unchecked(i);
-                bs.get_unchecked_mut(j).unset_unchecked(i);
+                bs.get_unchecked_mut(j).insert_unchecked(black_box(i));
+                a = bs.get_unchecked(j).contains_unchecked(black_box(i));
+                bs.get_unchecked_mut(j).remove_unchecked(black_box(i));
             }
             a
         })
     });
 }
 
-fn benchmark_set_get_unset_bit_primitives(c: &mut Criterion) {
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u8>>(
-        c,
-        "PrimitiveBitSet<u8>",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u16>>(
-        c,
-        "PrimitiveBitSet<u16>",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u32>>(
-        c,
-        "PrimitiveBitSet<u32>",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u64>>(
-        c,
-        "PrimitiveBitSet<u64>",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u128>>(
-        c,
-        "PrimitiveBitSet<u128>",
-    );
+// A range that doesn't line up with word boundaries at either end, so we pay for the partial words.
+fn benchmark_range_ops<BS: BitsetOps + BitsetRangeOps>(
+    c: &mut Criterion,
+    name: &str,
+    capacity: usize,
+) {
+    c.bench_function(format!("insert_remove_range {}", name).as_str(), |b| {
+        let range = capacity / 7..capacity - capacity / 5;
+        let mut bs = BS::empty();
+        b.iter(|| {
+            bs.insert_range(black_box(range.clone()));
+            let a = bs.count();
+            bs.remove_range(black_box(range.clone()));
+            a
+        })
+    });
 }
 
-fn benchmark_unsafe_set_get_unset_bit_primitives(c: &mut Criterion) {
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u8>>(
-        c,
-        "PrimitiveBitSet<u8>",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u16>>(
-        c,
-        "PrimitiveBitSet<u16>",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u32>>(
-        c,
-        "PrimitiveBitSet<u32>",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u64>>(
-        c,
-        "PrimitiveBitSet<u64>",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u128>>(
-        c,
-        "PrimitiveBitSet<u128>",
-    );
+fn benchmark_bitwise_ops<BS: BitsetOps + BitwiseOps + Clone>(
+    c: &mut Criterion,
+    name: &str,
+    capacity: usize,
+) {
+    let mut rng = rng();
+    let x: BS = random_bitset(&mut rng, capacity);
+    let y: BS = random_bitset(&mut rng, capacity);
+
+    c.bench_function(format!("bitwise_ops {}", name).as_str(), |b| {
+        b.iter(|| {
+            let (x, y) = (black_box(x.clone()), black_box(y.clone()));
+            let union = x.clone() | y.clone();
+            let symmetric_difference = x.clone() ^ y.clone();
+            (union & symmetric_difference) - (x & y)
+        })
+    });
+    c.bench_function(format!("bitwise_queries {}", name).as_str(), |b| {
+        b.iter(|| {
+            let (x, y) = (black_box(&x), black_box(&y));
+            (x.is_subset(y), x.is_disjoint(y), x.intersection_count(y))
+        })
+    });
 }
 
-fn benchmark_set_get_unset_bit_packed_8(c: &mut Criterion) {
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<8>>(c, "PackedBitset u8, 8");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u16>>(
-        c,
-        "PackedBitset u16, 8",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u32>>(
-        c,
-        "PackedBitset u32, 8",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u64>>(
-        c,
-        "PackedBitset u64, 8",
-    );
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u128>>(
-        c,
-        "PackedBitset u128, 8",
-    );
+fn benchmark_iterate<BS: BitsetOps>(c: &mut Criterion, name: &str, capacity: usize)
+where
+    for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+{
+    let mut rng = rng();
+    let bs: BS = random_bitset(&mut rng, capacity);
+
+    c.bench_function(format!("iterate {}", name).as_str(), |b| {
+        b.iter(|| black_box(&bs).into_iter().sum::<usize>())
+    });
+    c.bench_function(format!("iterate_back {}", name).as_str(), |b| {
+        b.iter(|| black_box(&bs).into_iter().rev().sum::<usize>())
+    });
 }
 
-fn benchmark_unsafe_set_get_unset_bit_packed_8(c: &mut Criterion) {
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<8>>(
-        c,
-        "PackedBitset u8, 8",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u16>>(
-        c,
-        "PackedBitset u16, 8",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u32>>(
-        c,
-        "PackedBitset u32, 8",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u64>>(
-        c,
-        "PackedBitset u64, 8",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::primitives::PrimitiveBitset<u128>>(
-        c,
-        "PackedBitset u128, 8",
-    );
+// The atomic bitset doesn't implement `BitsetOps`, as it is updated through a shared reference.
+fn benchmark_atomic_insert_contains_remove<const N: usize>(c: &mut Criterion, name: &str) {
+    let mut rng = rng();
+
+    c.bench_function(format!("insert_contains_remove {}", name).as_str(), |b| {
+        let i = rng.gen_range(0..N * 64);
+        let bs = AtomicBitset::<N>::empty();
+        b.iter(|| {
+            bs.insert(black_box(i));
+            let a = bs.contains(black_box(i));
+            bs.remove(black_box(i));
+            a
+        })
+    });
 }
 
-fn benchmark_set_get_unset_bit_packed_64(c: &mut Criterion) {
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<64>>(c, "PackedBitset u8, 64");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<64>>(c, "PackedBitset u16, 64");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<64>>(c, "PackedBitset u32, 64");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<64>>(c, "PackedBitset u64, 64");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<64>>(
-        c,
-        "PackedBitset u128, 64",
-    );
+fn benchmark_insert_contains_remove_all(c: &mut Criterion) {
+    bench_each!(
+        c,
+        benchmark_insert_contains_remove,
+        U8Bitset => 8,
+        U16Bitset => 16,
+        U32Bitset => 32,
+        U64Bitset => 64,
+        U128Bitset => 128,
+        PackedU8Bitset<8> => 64,
+        PackedU16Bitset<8> => 128,
+        PackedU32Bitset<8> => 256,
+        PackedU64Bitset<8> => 512,
+        PackedU128Bitset<8> => 1024,
+        SimdU8Bitset2 => 16,
+        SimdU8Bitset4 => 32,
+        SimdU8Bitset8 => 64,
+        SimdU8Bitset16 => 128,
+        SimdU8Bitset32 => 256,
+        SimdU16Bitset2 => 32,
+        SimdU16Bitset4 => 64,
+        SimdU16Bitset8 => 128,
+        SimdU16Bitset16 => 256,
+        SimdU32Bitset2 => 64,
+        SimdU32Bitset4 => 128,
+        SimdU32Bitset8 => 256,
+        SimdU64Bitset2 => 128,
+        SimdU64Bitset4 => 256,
+        DynBitset => 1024,
+        HierarchicalBitset<16, 1> => 1024,
+        AutoBitset<16> => 1024,
+        SparseU16Bitset => 1024,
+        SparseU64Bitset => 1024,
+        ArraySparseU16Bitset<64> => 1024,
+        ArraySparseU64Bitset<16> => 1024,
+    );
+    #[cfg(feature = "exact")]
+    bench_each!(c, benchmark_insert_contains_remove, Bitset<130> => 130);
+    benchmark_atomic_insert_contains_remove::<16>(c, "AtomicBitset<16>");
 }
 
-fn benchmark_unsafe_set_get_unset_bit_packed_64(c: &mut Criterion) {
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<64>>(
-        c,
-        "PackedBitset u8, 64",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<64>>(
-        c,
-        "PackedBitset u16, 64",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<64>>(
-        c,
-        "PackedBitset u32, 64",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<64>>(
-        c,
-        "PackedBitset u64, 64",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<64>>(
-        c,
-        "PackedBitset u128, 64",
-    );
+fn benchmark_unsafe_insert_contains_remove_all(c: &mut Criterion) {
+    bench_each!(
+        c,
+        benchmark_unsafe_insert_contains_remove,
+        U8Bitset => 8,
+        U16Bitset => 16,
+        U32Bitset => 32,
+        U64Bitset => 64,
+        U128Bitset => 128,
+        PackedU8Bitset<8> => 64,
+        PackedU16Bitset<8> => 128,
+        PackedU32Bitset<8> => 256,
+        PackedU64Bitset<8> => 512,
+        PackedU128Bitset<8> => 1024,
+        SimdU8Bitset2 => 16,
+        SimdU8Bitset4 => 32,
+        SimdU8Bitset8 => 64,
+        SimdU8Bitset16 => 128,
+        SimdU8Bitset32 => 256,
+        SimdU16Bitset2 => 32,
+        SimdU16Bitset4 => 64,
+        SimdU16Bitset8 => 128,
+        SimdU16Bitset16 => 256,
+        SimdU32Bitset2 => 64,
+        SimdU32Bitset4 => 128,
+        SimdU32Bitset8 => 256,
+        SimdU64Bitset2 => 128,
+        SimdU64Bitset4 => 256,
+        DynBitset => 1024,
+        AutoBitset<16> => 1024,
+        SparseU16Bitset => 1024,
+        SparseU64Bitset => 1024,
+        ArraySparseU16Bitset<64> => 1024,
+        ArraySparseU64Bitset<16> => 1024,
+    );
+    #[cfg(feature = "exact")]
+    bench_each!(c, benchmark_unsafe_insert_contains_remove, Bitset<130> => 130);
 }
 
-fn benchmark_set_get_unset_bit_packed_fixed_size(c: &mut Criterion) {
-    // The fixed size will be 4 x u128
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<4>>(c, "PackedBitset u128, 4");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<8>>(c, "PackedBitset u64, 8");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<16>>(c, "PackedBitset u32, 16");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<32>>(c, "PackedBitset u16, 32");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<64>>(c, "PackedBitset u8, 64");
+fn benchmark_range_ops_all(c: &mut Criterion) {
+    bench_each!(
+        c,
+        benchmark_range_ops,
+        U8Bitset => 8,
+        U16Bitset => 16,
+        U32Bitset => 32,
+        U64Bitset => 64,
+        U128Bitset => 128,
+        PackedU8Bitset<8> => 64,
+        PackedU16Bitset<8> => 128,
+        PackedU32Bitset<8> => 256,
+        PackedU64Bitset<8> => 512,
+        PackedU128Bitset<8> => 1024,
+        SimdU8Bitset2 => 16,
+        SimdU8Bitset4 => 32,
+        SimdU8Bitset8 => 64,
+        SimdU8Bitset16 => 128,
+        SimdU8Bitset32 => 256,
+        SimdU16Bitset2 => 32,
+        SimdU16Bitset4 => 64,
+        SimdU16Bitset8 => 128,
+        SimdU16Bitset16 => 256,
+        SimdU32Bitset2 => 64,
+        SimdU32Bitset4 => 128,
+        SimdU32Bitset8 => 256,
+        SimdU64Bitset2 => 128,
+        SimdU64Bitset4 => 256,
+        DynBitset => 1024,
+        HierarchicalBitset<16, 1> => 1024,
+        AutoBitset<16> => 1024,
+        SparseU16Bitset => 1024,
+        SparseU64Bitset => 1024,
+        ArraySparseU16Bitset<64> => 1024,
+        ArraySparseU64Bitset<16> => 1024,
+    );
+    #[cfg(feature = "exact")]
+    bench_each!(c, benchmark_range_ops, Bitset<130> => 130);
 }
 
-fn benchmark_unsafe_set_get_unset_bit_packed_fixed_size(c: &mut Criterion) {
-    // The fixed size will be 4 x u128
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<4>>(
-        c,
-        "PackedBitset u128, 4",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<8>>(
+// The hierarchical bitset doesn't have the bitwise operations.
+fn benchmark_bitwise_ops_all(c: &mut Criterion) {
+    bench_each!(
+        c,
+        benchmark_bitwise_ops,
+        U8Bitset => 8,
+        U16Bitset => 16,
+        U32Bitset => 32,
+        U64Bitset => 64,
+        U128Bitset => 128,
+        PackedU8Bitset<8> => 64,
+        PackedU16Bitset<8> => 128,
+        PackedU32Bitset<8> => 256,
+        PackedU64Bitset<8> => 512,
+        PackedU128Bitset<8> => 1024,
+        SimdU8Bitset2 => 16,
+        SimdU8Bitset4 => 32,
+        SimdU8Bitset8 => 64,
+        SimdU8Bitset16 => 128,
+        SimdU8Bitset32 => 256,
+        SimdU16Bitset2 => 32,
+        SimdU16Bitset4 => 64,
+        SimdU16Bitset8 => 128,
+        SimdU16Bitset16 => 256,
+        SimdU32Bitset2 => 64,
+        SimdU32Bitset4 => 128,
+        SimdU32Bitset8 => 256,
+        SimdU64Bitset2 => 128,
+        SimdU64Bitset4 => 256,
+        DynBitset => 1024,
+        AutoBitset<16> => 1024,
+        SparseU16Bitset => 1024,
+        SparseU64Bitset => 1024,
+        ArraySparseU16Bitset<64> => 1024,
+        ArraySparseU64Bitset<16> => 1024,
+    );
+    #[cfg(feature = "exact")]
+    bench_each!(c, benchmark_bitwise_ops, Bitset<130> => 130);
+}
+
+fn benchmark_iterate_all(c: &mut Criterion) {
+    bench_each!(
+        c,
+        benchmark_iterate,
+        U8Bitset => 8,
+        U16Bitset => 16,
+        U32Bitset => 32,
+        U64Bitset => 64,
+        U128Bitset => 128,
+        PackedU8Bitset<8> => 64,
+        PackedU16Bitset<8> => 128,
+        PackedU32Bitset<8> => 256,
+        PackedU64Bitset<8> => 512,
+        PackedU128Bitset<8> => 1024,
+        SimdU8Bitset2 => 16,
+        SimdU8Bitset4 => 32,
+        SimdU8Bitset8 => 64,
+        SimdU8Bitset16 => 128,
+        SimdU8Bitset32 => 256,
+        SimdU16Bitset2 => 32,
+        SimdU16Bitset4 => 64,
+        SimdU16Bitset8 => 128,
+        SimdU16Bitset16 => 256,
+        SimdU32Bitset2 => 64,
+        SimdU32Bitset4 => 128,
+        SimdU32Bitset8 => 256,
+        SimdU64Bitset2 => 128,
+        SimdU64Bitset4 => 256,
+        DynBitset => 1024,
+        HierarchicalBitset<16, 1> => 1024,
+        AutoBitset<16> => 1024,
+        SparseU16Bitset => 1024,
+        SparseU64Bitset => 1024,
+        ArraySparseU16Bitset<64> => 1024,
+        ArraySparseU64Bitset<16> => 1024,
+    );
+    #[cfg(feature = "exact")]
+    bench_each!(c, benchmark_iterate, Bitset<130> => 130);
+}
+
+fn benchmark_insert_contains_remove_packed_64(c: &mut Criterion) {
+    bench_each!(
         c,
-        "PackedBitset u64, 8",
+        benchmark_insert_contains_remove,
+        PackedU8Bitset<64> => 512,
+        PackedU16Bitset<64> => 1024,
+        PackedU32Bitset<64> => 2048,
+        PackedU64Bitset<64> => 4096,
+        PackedU128Bitset<64> => 8192,
     );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<16>>(
+    bench_each!(
         c,
-        "PackedBitset u32, 16",
+        benchmark_unsafe_insert_contains_remove,
+        PackedU8Bitset<64> => 512,
+        PackedU16Bitset<64> => 1024,
+        PackedU32Bitset<64> => 2048,
+        PackedU64Bitset<64> => 4096,
+        PackedU128Bitset<64> => 8192,
     );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<32>>(
+}
+
+fn benchmark_insert_contains_remove_packed_fixed_size(c: &mut Criterion) {
+    // The fixed size will be 4 x u128
+    bench_each!(
         c,
-        "PackedBitset u16, 32",
+        benchmark_insert_contains_remove,
+        PackedU128Bitset<4> => 512,
+        PackedU64Bitset<8> => 512,
+        PackedU32Bitset<16> => 512,
+        PackedU16Bitset<32> => 512,
+        PackedU8Bitset<64> => 512,
     );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<64>>(
+    bench_each!(
         c,
-        "PackedBitset u8, 64",
+        benchmark_unsafe_insert_contains_remove,
+        PackedU128Bitset<4> => 512,
+        PackedU64Bitset<8> => 512,
+        PackedU32Bitset<16> => 512,
+        PackedU16Bitset<32> => 512,
+        PackedU8Bitset<64> => 512,
     );
 }
 
-fn benchmark_set_get_unset_bit_packed_17_bytes(c: &mut Criterion) {
+fn benchmark_insert_contains_remove_packed_17_bytes(c: &mut Criterion) {
     // The size that must be covered is 17 bytes
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<2>>(c, "PackedBitset u128, 2");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<3>>(c, "PackedBitset u64, 3");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<5>>(c, "PackedBitset u32, 5");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<9>>(c, "PackedBitset u16, 9");
-    benchmark_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<17>>(c, "PackedBitset u8, 17");
-}
-
-fn benchmark_unsafe_set_get_unset_bit_packed_17_bytes(c: &mut Criterion) {
-    // The size that must be covered is 17 bytes
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU128Bitset<2>>(
-        c,
-        "PackedBitset u128, 2",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU64Bitset<3>>(
+    bench_each!(
         c,
-        "PackedBitset u64, 3",
+        benchmark_insert_contains_remove,
+        PackedU128Bitset<2> => 136,
+        PackedU64Bitset<3> => 136,
+        PackedU32Bitset<5> => 136,
+        PackedU16Bitset<9> => 136,
+        PackedU8Bitset<17> => 136,
     );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU32Bitset<5>>(
+    bench_each!(
         c,
-        "PackedBitset u32, 5",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU16Bitset<9>>(
-        c,
-        "PackedBitset u16, 9",
-    );
-    benchmark_unsafe_set_get_unset_bit::<aoc_2024::bitset::PackedU8Bitset<17>>(
-        c,
-        "PackedBitset u8, 17",
+        benchmark_unsafe_insert_contains_remove,
+        PackedU128Bitset<2> => 136,
+        PackedU64Bitset<3> => 136,
+        PackedU32Bitset<5> => 136,
+        PackedU16Bitset<9> => 136,
+        PackedU8Bitset<17> => 136,
     );
 }
 
 fn benchmark_matrix_access_packed_17_bytes(c: &mut Criterion) {
-    benchmark_matrix_access::<aoc_2024::bitset::PackedU128Bitset<2>>(c, "PackedBitset u128, 2");
-    benchmark_matrix_access::<aoc_2024::bitset::PackedU64Bitset<3>>(c, "PackedBitset u64, 3");
-    benchmark_matrix_access::<aoc_2024::bitset::PackedU32Bitset<5>>(c, "PackedBitset u32, 5");
-    benchmark_matrix_access::<aoc_2024::bitset::PackedU16Bitset<9>>(c, "PackedBitset u16, 9");
-    benchmark_matrix_access::<aoc_2024::bitset::PackedU8Bitset<17>>(c, "PackedBitset u8, 17");
+    benchmark_matrix_access::<PackedU128Bitset<2>>(c, "PackedBitset u128, 2");
+    benchmark_matrix_access::<PackedU64Bitset<3>>(c, "PackedBitset u64, 3");
+    benchmark_matrix_access::<PackedU32Bitset<5>>(c, "PackedBitset u32, 5");
+    benchmark_matrix_access::<PackedU16Bitset<9>>(c, "PackedBitset u16, 9");
+    benchmark_matrix_access::<PackedU8Bitset<17>>(c, "PackedBitset u8, 17");
+    benchmark_unsafe_matrix_access::<PackedU128Bitset<2>>(c, "PackedBitset u128, 2");
+    benchmark_unsafe_matrix_access::<PackedU64Bitset<3>>(c, "PackedBitset u64, 3");
+    benchmark_unsafe_matrix_access::<PackedU32Bitset<5>>(c, "PackedBitset u32, 5");
+    benchmark_unsafe_matrix_access::<PackedU16Bitset<9>>(c, "PackedBitset u16, 9");
+    benchmark_unsafe_matrix_access::<PackedU8Bitset<17>>(c, "PackedBitset u8, 17");
 }
 
-fn benchmark_unsafe_matrix_access_packed_17_bytes(c: &mut Criterion) {
-    benchmark_unsafe_matrix_access::<aoc_2024::bitset::PackedU128Bitset<2>>(
-        c,
-        "PackedBitset u128, 2",
-    );
-    benchmark_unsafe_matrix_access::<aoc_2024::bitset::PackedU64Bitset<3>>(
-        c,
-        "PackedBitset u64, 3",
-    );
-    benchmark_unsafe_matrix_access::<aoc_2024::bitset::PackedU32Bitset<5>>(
-        c,
-        "PackedBitset u32, 5",
-    );
-    benchmark_unsafe_matrix_access::<aoc_2024::bitset::PackedU16Bitset<9>>(
-        c,
-        "PackedBitset u16, 9",
-    );
-    benchmark_unsafe_matrix_access::<aoc_2024::bitset::PackedU8Bitset<17>>(
-        c,
-        "PackedBitset u8, 17",
-    );
+fn benchmark_matrix_access_simd_256_bits(c: &mut Criterion) {
+    benchmark_matrix_access::<SimdU8Bitset32>(c, "SimdBitset u8, 32");
+    benchmark_matrix_access::<SimdU16Bitset16>(c, "SimdBitset u16, 16");
+    benchmark_matrix_access::<SimdU32Bitset8>(c, "SimdBitset u32, 8");
+    benchmark_matrix_access::<SimdU64Bitset4>(c, "SimdBitset u64, 4");
+    benchmark_unsafe_matrix_access::<SimdU8Bitset32>(c, "SimdBitset u8, 32");
+    benchmark_unsafe_matrix_access::<SimdU16Bitset16>(c, "SimdBitset u16, 16");
+    benchmark_unsafe_matrix_access::<SimdU32Bitset8>(c, "SimdBitset u32, 8");
+    benchmark_unsafe_matrix_access::<SimdU64Bitset4>(c, "SimdBitset u64, 4");
 }
 
 criterion_group!(
     benches,
-    benchmark_set_get_unset_bit_primitives,
-    benchmark_set_get_unset_bit_packed_8,
-    benchmark_set_get_unset_bit_packed_64,
-    benchmark_set_get_unset_bit_packed_fixed_size,
-    benchmark_unsafe_set_get_unset_bit_primitives,
-    benchmark_unsafe_set_get_unset_bit_packed_8,
-    benchmark_unsafe_set_get_unset_bit_packed_64,
-    benchmark_unsafe_set_get_unset_bit_packed_fixed_size,
-    benchmark_set_get_unset_bit_packed_17_bytes,
-    benchmark_unsafe_set_get_unset_bit_packed_17_bytes,
+    benchmark_insert_contains_remove_all,
+    benchmark_unsafe_insert_contains_remove_all,
+    benchmark_range_ops_all,
+    benchmark_bitwise_ops_all,
+    benchmark_iterate_all,
+    benchmark_insert_contains_remove_packed_64,
+    benchmark_insert_contains_remove_packed_fixed_size,
+    benchmark_insert_contains_remove_packed_17_bytes,
     benchmark_matrix_access_packed_17_bytes,
-    benchmark_unsafe_matrix_access_packed_17_bytes,
+    benchmark_matrix_access_simd_256_bits,
 );
 
 criterion_main!(benches);
@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 use aoc_2024::day8::{
-    antenna_to_index_usize_early, antenna_to_index_usize_late, antenna_to_index_usize_mid, parse_rc, parse_skip, part1_solve_enumerated, part1_solve_rc, MAP_SIZE
+    antenna_to_index_usize_early, antenna_to_index_usize_late, antenna_to_index_usize_mid, parse_rc, parse_skip, part1_solve_enumerated, part1_solve_rc
 };
 
 fn bench_parse_input(c: &mut Criterion) {
@@ -72,17 +72,17 @@ fn bench_part1(c: &mut Criterion) {
     let input = include_str!("../input/2024/day8.txt");
     c.bench_function("part 1 using rc", |b| {
         b.iter(|| {
-            part1_solve_rc(input, MAP_SIZE)
+            part1_solve_rc(input)
         })
     });
     c.bench_function("part 1 using enumerated", |b| {
         b.iter(|| {
-            part1_solve_enumerated(input, MAP_SIZE)
+            part1_solve_enumerated(input)
         })
     });
     c.bench_function("part 1 using enumerated2", |b| {
         b.iter(|| {
-            part1_solve_enumerated(input, MAP_SIZE)
+            part1_solve_enumerated(input)
         })
     });
 }
@@ -40,4 +40,4 @@ macro_rules! benches {
     };
 }
 
-benches!(1, 2, 3, 4, 5, 7, 8, 10, 11); // Add more days here
+benches!(1, 2, 3, 4, 5, 7, 8, 10, 11, 12); // Add more days here
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use aoc_2024::stack_vec::ArrayDeque;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// Just enough of a queue to run the same benchmarks over both deques.
+trait Queue<T>: Default {
+    fn push_back(&mut self, value: T);
+    fn push_front(&mut self, value: T);
+    fn pop_front(&mut self) -> Option<T>;
+    fn pop_back(&mut self) -> Option<T>;
+    fn sum(&self) -> T;
+}
+
+impl<const N: usize> Queue<u32> for ArrayDeque<u32, N> {
+    fn push_back(&mut self, value: u32) {
+        ArrayDeque::push_back(self, value)
+    }
+
+    fn push_front(&mut self, value: u32) {
+        ArrayDeque::push_front(self, value)
+    }
+
+    fn pop_front(&mut self) -> Option<u32> {
+        ArrayDeque::pop_front(self)
+    }
+
+    fn pop_back(&mut self) -> Option<u32> {
+        ArrayDeque::pop_back(self)
+    }
+
+    fn sum(&self) -> u32 {
+        self.iter().sum()
+    }
+}
+
+impl Queue<u32> for VecDeque<u32> {
+    fn push_back(&mut self, value: u32) {
+        VecDeque::push_back(self, value)
+    }
+
+    fn push_front(&mut self, value: u32) {
+        VecDeque::push_front(self, value)
+    }
+
+    fn pop_front(&mut self) -> Option<u32> {
+        VecDeque::pop_front(self)
+    }
+
+    fn pop_back(&mut self) -> Option<u32> {
+        VecDeque::pop_back(self)
+    }
+
+    fn sum(&self) -> u32 {
+        self.iter().sum()
+    }
+}
+
+// The queue a BFS keeps, with a steady number of elements waiting, wrapping round the buffer as it goes.
+fn benchmark_steady_queue<Q: Queue<u32>>(c: &mut Criterion, name: &str) {
+    c.bench_function(format!("steady_queue {}", name).as_str(), |b| {
+        let mut q = Q::default();
+        for i in 0..32 {
+            q.push_back(i);
+        }
+        b.iter(|| {
+            let x = q.pop_front().unwrap();
+            q.push_back(black_box(x));
+        })
+    });
+}
+
+// Filling the queue from empty, and then draining it, including creating it each time.
+fn benchmark_fill_drain<Q: Queue<u32>>(c: &mut Criterion, name: &str) {
+    c.bench_function(format!("fill_drain {}", name).as_str(), |b| {
+        b.iter(|| {
+            let mut q = Q::default();
+            for i in 0..64 {
+                q.push_back(black_box(i));
+            }
+            let mut total = 0;
+            while let Some(x) = q.pop_front() {
+                total += x;
+            }
+            total
+        })
+    });
+}
+
+// Using it as a stack at the front, and a queue at the back.
+fn benchmark_both_ends<Q: Queue<u32>>(c: &mut Criterion, name: &str) {
+    c.bench_function(format!("both_ends {}", name).as_str(), |b| {
+        let mut q = Q::default();
+        for i in 0..32 {
+            q.push_back(i);
+        }
+        b.iter(|| {
+            q.push_front(black_box(1));
+            let a = q.pop_back().unwrap();
+            q.push_back(black_box(a));
+            q.pop_front()
+        })
+    });
+}
+
+// Summing the elements, after wrapping round so both halves have some in.
+fn benchmark_iterate<Q: Queue<u32>>(c: &mut Criterion, name: &str) {
+    c.bench_function(format!("iterate {}", name).as_str(), |b| {
+        let mut q = Q::default();
+        for i in 0..64 {
+            q.push_back(i);
+        }
+        for i in 0..32 {
+            q.pop_front();
+            q.push_back(i);
+        }
+        b.iter(|| black_box(&q).sum())
+    });
+}
+
+// A flood fill over an open grid from one corner, which is what the solvers want the queue for.
+fn benchmark_flood_fill<Q: Queue<u32>>(c: &mut Criterion, name: &str) {
+    const SIZE: u32 = 64;
+
+    c.bench_function(format!("flood_fill {}", name).as_str(), |b| {
+        b.iter(|| {
+            let mut seen = [false; (SIZE * SIZE) as usize];
+            let mut q = Q::default();
+            q.push_back(0);
+            seen[0] = true;
+            let mut count = 0;
+            while let Some(at) = q.pop_front() {
+                count += 1;
+                let (r, c) = (at / SIZE, at % SIZE);
+                for (dr, dc) in [(0, 1), (1, 0)] {
+                    let (r, c) = (r + dr, c + dc);
+                    let next = r * SIZE + c;
+                    if r < SIZE && c < SIZE && !seen[next as usize] {
+                        seen[next as usize] = true;
+                        q.push_back(next);
+                    }
+                }
+            }
+            black_box(count)
+        })
+    });
+}
+
+fn benchmark_deques(c: &mut Criterion) {
+    benchmark_steady_queue::<ArrayDeque<u32, 64>>(c, "ArrayDeque");
+    benchmark_steady_queue::<VecDeque<u32>>(c, "VecDeque");
+    benchmark_fill_drain::<ArrayDeque<u32, 64>>(c, "ArrayDeque");
+    benchmark_fill_drain::<VecDeque<u32>>(c, "VecDeque");
+    benchmark_both_ends::<ArrayDeque<u32, 64>>(c, "ArrayDeque");
+    benchmark_both_ends::<VecDeque<u32>>(c, "VecDeque");
+    benchmark_iterate::<ArrayDeque<u32, 64>>(c, "ArrayDeque");
+    benchmark_iterate::<VecDeque<u32>>(c, "VecDeque");
+    // The widest frontier is a diagonal of the grid.
+    benchmark_flood_fill::<ArrayDeque<u32, 128>>(c, "ArrayDeque");
+    benchmark_flood_fill::<VecDeque<u32>>(c, "VecDeque");
+}
+
+// The unchecked operations, to see what the capacity checks cost.
+fn benchmark_unchecked(c: &mut Criterion) {
+    c.bench_function("steady_queue_unchecked ArrayDeque", |b| {
+        let mut q: ArrayDeque<u32, 64> = (0..32).collect();
+        b.iter(|| unsafe {
+            let x = q.pop_front_unchecked();
+            q.push_back_unchecked(black_box(x));
+        })
+    });
+}
+
+criterion_group!(benches, benchmark_deques, benchmark_unchecked);
+
+criterion_main!(benches);
@@ -0,0 +1,178 @@
+///- A bitset that many threads can update at once, for solvers that share a visited set.
+///- It is laid out like a `PackedU64Bitset<N>`, but with atomic words, so every operation works through `&self`.
+///- Each operation touches a single word, so they are all lock-free.
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::*;
+
+pub struct AtomicBitset<const N: usize> {
+    words: [AtomicU64; N],
+}
+
+impl<const N: usize> AtomicBitset<N> {
+    const WORD_BITS: usize = u64::BITS as usize;
+
+    pub fn empty() -> Self {
+        Self {
+            words: [const { AtomicU64::new(0) }; N],
+        }
+    }
+
+    // The word holding `index`, and the mask for its bit.
+    fn locate(index: usize) -> (usize, u64) {
+        (index / Self::WORD_BITS, 1 << (index % Self::WORD_BITS))
+    }
+
+    /// Set the bit, returning true if this call was the one that set it.
+    ///
+    /// When several threads race to insert the same index, exactly one of them sees true,
+    /// so this can be used to claim work.
+    pub fn insert(&self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
+        let (word, mask) = Self::locate(index);
+        self.words[word].fetch_or(mask, Ordering::AcqRel) & mask == 0
+    }
+
+    /// Clear the bit, returning true if this call was the one that cleared it.
+    pub fn remove(&self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
+        let (word, mask) = Self::locate(index);
+        self.words[word].fetch_and(!mask, Ordering::AcqRel) & mask != 0
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
+        let (word, mask) = Self::locate(index);
+        self.words[word].load(Ordering::Acquire) & mask != 0
+    }
+
+    /// The number of elements.
+    ///
+    /// The words are read one at a time, so if other threads are updating the set,
+    /// this may not match the set at any single moment.
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|w| w.load(Ordering::Acquire).count_ones() as usize)
+            .sum()
+    }
+
+    /// Remove every element.
+    pub fn clear(&self) {
+        for w in &self.words {
+            w.store(0, Ordering::Release);
+        }
+    }
+
+    /// Copy the bits out into a plain bitset, for the single-threaded operations.
+    ///
+    /// As with `count`, this is only a consistent picture once the other threads have stopped updating.
+    pub fn snapshot(&self) -> PackedU64Bitset<N> {
+        let mut snapshot = PackedU64Bitset::<N>::empty();
+        for (s, w) in snapshot.words_mut().iter_mut().zip(&self.words) {
+            s.bits = w.load(Ordering::Acquire);
+        }
+        snapshot
+    }
+}
+
+impl<const N: usize> FixedSizeBitset for AtomicBitset<N> {
+    fn fixed_capacity() -> usize {
+        N * Self::WORD_BITS
+    }
+}
+
+impl<const N: usize> Default for AtomicBitset<N> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const N: usize> From<PackedU64Bitset<N>> for AtomicBitset<N> {
+    fn from(bitset: PackedU64Bitset<N>) -> Self {
+        Self {
+            words: bitset.words().map(|w| AtomicU64::new(w.bits)),
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for AtomicBitset<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AtomicBitset {}", self.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    type Visited = AtomicBitset<4>;
+
+    #[test]
+    fn insert_contains_remove() {
+        let visited = Visited::empty();
+        assert_eq!(Visited::fixed_capacity(), 256);
+        assert_eq!(visited.count(), 0);
+
+        assert!(visited.insert(0));
+        assert!(visited.insert(255));
+        assert!(!visited.insert(255));
+        assert!(visited.contains(255));
+        assert!(!visited.contains(254));
+        assert_eq!(visited.count(), 2);
+
+        assert!(visited.remove(255));
+        assert!(!visited.remove(255));
+        assert_eq!(visited.count(), 1);
+
+        visited.clear();
+        assert_eq!(visited.count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 256 is out of range for a bitset with capacity 256")]
+    fn out_of_range_panics() {
+        Visited::empty().insert(256);
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let visited = Visited::empty();
+        for i in [1, 63, 64, 200] {
+            visited.insert(i);
+        }
+        let snapshot = visited.snapshot();
+        assert!(snapshot.into_iter().eq([1, 63, 64, 200]));
+        assert_eq!(Visited::from(snapshot).snapshot(), snapshot);
+        assert_eq!(format!("{:?}", visited), "AtomicBitset {1, 63, 64, 200}");
+    }
+
+    // Threads racing over the same indices should each claim a different share of them,
+    // with every index claimed exactly once.
+    #[test]
+    fn threads_claim_each_bit_once() {
+        let visited = Visited::empty();
+        let claimed: usize = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|t| {
+                    let visited = &visited;
+                    scope.spawn(move || {
+                        (0..Visited::fixed_capacity())
+                            .cycle()
+                            .skip(t * 17)
+                            .take(Visited::fixed_capacity())
+                            .filter(|&i| visited.insert(i))
+                            .count()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(claimed, Visited::fixed_capacity());
+        assert_eq!(visited.count(), Visited::fixed_capacity());
+        assert_eq!(visited.snapshot(), PackedU64Bitset::full());
+    }
+}
@@ -0,0 +1,592 @@
+///- A bitset that picks its SIMD backend at runtime.
+///- The best lane configuration for `SimdBitset` depends on the machine, as the bitset benchmarks show,
+///- and we don't want to build a different binary for each one.
+///- The bits are stored as a `PackedU64Bitset`, which handles all the single element operations.
+///- The whole-set operations are then dispatched to a copy of the SIMD kernels compiled for AVX-512, AVX2 or SSE2,
+///- whichever is the best that the CPU supports, or done by the packed bitset if there's no SIMD at all.
+use std::simd::Simd;
+
+use super::simd::SimdBitset;
+use super::*;
+
+/// The instruction sets that the whole-set operations can be dispatched to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// `SimdBitset<u64, 8>`, using 512 bit registers.
+    Avx512,
+    /// `SimdBitset<u64, 4>`, using 256 bit registers.
+    Avx2,
+    /// `SimdBitset<u64, 2>`, using 128 bit registers.
+    Sse2,
+    /// `PackedBitset`, a word at a time.
+    Packed,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 4] = [
+        Backend::Avx512,
+        Backend::Avx2,
+        Backend::Sse2,
+        Backend::Packed,
+    ];
+
+    /// The best backend that this machine supports.
+    ///
+    /// The standard library caches the feature detection, so this is cheap enough to call for every operation.
+    pub fn detect() -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.is_supported())
+            .unwrap()
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            // SSE2 is part of the x86_64 baseline.
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse2 => true,
+            Backend::Packed => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+/// A bitset of `N` `u64` words, sized the same way as `PackedU64Bitset<N>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoBitset<const N: usize> {
+    bits: PackedU64Bitset<N>,
+}
+
+// The kernels are written once, generic over the number of lanes.
+// Each backend inlines them into functions compiled with its target features enabled,
+// which is what gets us the wide registers without compiling the whole crate for them.
+// Words that don't fill a whole vector are done one at a time as `U64Bitset`s.
+
+// A lane-wise operation, that has to work for both the SIMD chunks and the words left over.
+trait Combine {
+    fn combine<BS: BitwiseOps>(a: BS, b: BS) -> BS;
+}
+
+struct And;
+struct Or;
+struct Xor;
+struct Difference;
+
+impl Combine for And {
+    fn combine<BS: BitwiseOps>(a: BS, b: BS) -> BS {
+        a & b
+    }
+}
+
+impl Combine for Or {
+    fn combine<BS: BitwiseOps>(a: BS, b: BS) -> BS {
+        a | b
+    }
+}
+
+impl Combine for Xor {
+    fn combine<BS: BitwiseOps>(a: BS, b: BS) -> BS {
+        a ^ b
+    }
+}
+
+impl Combine for Difference {
+    fn combine<BS: BitwiseOps>(a: BS, b: BS) -> BS {
+        a - b
+    }
+}
+
+// A test that must hold lane by lane, so we can stop at the first failure.
+trait Predicate {
+    fn holds<BS: BitwiseOps>(a: &BS, b: &BS) -> bool;
+}
+
+struct Subset;
+struct Disjoint;
+
+impl Predicate for Subset {
+    fn holds<BS: BitwiseOps>(a: &BS, b: &BS) -> bool {
+        a.is_subset(b)
+    }
+}
+
+impl Predicate for Disjoint {
+    fn holds<BS: BitwiseOps>(a: &BS, b: &BS) -> bool {
+        a.is_disjoint(b)
+    }
+}
+
+#[inline(always)]
+fn load<const L: usize>(words: &[U64Bitset; L]) -> SimdBitset<u64, L> {
+    SimdBitset {
+        bits: Simd::from_array(words.map(|w| w.bits)),
+    }
+}
+
+#[inline(always)]
+fn combine_lanes<K: Combine, const L: usize>(a: &mut [U64Bitset], b: &[U64Bitset]) {
+    let (a_chunks, a_tail) = a.as_chunks_mut::<L>();
+    let (b_chunks, b_tail) = b.as_chunks::<L>();
+    for (x, y) in a_chunks.iter_mut().zip(b_chunks) {
+        let combined = K::combine(load(x), load(y)).bits.to_array();
+        *x = combined.map(|bits| U64Bitset { bits });
+    }
+    for (x, y) in a_tail.iter_mut().zip(b_tail) {
+        *x = K::combine(*x, *y);
+    }
+}
+
+#[inline(always)]
+fn all_lanes<K: Predicate, const L: usize>(a: &[U64Bitset], b: &[U64Bitset]) -> bool {
+    let (a_chunks, a_tail) = a.as_chunks::<L>();
+    let (b_chunks, b_tail) = b.as_chunks::<L>();
+    a_chunks
+        .iter()
+        .zip(b_chunks)
+        .all(|(x, y)| K::holds(&load(x), &load(y)))
+        && a_tail.iter().zip(b_tail).all(|(x, y)| K::holds(x, y))
+}
+
+#[inline(always)]
+fn intersection_count_lanes<const L: usize>(a: &[U64Bitset], b: &[U64Bitset]) -> usize {
+    let (a_chunks, a_tail) = a.as_chunks::<L>();
+    let (b_chunks, b_tail) = b.as_chunks::<L>();
+    let chunks: usize = a_chunks
+        .iter()
+        .zip(b_chunks)
+        .map(|(x, y)| load(x).intersection_count(&load(y)))
+        .sum();
+    chunks
+        + a_tail
+            .iter()
+            .zip(b_tail)
+            .map(|(x, y)| x.intersection_count(y))
+            .sum::<usize>()
+}
+
+#[inline(always)]
+fn count_lanes<const L: usize>(a: &[U64Bitset]) -> usize {
+    let (chunks, tail) = a.as_chunks::<L>();
+    chunks.iter().map(|x| load(x).count()).sum::<usize>()
+        + tail.iter().map(|x| x.count()).sum::<usize>()
+}
+
+// Stamp out a copy of the kernels for a backend that needs target features turning on.
+// These are only safe to call once we've checked that the CPU has the features.
+macro_rules! target_kernels {
+    ($name:ident, $feature:literal, $lanes:literal) => {
+        #[cfg(target_arch = "x86_64")]
+        mod $name {
+            use super::*;
+
+            #[target_feature(enable = $feature)]
+            pub(super) unsafe fn combine<K: Combine>(a: &mut [U64Bitset], b: &[U64Bitset]) {
+                combine_lanes::<K, $lanes>(a, b)
+            }
+
+            #[target_feature(enable = $feature)]
+            pub(super) unsafe fn all<K: Predicate>(a: &[U64Bitset], b: &[U64Bitset]) -> bool {
+                all_lanes::<K, $lanes>(a, b)
+            }
+
+            #[target_feature(enable = $feature)]
+            pub(super) unsafe fn intersection_count(a: &[U64Bitset], b: &[U64Bitset]) -> usize {
+                intersection_count_lanes::<$lanes>(a, b)
+            }
+
+            #[target_feature(enable = $feature)]
+            pub(super) unsafe fn count(a: &[U64Bitset]) -> usize {
+                count_lanes::<$lanes>(a)
+            }
+        }
+    };
+}
+
+target_kernels!(avx512, "avx512f", 8);
+target_kernels!(avx2, "avx2", 4);
+
+impl<const N: usize> AutoBitset<N> {
+    // The backend is passed in, rather than detected, so that the tests can try them all.
+    // These are unsafe as passing a backend that the machine doesn't support is undefined behaviour.
+    unsafe fn combine_with<K: Combine>(&mut self, rhs: &Self, backend: Backend) {
+        let (a, b) = (self.bits.words_mut(), rhs.bits.words());
+        match backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => unsafe { avx512::combine::<K>(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2::combine::<K>(a, b) },
+            Backend::Sse2 => combine_lanes::<K, 2>(a, b),
+            _ => self.bits = K::combine(self.bits, rhs.bits),
+        }
+    }
+
+    unsafe fn all_with<K: Predicate>(&self, rhs: &Self, backend: Backend) -> bool {
+        let (a, b) = (self.bits.words(), rhs.bits.words());
+        match backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => unsafe { avx512::all::<K>(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2::all::<K>(a, b) },
+            Backend::Sse2 => all_lanes::<K, 2>(a, b),
+            _ => K::holds(&self.bits, &rhs.bits),
+        }
+    }
+
+    unsafe fn intersection_count_with(&self, rhs: &Self, backend: Backend) -> usize {
+        let (a, b) = (self.bits.words(), rhs.bits.words());
+        match backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => unsafe { avx512::intersection_count(a, b) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2::intersection_count(a, b) },
+            Backend::Sse2 => intersection_count_lanes::<2>(a, b),
+            _ => self.bits.intersection_count(&rhs.bits),
+        }
+    }
+
+    unsafe fn count_with(&self, backend: Backend) -> usize {
+        let a = self.bits.words();
+        match backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => unsafe { avx512::count(a) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2::count(a) },
+            Backend::Sse2 => count_lanes::<2>(a),
+            _ => self.bits.count(),
+        }
+    }
+}
+
+impl<const N: usize> FixedSizeBitset for AutoBitset<N> {
+    fn fixed_capacity() -> usize {
+        PackedU64Bitset::<N>::fixed_capacity()
+    }
+}
+
+impl<const N: usize> FullBitset for AutoBitset<N> {
+    fn full() -> Self {
+        Self {
+            bits: PackedU64Bitset::full(),
+        }
+    }
+}
+
+// Single elements don't gain anything from SIMD, so go straight to the packed bitset.
+impl<const N: usize> BitsetOps for AutoBitset<N> {
+    fn empty() -> Self {
+        Self {
+            bits: PackedU64Bitset::empty(),
+        }
+    }
+
+    fn insert(&mut self, index: usize) -> bool {
+        self.bits.insert(index)
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.bits.remove(index)
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.bits.contains(index)
+    }
+
+    fn count(&self) -> usize {
+        unsafe { self.count_with(Backend::detect()) }
+    }
+}
+
+impl<const N: usize> BitsetOpsUnsafe for AutoBitset<N> {
+    unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
+        self.bits.insert_unchecked(index)
+    }
+
+    unsafe fn remove_unchecked(&mut self, index: usize) {
+        self.bits.remove_unchecked(index)
+    }
+
+    unsafe fn contains_unchecked(&self, index: usize) -> bool {
+        self.bits.contains_unchecked(index)
+    }
+}
+
+impl<const N: usize> BitsetTryOps for AutoBitset<N> {}
+
+impl<const N: usize> BitsetRangeOps for AutoBitset<N> {
+    fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.bits.insert_range(range)
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.bits.remove_range(range)
+    }
+
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps,
+    {
+        Self {
+            bits: PackedU64Bitset::from_range(range),
+        }
+    }
+}
+
+impl<const N: usize> BitsetRankOps for AutoBitset<N> {
+    fn rank(&self, index: usize) -> usize {
+        self.bits.rank(index)
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        self.bits.select(k)
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        self.bits.next_set_after(index)
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        self.bits.prev_set_before(index)
+    }
+}
+
+// The whole-set operations are the ones worth dispatching.
+impl<const N: usize> BitAnd for AutoBitset<N> {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self &= rhs;
+        self
+    }
+}
+
+impl<const N: usize> BitAndAssign for AutoBitset<N> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        unsafe { self.combine_with::<And>(&rhs, Backend::detect()) };
+    }
+}
+
+impl<const N: usize> BitOr for AutoBitset<N> {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self |= rhs;
+        self
+    }
+}
+
+impl<const N: usize> BitOrAssign for AutoBitset<N> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        unsafe { self.combine_with::<Or>(&rhs, Backend::detect()) };
+    }
+}
+
+impl<const N: usize> BitXor for AutoBitset<N> {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: Self) -> Self::Output {
+        self ^= rhs;
+        self
+    }
+}
+
+impl<const N: usize> BitXorAssign for AutoBitset<N> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        unsafe { self.combine_with::<Xor>(&rhs, Backend::detect()) };
+    }
+}
+
+impl<const N: usize> Sub for AutoBitset<N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl<const N: usize> SubAssign for AutoBitset<N> {
+    fn sub_assign(&mut self, rhs: Self) {
+        unsafe { self.combine_with::<Difference>(&rhs, Backend::detect()) };
+    }
+}
+
+impl<const N: usize> Not for AutoBitset<N> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self { bits: !self.bits }
+    }
+}
+
+impl<const N: usize> BitwiseOps for AutoBitset<N> {
+    fn is_subset(&self, other: &Self) -> bool {
+        unsafe { self.all_with::<Subset>(other, Backend::detect()) }
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        unsafe { self.all_with::<Disjoint>(other, Backend::detect()) }
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        unsafe { self.intersection_count_with(other, Backend::detect()) }
+    }
+}
+
+impl<const N: usize> ComplementOps for AutoBitset<N> {}
+
+impl<const N: usize> BitsetByteOps for AutoBitset<N> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.bits.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        PackedU64Bitset::from_bytes(bytes).map(|bits| Self { bits })
+    }
+}
+
+impl<const N: usize> Default for AutoBitset<N> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const N: usize> Extend<usize> for AutoBitset<N> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        self.bits.extend(iter)
+    }
+}
+
+impl<const N: usize> FromIterator<usize> for AutoBitset<N> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        Self {
+            bits: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<const N: usize> fmt::Display for AutoBitset<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.bits.fmt(f)
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a AutoBitset<N> {
+    type IntoIter = <&'a PackedU64Bitset<N> as IntoIterator>::IntoIter;
+    type Item = usize;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (&self.bits).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    // Small enough to be all tail, then enough for a vector or two of each width with words left over.
+    type Auto1 = AutoBitset<1>;
+    type Auto4 = AutoBitset<4>;
+    type Auto9 = AutoBitset<9>;
+    type Auto16 = AutoBitset<16>;
+
+    crate::generate_tests!(test_empty, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_full, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_set_get, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_range_out_of_range_panics, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_set_range, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_unset_range, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bitwise_and, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bitwise_and_assign, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bitwise_or, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bitwise_or_assign, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bitwise_xor, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_difference, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_complement, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_subset_superset, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_disjoint, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_intersection_count, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_set_two_bit_iterator, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_set_two_bit_iterator_back, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_from_iterator, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_from_range, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_try_ops, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_bytes_round_trip, Auto1, Auto4, Auto9);
+    crate::generate_tests!(test_conformance, Auto1, Auto4, Auto9);
+
+    #[test]
+    fn capacity_is_whole_words() {
+        assert_eq!(Auto1::fixed_capacity(), 64);
+        assert_eq!(Auto4::fixed_capacity(), 256);
+        assert_eq!(Auto9::fixed_capacity(), 576);
+    }
+
+    #[test]
+    fn detects_a_supported_backend() {
+        assert!(Backend::detect().is_supported());
+        assert!(Backend::Packed.is_supported());
+    }
+
+    // Every backend this machine can run should agree with the packed bitset.
+    #[test]
+    fn backends_agree() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(2024);
+        let mut random = || -> Auto16 {
+            let density = rng.gen_range(0.0..=1.0);
+            (0..Auto16::fixed_capacity())
+                .filter(|_| rng.gen_bool(density))
+                .collect()
+        };
+
+        for _ in 0..50 {
+            let (a, b) = (random(), random());
+            let sub = a & b;
+            for backend in Backend::ALL.into_iter().filter(|b| b.is_supported()) {
+                // Safe, as we only try the backends that this machine supports.
+                unsafe {
+                    let combined = |f: fn(&mut Auto16, &Auto16, Backend)| {
+                        let mut c = a;
+                        f(&mut c, &b, backend);
+                        c.bits
+                    };
+                    assert_eq!(
+                        combined(|a, b, k| a.combine_with::<And>(b, k)),
+                        a.bits & b.bits
+                    );
+                    assert_eq!(
+                        combined(|a, b, k| a.combine_with::<Or>(b, k)),
+                        a.bits | b.bits
+                    );
+                    assert_eq!(
+                        combined(|a, b, k| a.combine_with::<Xor>(b, k)),
+                        a.bits ^ b.bits
+                    );
+                    assert_eq!(
+                        combined(|a, b, k| a.combine_with::<Difference>(b, k)),
+                        a.bits - b.bits
+                    );
+
+                    assert_eq!(a.count_with(backend), a.bits.count(), "{:?}", backend);
+                    assert_eq!(
+                        a.intersection_count_with(&b, backend),
+                        a.bits.intersection_count(&b.bits)
+                    );
+                    assert_eq!(a.all_with::<Subset>(&b, backend), a.bits.is_subset(&b.bits));
+                    assert!(sub.all_with::<Subset>(&a, backend));
+                    assert_eq!(
+                        a.all_with::<Disjoint>(&b, backend),
+                        a.bits.is_disjoint(&b.bits)
+                    );
+                    assert!((a - b).all_with::<Disjoint>(&b, backend));
+                }
+            }
+        }
+    }
+}
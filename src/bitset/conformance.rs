@@ -0,0 +1,268 @@
+///- A model-based conformance suite for bitsets, behind the `testing` feature.
+///- Random sequences of operations are applied both to the bitset under test and to a `BTreeSet<usize>`,
+///- and after every step the two must agree on everything we can ask of them.
+///- The hand-picked tests in this module's parent are good at pinning down edge cases,
+///- but this is better at finding the combinations nobody thought to write down.
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::ops::Range;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{BitsetOps, BitsetRangeOps, BitwiseOps};
+
+/// One step of a conformance run.
+///
+/// The bitwise operations carry the elements of the other operand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    Insert(usize),
+    Remove(usize),
+    InsertRange(Range<usize>),
+    RemoveRange(Range<usize>),
+    And(Vec<usize>),
+    Or(Vec<usize>),
+    Xor(Vec<usize>),
+    Difference(Vec<usize>),
+}
+
+/// The number of runs, and steps in each run, used by `check_bitset`.
+pub const RUNS: usize = 20;
+pub const STEPS: usize = 100;
+
+/// Check a bitset against the oracle, using elements from `0..universe`.
+///
+/// For a fixed-size bitset, the universe would usually be its capacity.
+/// Panics with the seed and the operations so far if the bitset and the oracle disagree.
+pub fn check_bitset<BS>(universe: usize)
+where
+    BS: BitsetOps + BitsetRangeOps + BitwiseOps + Clone + Debug,
+    for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+{
+    for seed in 0..RUNS as u64 {
+        let ops = random_ops(universe, STEPS, seed);
+        if let Err(step) = std::panic::catch_unwind(|| check_ops::<BS>(&ops)) {
+            panic!(
+                "Conformance failure with seed {}: {}\nOperations: {:?}",
+                seed,
+                panic_message(&step),
+                ops
+            );
+        }
+    }
+}
+
+/// A random sequence of operations over `0..universe`, the same for the same seed.
+pub fn random_ops(universe: usize, steps: usize, seed: u64) -> Vec<Op> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    // Some runs are dense and some are sparse, so that we see both full and empty words.
+    let density = rng.gen_range(0.0..=1.0);
+    let range = |rng: &mut StdRng| {
+        let start = rng.gen_range(0..=universe);
+        start..rng.gen_range(start..=universe)
+    };
+    let elements = |rng: &mut StdRng| {
+        (0..universe)
+            .filter(|_| rng.gen_bool(density))
+            .collect::<Vec<_>>()
+    };
+
+    (0..steps)
+        .map(|_| match rng.gen_range(0..10) {
+            0..=2 => Op::Insert(rng.gen_range(0..universe)),
+            3..=4 => Op::Remove(rng.gen_range(0..universe)),
+            5 => Op::InsertRange(range(&mut rng)),
+            6 => Op::RemoveRange(range(&mut rng)),
+            7 => match rng.gen_range(0..2) {
+                0 => Op::And(elements(&mut rng)),
+                _ => Op::Or(elements(&mut rng)),
+            },
+            8 => Op::Xor(elements(&mut rng)),
+            _ => Op::Difference(elements(&mut rng)),
+        })
+        .collect()
+}
+
+/// Apply the operations to a new bitset and to the oracle, checking they agree after every step.
+///
+/// This is what `check_bitset` runs, and can be used to replay a failure.
+pub fn check_ops<BS>(ops: &[Op])
+where
+    BS: BitsetOps + BitsetRangeOps + BitwiseOps + Clone + Debug,
+    for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+{
+    let mut bitset = BS::empty();
+    let mut oracle = BTreeSet::new();
+
+    for (step, op) in ops.iter().enumerate() {
+        match op {
+            Op::Insert(i) => {
+                assert_eq!(
+                    bitset.insert(*i),
+                    oracle.insert(*i),
+                    "step {}: {:?}",
+                    step,
+                    op
+                )
+            }
+            Op::Remove(i) => {
+                bitset.remove(*i);
+                oracle.remove(i);
+            }
+            Op::InsertRange(r) => {
+                bitset.insert_range(r.clone());
+                oracle.extend(r.clone());
+            }
+            Op::RemoveRange(r) => {
+                bitset.remove_range(r.clone());
+                oracle.retain(|i| !r.contains(i));
+            }
+            Op::And(other) | Op::Or(other) | Op::Xor(other) | Op::Difference(other) => {
+                let (other_bitset, other_oracle) = build::<BS>(other);
+                check_queries(&bitset, &oracle, &other_bitset, &other_oracle, step);
+                match op {
+                    Op::And(_) => {
+                        bitset &= other_bitset;
+                        oracle = &oracle & &other_oracle;
+                    }
+                    Op::Or(_) => {
+                        bitset |= other_bitset;
+                        oracle = &oracle | &other_oracle;
+                    }
+                    Op::Xor(_) => {
+                        bitset ^= other_bitset;
+                        oracle = &oracle ^ &other_oracle;
+                    }
+                    _ => {
+                        bitset -= other_bitset;
+                        oracle = &oracle - &other_oracle;
+                    }
+                }
+            }
+        }
+        check_agrees::<BS>(&bitset, &oracle, step);
+    }
+}
+
+fn build<BS: BitsetOps>(elements: &[usize]) -> (BS, BTreeSet<usize>) {
+    let mut bitset = BS::empty();
+    for &i in elements {
+        bitset.insert(i);
+    }
+    (bitset, elements.iter().copied().collect())
+}
+
+fn check_agrees<BS>(bitset: &BS, oracle: &BTreeSet<usize>, step: usize)
+where
+    BS: BitsetOps + Debug,
+    for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+{
+    assert_eq!(
+        bitset.count(),
+        oracle.len(),
+        "step {}: count of {:?}",
+        step,
+        bitset
+    );
+    assert!(
+        bitset.into_iter().eq(oracle.iter().copied()),
+        "step {}: iterating {:?}, expected {:?}",
+        step,
+        bitset,
+        oracle
+    );
+    assert!(
+        bitset.into_iter().rev().eq(oracle.iter().rev().copied()),
+        "step {}: iterating {:?} backwards",
+        step,
+        bitset
+    );
+    // Check every index up to the last element, to catch bits that are set but not iterated over.
+    // Anything past it would have shown up in the count.
+    for i in 0..=oracle.last().copied().unwrap_or(0) {
+        assert_eq!(
+            bitset.contains(i),
+            oracle.contains(&i),
+            "step {}: contains {}",
+            step,
+            i
+        );
+    }
+}
+
+fn check_queries<BS: BitwiseOps + Debug>(
+    a: &BS,
+    a_oracle: &BTreeSet<usize>,
+    b: &BS,
+    b_oracle: &BTreeSet<usize>,
+    step: usize,
+) {
+    assert_eq!(
+        a.is_subset(b),
+        a_oracle.is_subset(b_oracle),
+        "step {}: {:?} is_subset {:?}",
+        step,
+        a,
+        b
+    );
+    assert_eq!(
+        a.is_disjoint(b),
+        a_oracle.is_disjoint(b_oracle),
+        "step {}: {:?} is_disjoint {:?}",
+        step,
+        a,
+        b
+    );
+    assert_eq!(
+        a.intersection_count(b),
+        a_oracle.intersection(b_oracle).count(),
+        "step {}: {:?} intersection_count {:?}",
+        step,
+        a,
+        b
+    );
+}
+
+fn panic_message(payload: &Box<dyn std::any::Any + Send>) -> &str {
+    payload
+        .downcast_ref::<String>()
+        .map(|s| s.as_str())
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitset::DynBitset;
+
+    #[test]
+    fn random_ops_are_reproducible() {
+        assert_eq!(random_ops(100, 50, 7), random_ops(100, 50, 7));
+        assert_ne!(random_ops(100, 50, 7), random_ops(100, 50, 8));
+        assert!(random_ops(100, 50, 7).iter().all(|op| match op {
+            Op::Insert(i) | Op::Remove(i) => *i < 100,
+            Op::InsertRange(r) | Op::RemoveRange(r) => r.end <= 100,
+            Op::And(v) | Op::Or(v) | Op::Xor(v) | Op::Difference(v) => v.iter().all(|&i| i < 100),
+        }));
+    }
+
+    #[test]
+    fn replays_a_sequence() {
+        check_ops::<DynBitset>(&[
+            Op::InsertRange(3..70),
+            Op::Remove(64),
+            Op::Xor(vec![0, 3, 200]),
+            Op::RemoveRange(60..300),
+            Op::Insert(65),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "step 0")]
+    fn notices_a_disagreement() {
+        // The oracle is missing an element, standing in for a bitset that grew one it shouldn't have.
+        let bitset = DynBitset::from_iter([1, 2]);
+        check_agrees::<DynBitset>(&bitset, &BTreeSet::from([1]), 0);
+    }
+}
@@ -0,0 +1,593 @@
+use std::ops::Bound;
+
+///- A bitset that grows as elements are inserted.
+///- This is a `Vec` of `u64` bitsets, so works like a `PackedBitset` where we don't know `N` up front.
+///- Words past the end of the vec are treated as empty, so reads never need to grow the set.
+use super::*;
+
+#[derive(Clone, Debug, Default)]
+pub struct DynBitset(Vec<U64Bitset>);
+
+impl DynBitset {
+    /// An empty bitset with room for at least `bits` elements before it needs to grow.
+    pub fn with_capacity(bits: usize) -> Self {
+        Self(vec![
+            U64Bitset::empty();
+            bits.div_ceil(U64Bitset::fixed_capacity())
+        ])
+    }
+
+    /// The number of elements that can be held without growing.
+    pub fn capacity(&self) -> usize {
+        self.0.len() * U64Bitset::fixed_capacity()
+    }
+
+    /// Remove every element, keeping the storage.
+    pub fn clear(&mut self) {
+        self.0.fill(U64Bitset::empty());
+    }
+
+    fn element_index(index: usize) -> usize {
+        index / U64Bitset::fixed_capacity()
+    }
+
+    fn bit_index(index: usize) -> usize {
+        index % U64Bitset::fixed_capacity()
+    }
+
+    // Make sure that the word at `element_index` exists.
+    fn grow_to(&mut self, element_index: usize) {
+        if element_index >= self.0.len() {
+            self.0.resize(element_index + 1, U64Bitset::empty());
+        }
+    }
+
+    // The word at `i`, or an empty word if we've not grown that far.
+    fn word(&self, i: usize) -> U64Bitset {
+        self.0.get(i).copied().unwrap_or_default()
+    }
+}
+
+impl BitsetOps for DynBitset {
+    fn empty() -> Self {
+        Self(Vec::new())
+    }
+
+    fn insert(&mut self, index: usize) -> bool {
+        let element_index = Self::element_index(index);
+        self.grow_to(element_index);
+        self.0[element_index].insert(Self::bit_index(index))
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(word) = self.0.get_mut(Self::element_index(index)) {
+            word.remove(Self::bit_index(index));
+        }
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.0
+            .get(Self::element_index(index))
+            .is_some_and(|word| word.contains(Self::bit_index(index)))
+    }
+
+    fn count(&self) -> usize {
+        self.0.iter().map(|word| word.count()).sum()
+    }
+}
+
+impl BitsetOpsUnsafe for DynBitset {
+    /// The index must be below `capacity()`, as this will not grow the bitset.
+    unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
+        self.0
+            .get_unchecked_mut(Self::element_index(index))
+            .insert(Self::bit_index(index))
+    }
+
+    unsafe fn remove_unchecked(&mut self, index: usize) {
+        self.0
+            .get_unchecked_mut(Self::element_index(index))
+            .remove(Self::bit_index(index));
+    }
+
+    unsafe fn contains_unchecked(&self, index: usize) -> bool {
+        self.0
+            .get_unchecked(Self::element_index(index))
+            .contains(Self::bit_index(index))
+    }
+}
+
+// An unbounded range runs to the current capacity, as there's no other end to pick.
+impl BitsetRangeOps for DynBitset {
+    fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => *i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => *i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.capacity(),
+        };
+        if start >= end {
+            return;
+        }
+
+        let first = Self::element_index(start);
+        let last = Self::element_index(end - 1);
+        self.grow_to(last);
+        for i in first..=last {
+            let lo = if i == first {
+                Self::bit_index(start)
+            } else {
+                0
+            };
+            let hi = if i == last {
+                Self::bit_index(end - 1) + 1
+            } else {
+                U64Bitset::fixed_capacity()
+            };
+            self.0[i].insert_range(lo..hi);
+        }
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => *i + 1,
+            Bound::Unbounded => 0,
+        };
+        // Nothing past the capacity can be set, so we never need to look further.
+        let end = match range.end_bound() {
+            Bound::Included(i) => *i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.capacity(),
+        }
+        .min(self.capacity());
+        if start >= end {
+            return;
+        }
+
+        let first = Self::element_index(start);
+        let last = Self::element_index(end - 1);
+        for i in first..=last {
+            let lo = if i == first {
+                Self::bit_index(start)
+            } else {
+                0
+            };
+            let hi = if i == last {
+                Self::bit_index(end - 1) + 1
+            } else {
+                U64Bitset::fixed_capacity()
+            };
+            self.0[i].remove_range(lo..hi);
+        }
+    }
+}
+
+// The same as the packed bitset, except that the words we haven't grown to yet are all empty.
+impl BitsetRankOps for DynBitset {
+    fn rank(&self, index: usize) -> usize {
+        let element_index = Self::element_index(index);
+        if element_index >= self.0.len() {
+            return self.count();
+        }
+
+        let before: usize = self.0[..element_index].iter().map(|w| w.count()).sum();
+        before + self.0[element_index].rank(Self::bit_index(index))
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        let mut k = k;
+        for (i, w) in self.0.iter().enumerate() {
+            let count = w.count();
+            if k < count {
+                return w.select(k).map(|b| i * U64Bitset::fixed_capacity() + b);
+            }
+            k -= count;
+        }
+        None
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        let element_index = Self::element_index(index);
+        if element_index >= self.0.len() {
+            return None;
+        }
+
+        if let Some(b) = self.0[element_index].next_set_after(Self::bit_index(index)) {
+            return Some(element_index * U64Bitset::fixed_capacity() + b);
+        }
+
+        // The first element of the following non-empty word.
+        self.0[element_index + 1..]
+            .iter()
+            .enumerate()
+            .find_map(|(i, w)| {
+                w.select(0)
+                    .map(|b| (element_index + 1 + i) * U64Bitset::fixed_capacity() + b)
+            })
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        if index == 0 {
+            return None;
+        }
+
+        // Past the end, everything is before the index, so we start from beyond the last word.
+        let element_index = Self::element_index(index);
+        let element_index = if element_index < self.0.len() {
+            if let Some(b) = self.0[element_index].prev_set_before(Self::bit_index(index)) {
+                return Some(element_index * U64Bitset::fixed_capacity() + b);
+            }
+            element_index
+        } else {
+            self.0.len()
+        };
+
+        // The last element of the preceding non-empty word.
+        self.0[..element_index]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, w)| {
+                w.prev_set_before(U64Bitset::fixed_capacity())
+                    .map(|b| i * U64Bitset::fixed_capacity() + b)
+            })
+    }
+}
+
+// Bitsets of different capacities can be combined.
+// The result is as large as it needs to be to hold the answer.
+impl BitAnd for DynBitset {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for DynBitset {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0.truncate(rhs.0.len());
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a &= b;
+        }
+    }
+}
+
+impl BitOr for DynBitset {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for DynBitset {
+    fn bitor_assign(&mut self, rhs: Self) {
+        if rhs.0.len() > self.0.len() {
+            self.0.resize(rhs.0.len(), U64Bitset::empty());
+        }
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a |= b;
+        }
+    }
+}
+
+impl BitXor for DynBitset {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: Self) -> Self::Output {
+        self ^= rhs;
+        self
+    }
+}
+
+impl BitXorAssign for DynBitset {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        if rhs.0.len() > self.0.len() {
+            self.0.resize(rhs.0.len(), U64Bitset::empty());
+        }
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a ^= b;
+        }
+    }
+}
+
+impl Sub for DynBitset {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl SubAssign for DynBitset {
+    fn sub_assign(&mut self, rhs: Self) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a -= b;
+        }
+    }
+}
+
+impl BitwiseOps for DynBitset {
+    fn is_subset(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .enumerate()
+            .all(|(i, a)| a.is_subset(&other.word(i)))
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .all(|(a, b)| a.is_disjoint(b))
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| a.intersection_count(b))
+            .sum()
+    }
+}
+
+// Trailing zero bytes are dropped, so that equal bitsets have the same bytes whatever their capacity.
+impl BitsetByteOps for DynBitset {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.0.iter().flat_map(|word| word.to_bytes()).collect();
+        let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        bytes.truncate(len);
+        bytes
+    }
+
+    // Always succeeds, as we can grow to hold anything.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes
+            .chunks(U64Bitset::fixed_capacity() / 8)
+            .map(U64Bitset::from_bytes)
+            .collect::<Option<Vec<_>>>()
+            .map(Self)
+    }
+}
+
+// Two bitsets are equal if they have the same elements, however much capacity they have.
+impl PartialEq for DynBitset {
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.0.len().max(other.0.len());
+        (0..len).all(|i| self.word(i) == other.word(i))
+    }
+}
+
+impl Eq for DynBitset {}
+
+impl Extend<usize> for DynBitset {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl FromIterator<usize> for DynBitset {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl std::fmt::Display for DynBitset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_set(f, self)
+    }
+}
+
+impl<'a> IntoIterator for &'a DynBitset {
+    // As with the packed bitset, we hide the iterator chain behind a newtype.
+    type IntoIter = DynBitsetIterator<impl DoubleEndedIterator<Item = usize> + 'a>;
+    type Item = usize;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DynBitsetIterator(self.0.iter().enumerate().flat_map(|(i, p)| {
+            let i = i * U64Bitset::fixed_capacity();
+            p.into_iter().map(move |b| i + b)
+        }))
+    }
+}
+
+/// An iterator over the elements of a dynamic bitset.
+pub struct DynBitsetIterator<I>(I);
+
+impl<I: Iterator<Item = usize>> Iterator for DynBitsetIterator<I> {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = usize>> DoubleEndedIterator for DynBitsetIterator<I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    crate::generate_tests!(test_empty_iterator, DynBitset);
+    crate::generate_tests!(test_empty_iterator_back, DynBitset);
+
+    #[test]
+    fn conformance() {
+        // Past a couple of words, so the bitset has to grow and shrink.
+        crate::bitset::conformance::check_bitset::<DynBitset>(300);
+    }
+
+    fn from_values(values: &[usize]) -> DynBitset {
+        let mut bitset = DynBitset::empty();
+        for &v in values {
+            bitset.insert(v);
+        }
+        bitset
+    }
+
+    #[test]
+    fn grows_on_insert() {
+        let mut bitset = DynBitset::empty();
+        assert_eq!(bitset.capacity(), 0);
+        assert!(!bitset.contains(1000));
+
+        assert!(bitset.insert(1000));
+        assert!(!bitset.insert(1000));
+        assert!(bitset.capacity() > 1000);
+        assert!(bitset.contains(1000));
+        assert_eq!(bitset.count(), 1);
+
+        // Removing past the end is a no-op, and doesn't grow.
+        let capacity = bitset.capacity();
+        bitset.remove(100_000);
+        assert_eq!(bitset.capacity(), capacity);
+    }
+
+    #[test]
+    fn with_capacity_does_not_need_to_grow() {
+        let mut bitset = DynBitset::with_capacity(130);
+        assert!(bitset.capacity() >= 130);
+        assert_eq!(bitset.count(), 0);
+
+        let capacity = bitset.capacity();
+        for i in 0..130 {
+            unsafe { bitset.insert_unchecked(i) };
+        }
+        assert_eq!(bitset.capacity(), capacity);
+        assert_eq!(bitset.count(), 130);
+    }
+
+    #[test]
+    fn equality_ignores_capacity() {
+        let mut a = DynBitset::with_capacity(1000);
+        let b = DynBitset::empty();
+        assert_eq!(a, b);
+        a.insert(3);
+        assert_ne!(a, b);
+        assert_eq!(a, from_values(&[3]));
+    }
+
+    #[test]
+    fn iterates_both_ways() {
+        let values = [0, 63, 64, 65, 200, 1000];
+        let bitset = from_values(&values);
+        assert!(bitset.into_iter().eq(values.iter().copied()));
+        assert!(bitset.into_iter().rev().eq(values.iter().rev().copied()));
+    }
+
+    #[test]
+    fn insert_and_remove_ranges() {
+        let mut bitset = DynBitset::empty();
+        bitset.insert_range(60..200);
+        assert_eq!(bitset.count(), 140);
+        assert!(bitset.into_iter().eq(60..200));
+
+        bitset.remove_range(62..=197);
+        assert!(bitset.into_iter().eq([60, 61, 198, 199]));
+
+        // Unbounded ranges run to the capacity.
+        bitset.remove_range(100..);
+        assert!(bitset.into_iter().eq([60, 61]));
+        bitset.insert_range(..);
+        assert_eq!(bitset.count(), bitset.capacity());
+    }
+
+    #[test]
+    fn collection_constructors() {
+        let values = [5, 1, 9, 1000];
+        let collected: DynBitset = values.into_iter().collect();
+        assert_eq!(collected, from_values(&values));
+        assert_eq!(DynBitset::from_slice(&values), collected);
+        assert_eq!(collected.to_string(), "{1, 5, 9, 1000}");
+
+        let mut extended = DynBitset::from_range(1..=5);
+        extended.extend([200, 3]);
+        assert_eq!(extended, from_values(&[1, 2, 3, 4, 5, 200]));
+        assert_eq!(DynBitset::empty().to_string(), "{}");
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let values = [0, 7, 8, 63, 64, 65, 200, 1000];
+        let bitset = from_values(&values);
+        let bytes = bitset.to_bytes();
+        assert_eq!(bytes.len(), 1000 / 8 + 1);
+        assert_eq!(&bytes[..2], &[0b1000_0001, 0b0000_0001]);
+        assert_eq!(DynBitset::from_bytes(&bytes), Some(bitset.clone()));
+
+        // Spare capacity doesn't show up in the bytes.
+        let mut roomy = DynBitset::with_capacity(10_000);
+        roomy |= bitset;
+        assert_eq!(roomy.to_bytes(), bytes);
+        assert!(DynBitset::empty().to_bytes().is_empty());
+    }
+
+    #[test]
+    fn bitwise_ops_on_different_sizes() {
+        let a = from_values(&[1, 70, 500]);
+        let b = from_values(&[1, 2, 70]);
+
+        assert_eq!(a.clone() & b.clone(), from_values(&[1, 70]));
+        assert_eq!(a.clone() | b.clone(), from_values(&[1, 2, 70, 500]));
+        assert_eq!(b.clone() | a.clone(), from_values(&[1, 2, 70, 500]));
+        assert_eq!(a.clone() ^ b.clone(), from_values(&[2, 500]));
+        assert_eq!(a.clone() - b.clone(), from_values(&[500]));
+        assert_eq!(b.clone() - a.clone(), from_values(&[2]));
+
+        assert_eq!(a.intersection_count(&b), 2);
+        assert!(from_values(&[1, 70]).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert!(a.is_superset(&from_values(&[500])));
+        assert!(a.is_disjoint(&from_values(&[2, 3, 4000])));
+    }
+
+    #[test]
+    fn rank_and_neighbours() {
+        let values = [3, 63, 64, 130, 500];
+        let bitset = from_values(&values);
+
+        assert_eq!(bitset.rank(0), 0);
+        assert_eq!(bitset.rank(64), 2);
+        assert_eq!(bitset.rank(65), 3);
+        assert_eq!(bitset.rank(10_000), 5);
+        for (k, &v) in values.iter().enumerate() {
+            assert_eq!(bitset.select(k), Some(v));
+        }
+        assert_eq!(bitset.select(5), None);
+
+        assert_eq!(bitset.next_set_after(3), Some(63));
+        assert_eq!(bitset.next_set_after(64), Some(130));
+        assert_eq!(bitset.next_set_after(500), None);
+        assert_eq!(bitset.next_set_after(10_000), None);
+        assert_eq!(bitset.next_set_after(usize::MAX), None);
+
+        assert_eq!(bitset.prev_set_before(3), None);
+        assert_eq!(bitset.prev_set_before(130), Some(64));
+        assert_eq!(bitset.prev_set_before(499), Some(130));
+        assert_eq!(bitset.prev_set_before(10_000), Some(500));
+
+        // Growing the storage with nothing in it doesn't change the answers.
+        let mut grown = DynBitset::with_capacity(1000);
+        grown.insert(130);
+        assert_eq!(grown.next_set_after(3), Some(130));
+        assert_eq!(grown.prev_set_before(900), Some(130));
+        assert_eq!(grown.rank(900), 1);
+    }
+}
@@ -0,0 +1,665 @@
+///- A bitset of exactly `BITS` elements, that picks its own storage.
+///- Sizing a `PackedBitset` by hand means working out how many words are needed, and remembering that
+///- the last word has some bits left over, which `full()`, `count()` and complement will happily include.
+///- Here the bit count is all that's given. It goes in a single `PrimitiveBitset` if it fits in a `u128`,
+///- or a `PackedBitset` of `u64` words if not. The bits past `BITS` are always kept clear,
+///- so everything that counts or iterates sees exactly `BITS` elements.
+use super::*;
+
+/// The width of the primitive integer that holds `bits` bits, or 0 if we need packed words instead.
+pub const fn primitive_bits(bits: usize) -> usize {
+    match bits {
+        0..=8 => 8,
+        9..=16 => 16,
+        17..=32 => 32,
+        33..=64 => 64,
+        65..=128 => 128,
+        _ => 0,
+    }
+}
+
+/// The number of `u64` words that hold `bits` bits, or 0 if they fit in a primitive.
+pub const fn packed_words(bits: usize) -> usize {
+    if primitive_bits(bits) == 0 {
+        bits.div_ceil(64)
+    } else {
+        0
+    }
+}
+
+/// The storage picked for a bit count.
+///
+/// There's no way to choose a type from a const expression directly,
+/// so `Select` is indexed by `primitive_bits` and `packed_words`, and implements this for each choice.
+pub trait Layout {
+    type Storage: BitsetOps
+        + BitsetRangeOps
+        + BitsetRankOps
+        + BitwiseOps
+        + ComplementOps
+        + ShiftOps
+        + BitsetOpsUnsafe
+        + BitsetByteOps
+        + FixedSizeBitset
+        + FullBitset
+        + Copy
+        + Default
+        + Eq
+        + fmt::Debug;
+}
+
+pub struct Select<const PRIMITIVE_BITS: usize, const WORDS: usize>;
+
+impl Layout for Select<8, 0> {
+    type Storage = U8Bitset;
+}
+
+impl Layout for Select<16, 0> {
+    type Storage = U16Bitset;
+}
+
+impl Layout for Select<32, 0> {
+    type Storage = U32Bitset;
+}
+
+impl Layout for Select<64, 0> {
+    type Storage = U64Bitset;
+}
+
+impl Layout for Select<128, 0> {
+    type Storage = U128Bitset;
+}
+
+impl<const WORDS: usize> Layout for Select<0, WORDS> {
+    type Storage = PackedU64Bitset<WORDS>;
+}
+
+type Storage<const BITS: usize> =
+    <Select<{ primitive_bits(BITS) }, { packed_words(BITS) }> as Layout>::Storage;
+
+/// A bitset with room for exactly `BITS` elements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExactBitset<const BITS: usize>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    bits: Storage<BITS>,
+}
+
+impl<const BITS: usize> ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    // Clear the bits past the end, after anything that might have set them.
+    fn masked(mut bits: Storage<BITS>) -> Self {
+        bits.remove_range(BITS..);
+        Self { bits }
+    }
+}
+
+impl<const BITS: usize> FixedSizeBitset for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn fixed_capacity() -> usize {
+        BITS
+    }
+}
+
+impl<const BITS: usize> FullBitset for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn full() -> Self {
+        Self::masked(Storage::<BITS>::full())
+    }
+}
+
+impl<const BITS: usize> BitsetOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn empty() -> Self {
+        Self {
+            bits: Storage::<BITS>::empty(),
+        }
+    }
+
+    fn insert(&mut self, index: usize) -> bool {
+        check_index(index, BITS);
+        self.bits.insert(index)
+    }
+
+    fn remove(&mut self, index: usize) {
+        check_index(index, BITS);
+        self.bits.remove(index)
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        check_index(index, BITS);
+        self.bits.contains(index)
+    }
+
+    // Nothing past `BITS` is ever set, so this is already exact.
+    fn count(&self) -> usize {
+        self.bits.count()
+    }
+}
+
+impl<const BITS: usize> BitsetOpsUnsafe for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
+        self.bits.insert_unchecked(index)
+    }
+
+    unsafe fn remove_unchecked(&mut self, index: usize) {
+        self.bits.remove_unchecked(index)
+    }
+
+    unsafe fn contains_unchecked(&self, index: usize) -> bool {
+        self.bits.contains_unchecked(index)
+    }
+}
+
+impl<const BITS: usize> BitsetTryOps for ExactBitset<BITS> where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout
+{
+}
+
+impl<const BITS: usize> BitsetRangeOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    // An unbounded range has to stop at `BITS`, not at the end of the storage.
+    fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, BITS);
+        let end = match range.end_bound() {
+            Bound::Unbounded => Bound::Excluded(BITS),
+            end => end.cloned(),
+        };
+        self.bits.insert_range((range.start_bound().cloned(), end))
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, BITS);
+        self.bits.remove_range(range)
+    }
+}
+
+impl<const BITS: usize> BitsetRankOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn rank(&self, index: usize) -> usize {
+        self.bits.rank(index)
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        self.bits.select(k)
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        self.bits.next_set_after(index)
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        self.bits.prev_set_before(index)
+    }
+}
+
+impl<const BITS: usize> BitAnd for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits & rhs.bits,
+        }
+    }
+}
+
+impl<const BITS: usize> BitAndAssign for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.bits &= rhs.bits;
+    }
+}
+
+impl<const BITS: usize> BitOr for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits | rhs.bits,
+        }
+    }
+}
+
+impl<const BITS: usize> BitOrAssign for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.bits |= rhs.bits;
+    }
+}
+
+impl<const BITS: usize> BitXor for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits ^ rhs.bits,
+        }
+    }
+}
+
+impl<const BITS: usize> BitXorAssign for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.bits ^= rhs.bits;
+    }
+}
+
+impl<const BITS: usize> Sub for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits - rhs.bits,
+        }
+    }
+}
+
+impl<const BITS: usize> SubAssign for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn sub_assign(&mut self, rhs: Self) {
+        self.bits -= rhs.bits;
+    }
+}
+
+impl<const BITS: usize> Not for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    // This is the one that needs the mask, as every bit of padding would be switched on.
+    fn not(self) -> Self::Output {
+        Self::masked(!self.bits)
+    }
+}
+
+impl<const BITS: usize> BitwiseOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn is_subset(&self, other: &Self) -> bool {
+        self.bits.is_subset(&other.bits)
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.bits.is_disjoint(&other.bits)
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.bits.intersection_count(&other.bits)
+    }
+}
+
+impl<const BITS: usize> ComplementOps for ExactBitset<BITS> where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout
+{
+}
+
+impl<const BITS: usize> Shl<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    // Shifting left can push elements into the padding, rather than off the end.
+    fn shl(self, n: usize) -> Self::Output {
+        Self::masked(self.bits << n)
+    }
+}
+
+impl<const BITS: usize> ShlAssign<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn shl_assign(&mut self, n: usize) {
+        *self = *self << n;
+    }
+}
+
+impl<const BITS: usize> Shr<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Output = Self;
+
+    fn shr(self, n: usize) -> Self::Output {
+        Self {
+            bits: self.bits >> n,
+        }
+    }
+}
+
+impl<const BITS: usize> ShrAssign<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn shr_assign(&mut self, n: usize) {
+        self.bits >>= n;
+    }
+}
+
+impl<const BITS: usize> ShiftOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    // The storage would rotate through the padding, so we build the rotations out of shifts by `BITS`.
+    fn rotate_left(self, n: usize) -> Self {
+        if BITS == 0 || n.is_multiple_of(BITS) {
+            return self;
+        }
+        let n = n % BITS;
+        (self << n) | (self >> (BITS - n))
+    }
+
+    fn rotate_right(self, n: usize) -> Self {
+        if BITS == 0 || n.is_multiple_of(BITS) {
+            return self;
+        }
+        let n = n % BITS;
+        (self >> n) | (self << (BITS - n))
+    }
+}
+
+impl<const BITS: usize> BitsetByteOps for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    // Only as many bytes as it takes to hold `BITS` bits, rather than the whole of the storage.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bits.to_bytes();
+        bytes.truncate(BITS.div_ceil(8));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bits = Storage::<BITS>::from_bytes(bytes)?;
+        let bitset = Self::masked(bits);
+        (bitset.bits == bits).then_some(bitset)
+    }
+}
+
+impl<const BITS: usize> Extend<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<const BITS: usize> FromIterator<usize> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<const BITS: usize> fmt::Display for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_set(f, self)
+    }
+}
+
+/// Iterates over the elements of an `ExactBitset`, from either end.
+///
+/// The storage types all have different iterators, so we walk with the rank operations they have in common.
+pub struct ExactBitsetIterator<const BITS: usize>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    bitset: ExactBitset<BITS>,
+    // The elements left to yield are those in `front..back`.
+    front: usize,
+    back: usize,
+}
+
+impl<const BITS: usize> Iterator for ExactBitsetIterator<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let next = if self.bitset.bits.contains(self.front) {
+            Some(self.front)
+        } else {
+            self.bitset.bits.next_set_after(self.front)
+        };
+        match next {
+            Some(i) if i < self.back => {
+                self.front = i + 1;
+                Some(i)
+            }
+            _ => {
+                self.front = self.back;
+                None
+            }
+        }
+    }
+}
+
+impl<const BITS: usize> DoubleEndedIterator for ExactBitsetIterator<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        match self.bitset.bits.prev_set_before(self.back) {
+            Some(i) if i >= self.front => {
+                self.back = i;
+                Some(i)
+            }
+            _ => {
+                self.back = self.front;
+                None
+            }
+        }
+    }
+}
+
+impl<const BITS: usize> IntoIterator for &ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    type IntoIter = ExactBitsetIterator<BITS>;
+    type Item = usize;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ExactBitsetIterator {
+            bitset: *self,
+            front: 0,
+            back: BITS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    // Padded within a u16, a whole word with no padding, padded within a u128, and padded packed words.
+    type Exact10 = ExactBitset<10>;
+    type Exact64 = ExactBitset<64>;
+    type Exact100 = ExactBitset<100>;
+    type Exact130 = ExactBitset<130>;
+
+    crate::generate_tests!(test_empty, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_full, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_set_get, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_unset, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_set_range, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_set_unset_get, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_unset_range, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_set_all, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_bitwise_and, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_bitwise_or, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_bitwise_xor, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_difference, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_complement, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_subset_superset, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_disjoint, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(
+        test_intersection_count,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(test_rank, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_select, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_next_set_after, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_prev_set_before, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_shift_left, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_shift_right, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_rotate, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_empty_iterator, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(
+        test_empty_iterator_back,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_set_one_bit_iterator,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_one_bit_iterator_back,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator_back,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(test_from_iterator, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(
+        test_from_iterator_out_of_range,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(test_from_range, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_display, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(test_try_ops, Exact10, Exact64, Exact100, Exact130);
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(test_conformance, Exact10, Exact64, Exact100, Exact130);
+    // The byte tests expect the capacity to be a whole number of bytes.
+    crate::generate_tests!(test_bytes_round_trip, Exact64);
+    crate::generate_tests!(test_bytes_padding, Exact64);
+    crate::generate_tests!(
+        test_padding_stays_clear,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+    crate::generate_tests!(
+        test_bytes_cover_only_the_bits,
+        Exact10,
+        Exact64,
+        Exact100,
+        Exact130
+    );
+
+    // The storage has room past `BITS`, so the range has to be checked against `BITS` rather than left to it.
+    #[test]
+    #[should_panic(expected = "Index 14 is out of range for a bitset with capacity 10")]
+    fn remove_range_past_the_end_panics() {
+        Exact10::full().remove_range(5..15);
+    }
+
+    #[test]
+    fn picks_the_smallest_storage() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<ExactBitset<1>>(), 1);
+        assert_eq!(size_of::<ExactBitset<8>>(), 1);
+        assert_eq!(size_of::<ExactBitset<9>>(), 2);
+        assert_eq!(size_of::<ExactBitset<33>>(), 8);
+        assert_eq!(size_of::<ExactBitset<128>>(), 16);
+        assert_eq!(size_of::<ExactBitset<129>>(), 24);
+        assert_eq!(size_of::<ExactBitset<130>>(), 24);
+        assert_eq!(size_of::<ExactBitset<1024>>(), 128);
+    }
+}
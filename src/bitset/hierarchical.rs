@@ -0,0 +1,446 @@
+///- A bitset with a second, smaller bitset summarising which of its words are not empty.
+///- For a mostly empty set, iteration and the first/last queries only visit the words that have elements,
+///- rather than scanning every word as a `PackedBitset` does.
+///- The price is that every update also has to keep the summary in step.
+///- The summary's word count `S` is a second parameter, as working it out from `N` would need generic_const_exprs,
+///- so it's checked against `N` when a bitset is made instead.
+use std::ops::Bound;
+
+use super::*;
+
+/// The number of `u64` words needed for the summary of `words` words, which is what `S` has to be.
+pub const fn summary_words(words: usize) -> usize {
+    words.div_ceil(64)
+}
+
+/// A bitset of `N` `u64` words, with a summary bit for each word held in `S` more words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HierarchicalBitset<const N: usize, const S: usize> {
+    words: PackedU64Bitset<N>,
+    // Bit `i` is set when word `i` is not empty.
+    summary: PackedU64Bitset<S>,
+}
+
+impl<const N: usize, const S: usize> HierarchicalBitset<N, S> {
+    const WORD_BITS: usize = u64::BITS as usize;
+
+    // Every constructor looks at this, so a mismatched `S` fails to compile.
+    const SUMMARY_FITS: () = assert!(
+        S == summary_words(N),
+        "a HierarchicalBitset<N, S> needs S = summary_words(N)"
+    );
+
+    /// True if there are no elements, without looking at the words.
+    pub fn is_empty(&self) -> bool {
+        self.summary.words().iter().all(|s| s.bits == 0)
+    }
+
+    /// The smallest element.
+    pub fn first(&self) -> Option<usize> {
+        let w = self.summary.select(0)?;
+        self.word(w).select(0).map(|b| w * Self::WORD_BITS + b)
+    }
+
+    /// The largest element.
+    pub fn last(&self) -> Option<usize> {
+        let w = self.summary.prev_set_before(N)?;
+        self.word(w)
+            .prev_set_before(Self::WORD_BITS)
+            .map(|b| w * Self::WORD_BITS + b)
+    }
+
+    fn word(&self, w: usize) -> &U64Bitset {
+        &self.words.words()[w]
+    }
+
+    // The indices of the words that have elements, in order.
+    fn occupied(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.summary.into_iter().filter(|&w| w < N)
+    }
+
+    // Bring the summary up to date for the words in `first..=last`.
+    fn resummarise(&mut self, first: usize, last: usize) {
+        for w in first..=last.min(N - 1) {
+            if self.word(w).count() > 0 {
+                self.summary.insert(w);
+            } else {
+                self.summary.remove(w);
+            }
+        }
+    }
+
+    // The words that a range touches, or `None` if it is empty.
+    fn word_span<R: RangeBounds<usize>>(range: &R) -> Option<(usize, usize)> {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => *i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => *i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => Self::fixed_capacity(),
+        };
+        (start < end).then(|| (start / Self::WORD_BITS, (end - 1) / Self::WORD_BITS))
+    }
+}
+
+impl<const N: usize, const S: usize> FixedSizeBitset for HierarchicalBitset<N, S> {
+    fn fixed_capacity() -> usize {
+        PackedU64Bitset::<N>::fixed_capacity()
+    }
+}
+
+impl<const N: usize, const S: usize> FullBitset for HierarchicalBitset<N, S> {
+    fn full() -> Self {
+        let () = Self::SUMMARY_FITS;
+        let mut summary = PackedU64Bitset::empty();
+        summary.insert_range(0..N);
+        Self {
+            words: PackedU64Bitset::full(),
+            summary,
+        }
+    }
+}
+
+impl<const N: usize, const S: usize> BitsetOps for HierarchicalBitset<N, S> {
+    fn empty() -> Self {
+        let () = Self::SUMMARY_FITS;
+        Self {
+            words: PackedU64Bitset::empty(),
+            summary: PackedU64Bitset::empty(),
+        }
+    }
+
+    fn insert(&mut self, index: usize) -> bool {
+        let inserted = self.words.insert(index);
+        self.summary.insert(index / Self::WORD_BITS);
+        inserted
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.words.remove(index);
+        let w = index / Self::WORD_BITS;
+        if self.word(w).count() == 0 {
+            self.summary.remove(w);
+        }
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.words.contains(index)
+    }
+
+    fn count(&self) -> usize {
+        self.occupied().map(|w| self.word(w).count()).sum()
+    }
+}
+
+impl<const N: usize, const S: usize> BitsetTryOps for HierarchicalBitset<N, S> {}
+
+// The packed bitset does the work, and then we fix up the summary for the words it touched.
+impl<const N: usize, const S: usize> BitsetRangeOps for HierarchicalBitset<N, S> {
+    fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let span = Self::word_span(&range);
+        self.words.insert_range(range);
+        if let Some((first, last)) = span {
+            self.resummarise(first, last);
+        }
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let span = Self::word_span(&range);
+        self.words.remove_range(range);
+        if let Some((first, last)) = span {
+            self.resummarise(first, last);
+        }
+    }
+
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps,
+    {
+        check_range(&range, Self::fixed_capacity());
+        let mut bitset = Self::empty();
+        bitset.insert_range(range);
+        bitset
+    }
+}
+
+// The queries skip over empty words using the summary, so never need to look at them.
+impl<const N: usize, const S: usize> BitsetRankOps for HierarchicalBitset<N, S> {
+    fn rank(&self, index: usize) -> usize {
+        let w = index / Self::WORD_BITS;
+        let before: usize = self
+            .occupied()
+            .take_while(|&o| o < w)
+            .map(|o| self.word(o).count())
+            .sum();
+        if w < N {
+            before + self.word(w).rank(index % Self::WORD_BITS)
+        } else {
+            before
+        }
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        let mut k = k;
+        for w in self.occupied() {
+            let count = self.word(w).count();
+            if k < count {
+                return self.word(w).select(k).map(|b| w * Self::WORD_BITS + b);
+            }
+            k -= count;
+        }
+        None
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        if index >= Self::fixed_capacity().saturating_sub(1) {
+            return None;
+        }
+        let w = index / Self::WORD_BITS;
+        if let Some(b) = self.word(w).next_set_after(index % Self::WORD_BITS) {
+            return Some(w * Self::WORD_BITS + b);
+        }
+        let next = self.summary.next_set_after(w).filter(|&next| next < N)?;
+        self.word(next)
+            .select(0)
+            .map(|b| next * Self::WORD_BITS + b)
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        if index >= Self::fixed_capacity() {
+            return self.last();
+        }
+        let w = index / Self::WORD_BITS;
+        if let Some(b) = self.word(w).prev_set_before(index % Self::WORD_BITS) {
+            return Some(w * Self::WORD_BITS + b);
+        }
+        let prev = self.summary.prev_set_before(w)?;
+        self.word(prev)
+            .prev_set_before(Self::WORD_BITS)
+            .map(|b| prev * Self::WORD_BITS + b)
+    }
+}
+
+// The summary can be rebuilt from the words, so only the words are stored.
+impl<const N: usize, const S: usize> BitsetByteOps for HierarchicalBitset<N, S> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.words.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let () = Self::SUMMARY_FITS;
+        let mut bitset = Self {
+            words: PackedU64Bitset::from_bytes(bytes)?,
+            summary: PackedU64Bitset::empty(),
+        };
+        bitset.resummarise(0, N - 1);
+        Some(bitset)
+    }
+}
+
+impl<const N: usize, const S: usize> Default for HierarchicalBitset<N, S> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const N: usize, const S: usize> Extend<usize> for HierarchicalBitset<N, S> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<const N: usize, const S: usize> FromIterator<usize> for HierarchicalBitset<N, S> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<const N: usize, const S: usize> fmt::Display for HierarchicalBitset<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_set(f, self)
+    }
+}
+
+impl<'a, const N: usize, const S: usize> IntoIterator for &'a HierarchicalBitset<N, S> {
+    // As with the packed bitset, we hide the iterator chain behind a newtype.
+    type IntoIter = HierarchicalBitsetIterator<impl DoubleEndedIterator<Item = usize> + 'a>;
+    type Item = usize;
+
+    fn into_iter(self) -> Self::IntoIter {
+        HierarchicalBitsetIterator(self.occupied().flat_map(move |w| {
+            let i = w * HierarchicalBitset::<N, S>::WORD_BITS;
+            self.word(w).into_iter().map(move |b| i + b)
+        }))
+    }
+}
+
+/// An iterator over the elements of a hierarchical bitset, visiting only the words that have any.
+pub struct HierarchicalBitsetIterator<I>(I);
+
+impl<I: Iterator<Item = usize>> Iterator for HierarchicalBitsetIterator<I> {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = usize>> DoubleEndedIterator for HierarchicalBitsetIterator<I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    // One word, a summary that is only partly used, and a summary of more than one word.
+    type Hierarchical1 = HierarchicalBitset<1, 1>;
+    type Hierarchical5 = HierarchicalBitset<5, 1>;
+    type Hierarchical70 = HierarchicalBitset<70, { summary_words(70) }>;
+
+    crate::generate_tests!(test_empty, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_full, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_set_get, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_unset, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_set_range, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_set_unset_get, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_unset_range, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_set_all, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_try_ops, Hierarchical1, Hierarchical5, Hierarchical70);
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_from_iterator,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_from_range,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(test_display, Hierarchical1, Hierarchical5, Hierarchical70);
+    crate::generate_tests!(
+        test_bytes_round_trip,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(test_rank, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_select, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_next_set_after, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_prev_set_before, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(
+        test_empty_iterator,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_empty_iterator_back,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_set_one_bit_iterator,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(
+        test_one_bit_iterator_back,
+        Hierarchical1,
+        Hierarchical5,
+        Hierarchical70
+    );
+    crate::generate_tests!(test_set_two_bit_iterator, Hierarchical1, Hierarchical5);
+    crate::generate_tests!(test_set_two_bit_iterator_back, Hierarchical1, Hierarchical5);
+
+    #[test]
+    fn summary_tracks_occupied_words() {
+        let mut bitset = Hierarchical70::empty();
+        assert!(bitset.is_empty());
+        assert_eq!(bitset.first(), None);
+        assert_eq!(bitset.last(), None);
+
+        bitset.insert(5);
+        bitset.insert(64 * 65 + 3);
+        bitset.insert(64 * 69 + 63);
+        assert!(!bitset.is_empty());
+        assert!(bitset.occupied().eq([0, 65, 69]));
+        assert_eq!(bitset.first(), Some(5));
+        assert_eq!(bitset.last(), Some(64 * 69 + 63));
+        assert!(bitset.into_iter().eq([5, 64 * 65 + 3, 64 * 69 + 63]));
+        assert!(bitset.into_iter().rev().eq([64 * 69 + 63, 64 * 65 + 3, 5]));
+
+        // Emptying a word drops it from the summary.
+        bitset.remove(5);
+        assert!(bitset.occupied().eq([65, 69]));
+        assert_eq!(bitset.first(), Some(64 * 65 + 3));
+        assert_eq!(bitset.next_set_after(64 * 65 + 3), Some(64 * 69 + 63));
+        assert_eq!(bitset.prev_set_before(64 * 69), Some(64 * 65 + 3));
+        bitset.remove(64 * 65 + 3);
+        bitset.remove(64 * 69 + 63);
+        assert!(bitset.is_empty());
+    }
+
+    #[test]
+    fn queries_cross_summary_words() {
+        // Words 63 and 64 have their summary bits in different summary words.
+        let mut bitset = Hierarchical70::empty();
+        bitset.insert(64 * 63 + 1);
+        bitset.insert(64 * 64 + 2);
+        assert!(bitset.occupied().eq([63, 64]));
+        assert_eq!(bitset.next_set_after(64 * 63 + 1), Some(64 * 64 + 2));
+        assert_eq!(bitset.prev_set_before(64 * 64), Some(64 * 63 + 1));
+        assert_eq!(bitset.select(1), Some(64 * 64 + 2));
+        assert_eq!(bitset.rank(64 * 64 + 2), 1);
+
+        // Emptying word 63 leaves only the second summary word with anything in it.
+        bitset.remove(64 * 63 + 1);
+        assert!(bitset.occupied().eq([64]));
+        assert_eq!(bitset.first(), Some(64 * 64 + 2));
+        assert_eq!(bitset.next_set_after(0), Some(64 * 64 + 2));
+        assert_eq!(bitset.prev_set_before(64 * 64), None);
+        bitset.remove(64 * 64 + 2);
+        assert!(bitset.is_empty());
+    }
+
+    #[test]
+    fn ranges_keep_the_summary_in_step() {
+        let mut bitset = Hierarchical70::empty();
+        bitset.insert_range(60..200);
+        assert!(bitset.occupied().eq([0, 1, 2, 3]));
+        bitset.remove_range(64..192);
+        assert!(bitset.occupied().eq([0, 3]));
+        assert!(bitset.into_iter().eq((60..64).chain(192..200)));
+
+        bitset.insert_range(..);
+        assert_eq!(bitset.occupied().count(), 70);
+        assert_eq!(bitset, Hierarchical70::full());
+        bitset.remove_range(..);
+        assert!(bitset.is_empty());
+    }
+}
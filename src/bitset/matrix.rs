@@ -0,0 +1,386 @@
+///- A two-dimensional grid of bits, stored as an array of row bitsets.
+///- This is the layout that day 6 uses for the lab map, pulled out so that other grid puzzles can share it.
+///- Row `r`, column `c` is bit `c` of row `r`, so the number of columns is the capacity of the row bitset.
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitMatrix<Row, const R: usize> {
+    rows: [Row; R],
+}
+
+impl<Row: BitsetOps + FixedSizeBitset + Copy, const R: usize> BitMatrix<Row, R> {
+    pub fn empty() -> Self {
+        Self {
+            rows: [Row::empty(); R],
+        }
+    }
+
+    /// The number of rows.
+    pub fn row_count() -> usize {
+        R
+    }
+
+    /// The number of columns.
+    pub fn column_count() -> usize {
+        Row::fixed_capacity()
+    }
+
+    /// Set the cell, returning true if it was not already set.
+    pub fn insert(&mut self, row: usize, col: usize) -> bool {
+        self.rows[row].insert(col)
+    }
+
+    pub fn remove(&mut self, row: usize, col: usize) {
+        self.rows[row].remove(col);
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        self.rows[row].contains(col)
+    }
+
+    /// The number of cells that are set.
+    pub fn count(&self) -> usize {
+        self.rows.iter().map(|row| row.count()).sum()
+    }
+
+    pub fn row(&self, row: usize) -> &Row {
+        &self.rows[row]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut Row {
+        &mut self.rows[row]
+    }
+
+    /// The rows, from top to bottom.
+    pub fn rows(&self) -> std::slice::Iter<'_, Row> {
+        self.rows.iter()
+    }
+
+    /// The rows that have `col` set, from top to bottom.
+    pub fn column(&self, col: usize) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(move |(_, row)| row.contains(col))
+            .map(|(r, _)| r)
+    }
+
+    /// Move every cell `n` rows up, dropping those that fall off the top.
+    pub fn shift_up(&self, n: usize) -> Self {
+        let mut result = Self::empty();
+        if n < R {
+            result.rows[..R - n].copy_from_slice(&self.rows[n..]);
+        }
+        result
+    }
+
+    /// Move every cell `n` rows down, dropping those that fall off the bottom.
+    pub fn shift_down(&self, n: usize) -> Self {
+        let mut result = Self::empty();
+        if n < R {
+            result.rows[n..].copy_from_slice(&self.rows[..R - n]);
+        }
+        result
+    }
+}
+
+impl<Row, const R: usize> BitMatrix<Row, R>
+where
+    Row: BitsetOps + FixedSizeBitset + Copy,
+    for<'a> &'a Row: IntoIterator<Item = usize>,
+{
+    /// Every cell that is set, as `(row, col)`, in reading order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.into_iter().map(move |c| (r, c)))
+    }
+
+    /// Swap rows and columns, so that cell `(r, c)` moves to `(c, r)`.
+    ///
+    /// The result can have a different row type and row count.
+    /// Panics if the new rows are too short to hold all `R` rows,
+    /// and cells in columns past `C` are dropped.
+    pub fn transpose<T: BitsetOps + FixedSizeBitset + Copy, const C: usize>(
+        &self,
+    ) -> BitMatrix<T, C> {
+        assert!(
+            T::fixed_capacity() >= R,
+            "Transposed rows hold {} columns but {} are needed",
+            T::fixed_capacity(),
+            R
+        );
+        let mut result = BitMatrix::<T, C>::empty();
+        for (r, c) in self.iter().filter(|&(_, c)| c < C) {
+            result.insert(c, r);
+        }
+        result
+    }
+}
+
+// Columns are bits within a row, so moving sideways is a shift of every row.
+// Note that bit `c` shifted left moves to `c + n`, which is a step to the right on the grid.
+impl<Row: BitsetOps + FixedSizeBitset + ShiftOps + Copy, const R: usize> BitMatrix<Row, R> {
+    /// Move every cell `n` columns left, dropping those that fall off the left edge.
+    pub fn shift_left(&self, n: usize) -> Self {
+        Self {
+            rows: self.rows.map(|row| row >> n),
+        }
+    }
+
+    /// Move every cell `n` columns right, dropping those that fall off the right edge.
+    pub fn shift_right(&self, n: usize) -> Self {
+        Self {
+            rows: self.rows.map(|row| row << n),
+        }
+    }
+}
+
+impl<Row: BitsetOps + FixedSizeBitset + Copy, const R: usize> Default for BitMatrix<Row, R> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+// The bitwise operators work a row at a time.
+impl<Row: BitAndAssign + Copy, const R: usize> BitAnd for BitMatrix<Row, R> {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self &= rhs;
+        self
+    }
+}
+
+impl<Row: BitAndAssign + Copy, const R: usize> BitAndAssign for BitMatrix<Row, R> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        for (a, b) in self.rows.iter_mut().zip(rhs.rows) {
+            *a &= b;
+        }
+    }
+}
+
+impl<Row: BitOrAssign + Copy, const R: usize> BitOr for BitMatrix<Row, R> {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self |= rhs;
+        self
+    }
+}
+
+impl<Row: BitOrAssign + Copy, const R: usize> BitOrAssign for BitMatrix<Row, R> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (a, b) in self.rows.iter_mut().zip(rhs.rows) {
+            *a |= b;
+        }
+    }
+}
+
+impl<Row: BitXorAssign + Copy, const R: usize> BitXor for BitMatrix<Row, R> {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: Self) -> Self::Output {
+        self ^= rhs;
+        self
+    }
+}
+
+impl<Row: BitXorAssign + Copy, const R: usize> BitXorAssign for BitMatrix<Row, R> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        for (a, b) in self.rows.iter_mut().zip(rhs.rows) {
+            *a ^= b;
+        }
+    }
+}
+
+impl<Row: SubAssign + Copy, const R: usize> Sub for BitMatrix<Row, R> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl<Row: SubAssign + Copy, const R: usize> SubAssign for BitMatrix<Row, R> {
+    fn sub_assign(&mut self, rhs: Self) {
+        for (a, b) in self.rows.iter_mut().zip(rhs.rows) {
+            *a -= b;
+        }
+    }
+}
+
+impl<Row: Not<Output = Row> + Copy, const R: usize> Not for BitMatrix<Row, R> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self {
+            rows: self.rows.map(|row| !row),
+        }
+    }
+}
+
+impl<Row: BitwiseOps + Copy, const R: usize> BitwiseOps for BitMatrix<Row, R> {
+    fn is_subset(&self, other: &Self) -> bool {
+        self.rows
+            .iter()
+            .zip(other.rows.iter())
+            .all(|(a, b)| a.is_subset(b))
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.rows
+            .iter()
+            .zip(other.rows.iter())
+            .all(|(a, b)| a.is_disjoint(b))
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.rows
+            .iter()
+            .zip(other.rows.iter())
+            .map(|(a, b)| a.intersection_count(b))
+            .sum()
+    }
+}
+
+impl<Row: Not<Output = Row> + Copy, const R: usize> ComplementOps for BitMatrix<Row, R> {}
+
+// Rows are written out top to bottom, each taking the same number of bytes, which is the row's
+// capacity rounded up to a whole byte.
+impl<Row, const R: usize> BitsetByteOps for BitMatrix<Row, R>
+where
+    Row: BitsetOps + FixedSizeBitset + BitsetByteOps + Copy,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        self.rows.iter().flat_map(|row| row.to_bytes()).collect()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut result = Self::empty();
+        let mut chunks = bytes.chunks(Row::fixed_capacity().div_ceil(8));
+        for (row, chunk) in result.rows.iter_mut().zip(&mut chunks) {
+            *row = Row::from_bytes(chunk)?;
+        }
+        if chunks.flatten().any(|&b| b != 0) {
+            return None;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type SmallMatrix = BitMatrix<U16Bitset, 10>;
+    type PackedMatrix = BitMatrix<PackedU8Bitset<3>, 20>;
+
+    fn from_cells<Row: BitsetOps + FixedSizeBitset + Copy, const R: usize>(
+        cells: &[(usize, usize)],
+    ) -> BitMatrix<Row, R> {
+        let mut matrix = BitMatrix::empty();
+        for &(r, c) in cells {
+            matrix.insert(r, c);
+        }
+        matrix
+    }
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut matrix = PackedMatrix::empty();
+        assert_eq!(PackedMatrix::row_count(), 20);
+        assert_eq!(PackedMatrix::column_count(), 24);
+
+        assert!(matrix.insert(3, 17));
+        assert!(!matrix.insert(3, 17));
+        assert!(matrix.contains(3, 17));
+        assert!(!matrix.contains(17, 3));
+        assert_eq!(matrix.count(), 1);
+
+        matrix.remove(3, 17);
+        assert!(!matrix.contains(3, 17));
+        assert_eq!(matrix.count(), 0);
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let matrix: PackedMatrix = from_cells(&[(0, 5), (2, 5), (2, 20), (19, 5)]);
+        assert!(matrix.iter().eq([(0, 5), (2, 5), (2, 20), (19, 5)]));
+        assert!(matrix.column(5).eq([0, 2, 19]));
+        assert!(matrix.column(5).rev().eq([19, 2, 0]));
+        assert!(matrix.column(20).eq([2]));
+        assert!(matrix.row(2).into_iter().eq([5, 20]));
+        assert_eq!(matrix.rows().filter(|row| row.count() > 0).count(), 3);
+    }
+
+    #[test]
+    fn shifts() {
+        let matrix: SmallMatrix = from_cells(&[(0, 0), (5, 7), (9, 15)]);
+
+        assert_eq!(matrix.shift_up(1), from_cells(&[(4, 7), (8, 15)]));
+        assert_eq!(matrix.shift_down(1), from_cells(&[(1, 0), (6, 7)]));
+        assert_eq!(matrix.shift_left(1), from_cells(&[(5, 6), (9, 14)]));
+        assert_eq!(matrix.shift_right(1), from_cells(&[(0, 1), (5, 8)]));
+
+        assert_eq!(matrix.shift_up(10), SmallMatrix::empty());
+        assert_eq!(matrix.shift_right(16), SmallMatrix::empty());
+        assert_eq!(matrix.shift_down(0), matrix);
+    }
+
+    #[test]
+    fn transpose() {
+        let matrix: SmallMatrix = from_cells(&[(0, 1), (3, 15), (9, 2)]);
+        let transposed: BitMatrix<U16Bitset, 16> = matrix.transpose();
+        assert_eq!(transposed, from_cells(&[(1, 0), (15, 3), (2, 9)]));
+
+        let back: SmallMatrix = transposed.transpose();
+        assert_eq!(back, matrix);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let matrix: PackedMatrix = from_cells(&[(0, 0), (0, 23), (7, 8), (19, 5)]);
+        let bytes = matrix.to_bytes();
+        assert_eq!(bytes.len(), 20 * 3);
+        assert_eq!(&bytes[..3], &[0b1, 0, 0b1000_0000]);
+        assert_eq!(PackedMatrix::from_bytes(&bytes), Some(matrix));
+
+        let mut too_long = bytes.clone();
+        too_long.push(1);
+        assert_eq!(PackedMatrix::from_bytes(&too_long), None);
+    }
+
+    #[test]
+    fn bytes_round_trip_with_padded_rows() {
+        type PaddedMatrix = BitMatrix<packed::PackedBitset<U64Bitset, 3, 130>, 4>;
+        let matrix: PaddedMatrix = from_cells(&[(0, 0), (0, 129), (1, 64), (3, 128)]);
+        let bytes = matrix.to_bytes();
+        assert_eq!(bytes.len(), 4 * 17);
+        assert_eq!(PaddedMatrix::from_bytes(&bytes), Some(matrix));
+
+        // Rows narrower than a byte still take a byte each.
+        type NarrowMatrix = BitMatrix<packed::PackedBitset<U8Bitset, 1, 5>, 3>;
+        let matrix: NarrowMatrix = from_cells(&[(0, 4), (2, 0)]);
+        let bytes = matrix.to_bytes();
+        assert_eq!(bytes, vec![0b1_0000, 0, 0b1]);
+        assert_eq!(NarrowMatrix::from_bytes(&bytes), Some(matrix));
+    }
+
+    #[test]
+    fn bitwise_ops() {
+        let a: SmallMatrix = from_cells(&[(0, 0), (1, 1), (2, 2)]);
+        let b: SmallMatrix = from_cells(&[(1, 1), (2, 3)]);
+
+        assert_eq!(a & b, from_cells(&[(1, 1)]));
+        assert_eq!(a | b, from_cells(&[(0, 0), (1, 1), (2, 2), (2, 3)]));
+        assert_eq!(a ^ b, from_cells(&[(0, 0), (2, 2), (2, 3)]));
+        assert_eq!(a - b, from_cells(&[(0, 0), (2, 2)]));
+        assert_eq!(a.complement().count(), 10 * 16 - 3);
+        assert_eq!(a.intersection_count(&b), 1);
+        assert!((a & b).is_subset(&a));
+        assert!(!a.is_disjoint(&b));
+    }
+}
@@ -1,7 +1,23 @@
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, RangeBounds};
+use std::fmt;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, Not, Range, RangeBounds,
+    Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign,
+};
 
+pub mod atomic;
+pub mod auto;
+#[cfg(any(test, feature = "testing"))]
+pub mod conformance;
+pub mod dynamic;
+#[cfg(feature = "exact")]
+pub mod exact;
+pub mod hierarchical;
+pub mod matrix;
 pub mod packed;
 pub mod primitives;
+pub mod runs;
+#[cfg(feature = "serde")]
+mod serialize;
 pub mod simd;
 pub mod sparse;
 
@@ -12,11 +28,45 @@ pub trait BitsetOps {
     fn remove(&mut self, index: usize);
     fn contains(&self, index: usize) -> bool;
     fn count(&self) -> usize;
+
+    /// A bitset holding each of `values`.
+    /// Like `collect`, this panics if a value doesn't fit.
+    fn from_slice(values: &[usize]) -> Self
+    where
+        Self: Sized + FromIterator<usize>,
+    {
+        values.iter().copied().collect()
+    }
 }
 
 pub trait BitsetRangeOps {
     fn insert_range<R: RangeBounds<usize>>(&mut self, range: R);
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R);
+
+    /// A bitset holding every element in the range.
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps + Sized,
+    {
+        let mut bitset = Self::empty();
+        bitset.insert_range(range);
+        bitset
+    }
+}
+
+/// Positional queries over the elements of a bitset, in index order.
+pub trait BitsetRankOps {
+    /// The number of elements strictly less than `index`.
+    fn rank(&self, index: usize) -> usize;
+
+    /// The `k`th smallest element, counting from zero.
+    fn select(&self, k: usize) -> Option<usize>;
+
+    /// The smallest element strictly greater than `index`.
+    fn next_set_after(&self, index: usize) -> Option<usize>;
+
+    /// The largest element strictly less than `index`.
+    fn prev_set_before(&self, index: usize) -> Option<usize>;
 }
 
 pub trait BitsetOpsUnsafe {
@@ -25,10 +75,112 @@ pub trait BitsetOpsUnsafe {
     unsafe fn contains_unchecked(&self, index: usize) -> bool;
 }
 
+/// Checked versions of the fundamental operations, for fixed-capacity bitsets.
+///
+/// The plain `BitsetOps` methods panic on an out of range index, and these return an error instead.
+pub trait BitsetTryOps: BitsetOps + FixedSizeBitset {
+    fn try_insert(&mut self, index: usize) -> Result<bool, CapacityError> {
+        CapacityError::check(index, Self::fixed_capacity())?;
+        Ok(self.insert(index))
+    }
+
+    fn try_remove(&mut self, index: usize) -> Result<(), CapacityError> {
+        CapacityError::check(index, Self::fixed_capacity())?;
+        self.remove(index);
+        Ok(())
+    }
+
+    fn try_contains(&self, index: usize) -> Result<bool, CapacityError> {
+        CapacityError::check(index, Self::fixed_capacity())?;
+        Ok(self.contains(index))
+    }
+}
+
 /// Bitsets that support logical operations.
+///
+/// The operators follow the usual set algebra:
+/// `&` is intersection, `|` is union, `^` is symmetric difference and `-` is difference (`a & !b`).
 pub trait BitwiseOps:
-    Sized + BitAnd<Output = Self> + BitAndAssign + BitOr<Output = Self> + BitOrAssign
+    Sized
+    + BitAnd<Output = Self>
+    + BitAndAssign
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + BitXor<Output = Self>
+    + BitXorAssign
+    + Sub<Output = Self>
+    + SubAssign
+{
+    /// The elements in `self` but not in `other`.
+    fn difference(self, other: Self) -> Self {
+        self - other
+    }
+
+    /// The elements in exactly one of `self` or `other`.
+    fn symmetric_difference(self, other: Self) -> Self {
+        self ^ other
+    }
+
+    /// True if every element of `self` is also in `other`.
+    fn is_subset(&self, other: &Self) -> bool;
+
+    /// True if every element of `other` is also in `self`.
+    fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// True if `self` and `other` have no elements in common.
+    fn is_disjoint(&self, other: &Self) -> bool;
+
+    /// The number of elements in both `self` and `other`, without building the intersection.
+    fn intersection_count(&self, other: &Self) -> usize;
+}
+
+/// Bitsets that can be complemented.
+///
+/// This only makes sense for bitsets with a bounded number of bits,
+/// so it is kept apart from `BitwiseOps`.
+pub trait ComplementOps: Sized + Not<Output = Self> {
+    /// Every element not in `self`.
+    fn complement(self) -> Self {
+        !self
+    }
+}
+
+/// Bitsets that can be shifted as a whole, like a very wide integer.
+///
+/// `<<` moves element `i` to `i + n`, and `>>` moves it to `i - n`.
+/// Elements shifted past either end are dropped.
+/// The rotations wrap them around to the other end instead.
+pub trait ShiftOps:
+    Sized + Shl<usize, Output = Self> + ShlAssign<usize> + Shr<usize, Output = Self> + ShrAssign<usize>
 {
+    fn rotate_left(self, n: usize) -> Self;
+    fn rotate_right(self, n: usize) -> Self;
+}
+
+/// Maximal runs of consecutive elements, for code that thinks in spans rather than single indices.
+pub trait BitsetRunOps {
+    type Runs: DoubleEndedIterator<Item = Range<usize>>;
+
+    /// Each maximal run of consecutive elements, in index order.
+    fn runs(&self) -> Self::Runs;
+
+    /// Each maximal run of indices below the capacity that are not elements.
+    fn gaps(&self) -> Self::Runs;
+}
+
+/// A compact encoding of a bitset as bytes, for saving state between runs.
+///
+/// Element `i` is bit `i % 8` of byte `i / 8`, whatever the word size or endianness,
+/// so fixed-size bitsets of the same capacity share an encoding.
+/// Sparse bitsets are the exception, and use a run-length encoding instead.
+pub trait BitsetByteOps: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Missing bytes at the end count as zero.
+    /// Returns `None` if the bytes hold elements that don't fit.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
 }
 
 /// A bitset that can not change the number of bits it contains.
@@ -41,6 +193,82 @@ pub trait FullBitset {
     fn full() -> Self;
 }
 
+/// An index that is out of range for a fixed-capacity bitset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError {
+    pub index: usize,
+    pub capacity: usize,
+}
+
+impl CapacityError {
+    pub fn check(index: usize, capacity: usize) -> Result<(), Self> {
+        if index < capacity {
+            Ok(())
+        } else {
+            Err(Self { index, capacity })
+        }
+    }
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Index {} is out of range for a bitset with capacity {}",
+            self.index, self.capacity
+        )
+    }
+}
+
+impl std::error::Error for CapacityError {}
+
+// Left to themselves, the fixed-size bitsets would silently drop or mangle bits past their capacity,
+// so the safe methods all panic through here instead.
+// Use `BitsetOpsUnsafe` where the check costs too much.
+pub(crate) fn check_index(index: usize, capacity: usize) {
+    if let Err(e) = CapacityError::check(index, capacity) {
+        panic!("{}", e);
+    }
+}
+
+// A range can start at the capacity, as long as it's empty, but not past it.
+pub(crate) fn check_range<R: RangeBounds<usize>>(range: &R, capacity: usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&i) => i,
+        Bound::Excluded(&i) => i.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    if start > capacity {
+        panic!(
+            "{}",
+            CapacityError {
+                index: start,
+                capacity
+            }
+        );
+    }
+    match range.end_bound() {
+        Bound::Included(&i) => check_index(i, capacity),
+        Bound::Excluded(&i) if i > 0 => check_index(i - 1, capacity),
+        _ => {}
+    }
+}
+
+// Write out the elements in set notation, like `{1, 5, 9}`.
+pub(crate) fn write_set(
+    f: &mut fmt::Formatter,
+    elements: impl IntoIterator<Item = usize>,
+) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, element) in elements.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", element)?;
+    }
+    write!(f, "}}")
+}
+
 pub type U8Bitset = primitives::PrimitiveBitset<u8>;
 pub type U16Bitset = primitives::PrimitiveBitset<u16>;
 pub type U32Bitset = primitives::PrimitiveBitset<u32>;
@@ -72,6 +300,32 @@ pub type SimdU32Bitset8 = simd::SimdU32Bitset8;
 pub type SimdU64Bitset2 = simd::SimdU64Bitset2;
 pub type SimdU64Bitset4 = simd::SimdU64Bitset4;
 
+// A bitset that grows to fit, for when we don't know the size up front.
+pub type DynBitset = dynamic::DynBitset;
+
+// A bitset of `N` words that keeps track of which words have anything in them, for sets that are mostly empty.
+// `S` is the size of that summary, `hierarchical::summary_words(N)`.
+pub type HierarchicalBitset<const N: usize, const S: usize> =
+    hierarchical::HierarchicalBitset<N, S>;
+
+// A bitset that can be shared between threads, and updated through a shared reference.
+pub type AtomicBitset<const N: usize> = atomic::AtomicBitset<N>;
+
+// A bitset of exactly `BITS` bits, in the smallest primitive that fits or else packed `u64` words.
+#[cfg(feature = "exact")]
+pub type Bitset<const BITS: usize> = exact::ExactBitset<BITS>;
+
+// A bitset of `N` `u64` words, using the widest SIMD the machine has for whole-set operations.
+pub type AutoBitset<const N: usize> = auto::AutoBitset<N>;
+
+// Sparse bitsets, with their entries either on the heap, or in a fixed capacity array.
+pub type SparseU16Bitset = sparse::SparseBitset<Vec<sparse::SparseEntry<u16>>, u16>;
+pub type SparseU64Bitset = sparse::SparseBitset<Vec<sparse::SparseEntry<u64>>, u64>;
+pub type ArraySparseU16Bitset<const N: usize> =
+    sparse::SparseBitset<crate::stack_vec::ArrayVec<sparse::SparseEntry<u16>, N>, u16>;
+pub type ArraySparseU64Bitset<const N: usize> =
+    sparse::SparseBitset<crate::stack_vec::ArrayVec<sparse::SparseEntry<u64>, N>, u64>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,7 +350,10 @@ mod tests {
         assert_eq!(empty.count(), 0, "empty bitset should have count 0");
 
         for i in 0..BS::fixed_capacity() {
-            assert!(!empty.contains(i), "empty bitset should not have any bits set");
+            assert!(
+                !empty.contains(i),
+                "empty bitset should not have any bits set"
+            );
         }
     }
 
@@ -163,7 +420,11 @@ mod tests {
                 "setting a set bit in bitset should have returned false"
             );
             bitset.remove(i);
-            assert!(!bitset.contains(i), "bitset should not have bit {} unset", i);
+            assert!(
+                !bitset.contains(i),
+                "bitset should not have bit {} unset",
+                i
+            );
             assert_eq!(bitset.count(), 0, "bitset should have count {}", i);
         }
     }
@@ -263,6 +524,592 @@ mod tests {
         }
     }
 
+    pub fn test_bitwise_xor<BS: BitsetOps + BitwiseOps + FixedSizeBitset + Eq + std::fmt::Debug>() {
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut bitset1 = BS::empty();
+                let mut bitset2 = BS::empty();
+                let mut bitset3 = BS::empty();
+                bitset1.insert(i);
+                bitset2.insert(j);
+                if i != j {
+                    bitset3.insert(i);
+                    bitset3.insert(j);
+                }
+                assert_eq!(bitset1 ^ bitset2, bitset3);
+            }
+        }
+    }
+
+    pub fn test_bitwise_xor_assign<
+        BS: BitsetOps + BitwiseOps + FixedSizeBitset + Eq + std::fmt::Debug,
+    >() {
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut bitset1 = BS::empty();
+                let mut bitset2 = BS::empty();
+                let mut bitset3 = BS::empty();
+                bitset1.insert(i);
+                bitset2.insert(j);
+                if i != j {
+                    bitset3.insert(i);
+                    bitset3.insert(j);
+                }
+
+                bitset1 ^= bitset2;
+                assert_eq!(bitset1, bitset3);
+            }
+        }
+    }
+
+    pub fn test_difference<BS: BitsetOps + BitwiseOps + FixedSizeBitset + Eq + std::fmt::Debug>() {
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut bitset1 = BS::empty();
+                let mut bitset2 = BS::empty();
+                let mut bitset3 = BS::empty();
+                bitset1.insert(i);
+                bitset1.insert(j);
+                bitset2.insert(j);
+                if i != j {
+                    bitset3.insert(i);
+                }
+                assert_eq!(bitset1 - bitset2, bitset3);
+            }
+        }
+    }
+
+    pub fn test_difference_assign<
+        BS: BitsetOps + BitwiseOps + FixedSizeBitset + Eq + std::fmt::Debug,
+    >() {
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut bitset1 = BS::empty();
+                let mut bitset2 = BS::empty();
+                let mut bitset3 = BS::empty();
+                bitset1.insert(i);
+                bitset1.insert(j);
+                bitset2.insert(j);
+                if i != j {
+                    bitset3.insert(i);
+                }
+
+                bitset1 -= bitset2;
+                assert_eq!(bitset1, bitset3);
+            }
+        }
+    }
+
+    pub fn test_complement<BS: BitsetOps + ComplementOps + FixedSizeBitset>() {
+        for i in 0..BS::fixed_capacity() {
+            let mut bitset = BS::empty();
+            bitset.insert(i);
+            let complement = bitset.complement();
+            assert_eq!(complement.count(), BS::fixed_capacity() - 1);
+            assert!(
+                !complement.contains(i),
+                "complement should not contain {}",
+                i
+            );
+        }
+    }
+
+    pub fn test_subset_superset<BS: BitsetOps + BitwiseOps + FixedSizeBitset>() {
+        let empty = BS::empty();
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut one = BS::empty();
+                let mut two = BS::empty();
+                one.insert(i);
+                two.insert(i);
+                two.insert(j);
+
+                assert!(empty.is_subset(&one), "empty is a subset of everything");
+                assert!(one.is_subset(&one), "a set is a subset of itself");
+                assert!(
+                    one.is_subset(&two),
+                    "{{{}}} is a subset of {{{}, {}}}",
+                    i,
+                    i,
+                    j
+                );
+                assert_eq!(two.is_subset(&one), i == j);
+                assert!(two.is_superset(&one));
+                assert_eq!(one.is_superset(&two), i == j);
+            }
+        }
+    }
+
+    pub fn test_disjoint<BS: BitsetOps + BitwiseOps + FixedSizeBitset>() {
+        for i in 0..BS::fixed_capacity() {
+            for j in 0..BS::fixed_capacity() {
+                let mut bitset1 = BS::empty();
+                let mut bitset2 = BS::empty();
+                bitset1.insert(i);
+                bitset2.insert(j);
+                assert_eq!(bitset1.is_disjoint(&bitset2), i != j);
+            }
+        }
+    }
+
+    pub fn test_intersection_count<BS: BitsetOps + BitwiseOps + FixedSizeBitset>() {
+        // Multiples of 2 and multiples of 3 intersect on the multiples of 6.
+        let mut twos = BS::empty();
+        let mut threes = BS::empty();
+        for i in (0..BS::fixed_capacity()).step_by(2) {
+            twos.insert(i);
+        }
+        for i in (0..BS::fixed_capacity()).step_by(3) {
+            threes.insert(i);
+        }
+        let sixes = BS::fixed_capacity().div_ceil(6);
+        assert_eq!(twos.intersection_count(&threes), sixes);
+        assert_eq!(threes.intersection_count(&twos), sixes);
+        assert_eq!(twos.intersection_count(&BS::empty()), 0);
+    }
+
+    // Random bitsets of varying density, the same ones each run.
+    fn random_bitsets<BS: BitsetOps + FixedSizeBitset>(count: usize) -> impl Iterator<Item = BS> {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(2024);
+        (0..count).map(move |_| {
+            let density = rng.gen_range(0.0..=1.0);
+            let mut bitset = BS::empty();
+            for i in 0..BS::fixed_capacity() {
+                if rng.gen_bool(density) {
+                    bitset.insert(i);
+                }
+            }
+            bitset
+        })
+    }
+
+    // The runs of indices where `contains` is `wanted`, found the slow way.
+    fn naive_runs<BS: BitsetOps + FixedSizeBitset>(bitset: &BS, wanted: bool) -> Vec<Range<usize>> {
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for i in (0..BS::fixed_capacity()).filter(|&i| bitset.contains(i) == wanted) {
+            match runs.last_mut() {
+                Some(run) if run.end == i => run.end += 1,
+                _ => runs.push(i..i + 1),
+            }
+        }
+        runs
+    }
+
+    pub fn test_runs_and_gaps<
+        BS: BitsetOps + BitsetRunOps + FullBitset + FixedSizeBitset + std::fmt::Debug,
+    >() {
+        let capacity = BS::fixed_capacity();
+        assert_eq!(BS::empty().runs().next(), None);
+        assert_eq!(BS::empty().gaps().collect::<Vec<_>>(), vec![0..capacity]);
+        assert_eq!(BS::full().runs().collect::<Vec<_>>(), vec![0..capacity]);
+        assert_eq!(BS::full().gaps().next(), None);
+
+        for bitset in random_bitsets::<BS>(100) {
+            let runs = naive_runs(&bitset, true);
+            let gaps = naive_runs(&bitset, false);
+            assert_eq!(bitset.runs().collect::<Vec<_>>(), runs, "{:?}", bitset);
+            assert_eq!(bitset.gaps().collect::<Vec<_>>(), gaps, "{:?}", bitset);
+            assert!(
+                bitset.runs().rev().eq(runs.iter().rev().cloned()),
+                "{:?}",
+                bitset
+            );
+            assert!(
+                bitset.gaps().rev().eq(gaps.iter().rev().cloned()),
+                "{:?}",
+                bitset
+            );
+
+            // Taking from alternate ends still gives each run exactly once.
+            let mut iter = bitset.runs();
+            let (mut front, mut back) = (Vec::new(), Vec::new());
+            while let Some(run) = iter.next() {
+                front.push(run);
+                let Some(run) = iter.next_back() else {
+                    break;
+                };
+                back.push(run);
+            }
+            front.extend(back.into_iter().rev());
+            assert_eq!(front, runs, "{:?}", bitset);
+        }
+    }
+
+    pub fn test_bytes_round_trip<BS: BitsetOps + BitsetByteOps + FixedSizeBitset + PartialEq>() {
+        for bitset in random_bitsets::<BS>(100) {
+            let bytes = bitset.to_bytes();
+            assert_eq!(bytes.len(), BS::fixed_capacity() / 8);
+            for i in 0..BS::fixed_capacity() {
+                assert_eq!(
+                    (bytes[i / 8] >> (i % 8)) & 1 == 1,
+                    bitset.contains(i),
+                    "bit {}",
+                    i
+                );
+            }
+            assert!(BS::from_bytes(&bytes) == Some(bitset));
+        }
+    }
+
+    pub fn test_bytes_padding<BS: BitsetOps + BitsetByteOps + FixedSizeBitset + PartialEq>() {
+        assert!(BS::from_bytes(&[]) == Some(BS::empty()));
+
+        let mut short = BS::empty();
+        short.insert(3);
+        assert!(BS::from_bytes(&[0b1000]) == Some(short));
+
+        // Extra bytes are fine as long as they are zero.
+        let mut long = vec![0; BS::fixed_capacity() / 8 + 1];
+        assert!(BS::from_bytes(&long) == Some(BS::empty()));
+        *long.last_mut().unwrap() = 1;
+        assert!(BS::from_bytes(&long).is_none());
+    }
+
+    // Nothing should be able to reach the bits past the capacity in the last word.
+    pub fn test_padding_stays_clear<
+        BS: BitsetOps
+            + BitsetRangeOps
+            + ComplementOps
+            + ShiftOps
+            + FullBitset
+            + FixedSizeBitset
+            + Copy
+            + PartialEq
+            + fmt::Debug,
+    >()
+    where
+        for<'a> &'a BS: IntoIterator<IntoIter: DoubleEndedIterator<Item = usize>>,
+    {
+        let capacity = BS::fixed_capacity();
+        let full = BS::full();
+        assert_eq!(full.count(), capacity);
+        assert_eq!((!BS::empty()).count(), capacity);
+        assert_eq!((full << 1).count(), capacity - 1);
+        assert_eq!(full.rotate_left(3), full);
+        assert_eq!(BS::from_range(capacity - 10..).count(), 10);
+        assert_eq!(full.into_iter().count(), capacity);
+        assert_eq!(full.into_iter().next_back(), Some(capacity - 1));
+    }
+
+    pub fn test_bytes_cover_only_the_bits<
+        BS: BitsetOps + BitsetByteOps + FullBitset + FixedSizeBitset + PartialEq + fmt::Debug,
+    >() {
+        let capacity = BS::fixed_capacity();
+        let full = BS::full();
+        let bytes = full.to_bytes();
+        assert_eq!(bytes.len(), capacity.div_ceil(8));
+        assert_eq!(BS::from_bytes(&bytes), Some(full));
+        assert_eq!(BS::from_bytes(&vec![0; bytes.len() - 1]), Some(BS::empty()));
+
+        let spare = capacity % 8;
+        if spare != 0 {
+            assert_eq!(*bytes.last().unwrap(), (1 << spare) - 1);
+            // The next bit up is in the padding.
+            let mut too_big = bytes;
+            *too_big.last_mut().unwrap() |= 1 << spare;
+            assert_eq!(BS::from_bytes(&too_big), None);
+        }
+    }
+
+    pub fn test_try_ops<BS: BitsetTryOps>() {
+        let capacity = BS::fixed_capacity();
+        let out_of_range = Err(CapacityError {
+            index: capacity,
+            capacity,
+        });
+        let mut bitset = BS::empty();
+
+        assert_eq!(bitset.try_insert(capacity - 1), Ok(true));
+        assert_eq!(bitset.try_insert(capacity - 1), Ok(false));
+        assert_eq!(bitset.try_contains(capacity - 1), Ok(true));
+        assert_eq!(bitset.try_remove(capacity - 1), Ok(()));
+        assert_eq!(bitset.try_contains(capacity - 1), Ok(false));
+
+        assert_eq!(bitset.try_insert(capacity), out_of_range.map(|_| true));
+        assert_eq!(bitset.try_remove(capacity), out_of_range);
+        assert_eq!(bitset.try_contains(capacity), out_of_range.map(|_| true));
+        assert_eq!(bitset.try_insert(usize::MAX).unwrap_err().index, usize::MAX);
+        assert_eq!(bitset.count(), 0);
+    }
+
+    pub fn test_out_of_range_panics<BS: BitsetOps + FixedSizeBitset>() {
+        let capacity = BS::fixed_capacity();
+        let message = format!(
+            "Index {} is out of range for a bitset with capacity {}",
+            capacity, capacity
+        );
+        let panics = |f: &dyn Fn(&mut BS)| {
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut BS::empty())));
+            let payload = result.expect_err("should have panicked");
+            assert_eq!(payload.downcast_ref::<String>(), Some(&message));
+        };
+        panics(&|bitset| {
+            bitset.insert(capacity);
+        });
+        panics(&|bitset| bitset.remove(capacity));
+        panics(&|bitset| {
+            bitset.contains(capacity);
+        });
+    }
+
+    // Ranges that start on a word boundary and run to the end take the whole-word paths, which
+    // mustn't spill into the padding past the capacity.
+    pub fn test_aligned_ranges_stop_at_the_capacity<
+        BS: BitsetOps + BitsetRangeOps + FixedSizeBitset,
+    >() {
+        let capacity = BS::fixed_capacity();
+        for word in [8, 16, 32, 64, 128].into_iter().filter(|&w| w < capacity) {
+            let mut bitset = BS::empty();
+            bitset.insert_range(word..capacity);
+            assert_eq!(bitset.count(), capacity - word);
+            bitset.insert_range(0..word);
+            assert_eq!(bitset.count(), capacity);
+            bitset.remove_range(0..word);
+            assert_eq!(bitset.count(), capacity - word);
+            bitset.remove_range(word..);
+            assert_eq!(bitset.count(), 0);
+        }
+        let bitset = BS::from_range(0..capacity);
+        assert_eq!(bitset.count(), capacity);
+    }
+
+    pub fn test_range_out_of_range_panics<BS: BitsetOps + BitsetRangeOps + FixedSizeBitset>() {
+        let capacity = BS::fixed_capacity();
+        let panics = |index: usize, f: &dyn Fn(&mut BS)| {
+            let message = format!(
+                "Index {} is out of range for a bitset with capacity {}",
+                index, capacity
+            );
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut BS::empty())));
+            let payload = result.expect_err("should have panicked");
+            assert_eq!(payload.downcast_ref::<String>(), Some(&message));
+        };
+        panics(capacity, &|bitset| bitset.insert_range(0..capacity + 1));
+        panics(capacity, &|bitset| bitset.remove_range(0..=capacity));
+        panics(capacity + 1, &|bitset| bitset.insert_range(capacity + 1..));
+        panics(capacity, &|_| {
+            BS::from_range(0..capacity + 1);
+        });
+
+        // An empty range at the capacity is still fine.
+        let mut bitset = BS::empty();
+        bitset.insert_range(capacity..);
+        bitset.remove_range(capacity..capacity);
+        assert_eq!(bitset.count(), 0);
+    }
+
+    pub fn test_from_iterator<
+        BS: BitsetOps + FixedSizeBitset + FromIterator<usize> + Extend<usize>,
+    >() {
+        let capacity = BS::fixed_capacity();
+        let values = [0, 3, capacity / 2, capacity - 1];
+
+        let collected: BS = values.iter().copied().collect();
+        assert_eq!(collected.count(), values.len());
+        assert!(values.iter().all(|&v| collected.contains(v)));
+
+        let mut extended = BS::from_slice(&values[..2]);
+        extended.extend(values[2..].iter().copied());
+        extended.extend([0, 3]);
+        for i in 0..capacity {
+            assert_eq!(extended.contains(i), collected.contains(i), "bit {}", i);
+        }
+    }
+
+    pub fn test_from_iterator_out_of_range<
+        BS: BitsetOps + FixedSizeBitset + FromIterator<usize>,
+    >() {
+        let result = std::panic::catch_unwind(|| {
+            let _: BS = [1, BS::fixed_capacity()].into_iter().collect();
+        });
+        assert!(
+            result.is_err(),
+            "collecting an out of range index should panic"
+        );
+    }
+
+    pub fn test_conformance<
+        BS: BitsetOps + BitsetRangeOps + BitwiseOps + FixedSizeBitset + Clone + std::fmt::Debug,
+    >()
+    where
+        for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+    {
+        super::conformance::check_bitset::<BS>(BS::fixed_capacity());
+    }
+
+    pub fn test_from_range<BS: BitsetOps + BitsetRangeOps + FixedSizeBitset>() {
+        let capacity = BS::fixed_capacity();
+        let bitset = BS::from_range(1..capacity - 1);
+        assert_eq!(bitset.count(), capacity - 2);
+        assert!(!bitset.contains(0) && !bitset.contains(capacity - 1));
+        assert_eq!(BS::from_range(..).count(), capacity);
+        assert_eq!(BS::from_range(0..=capacity - 1).count(), capacity);
+        assert_eq!(BS::from_range(3..3).count(), 0);
+
+        let result = std::panic::catch_unwind(|| BS::from_range(0..=capacity).count());
+        assert!(result.is_err(), "a range past the capacity should panic");
+    }
+
+    pub fn test_display<BS: BitsetOps + FixedSizeBitset + fmt::Display>() {
+        let mut bitset = BS::empty();
+        assert_eq!(bitset.to_string(), "{}");
+        bitset.insert(5);
+        assert_eq!(bitset.to_string(), "{5}");
+        bitset.insert(1);
+        bitset.insert(BS::fixed_capacity() - 1);
+        assert_eq!(
+            bitset.to_string(),
+            format!("{{1, 5, {}}}", BS::fixed_capacity() - 1)
+        );
+    }
+
+    // Shift amounts that hit the interesting cases for all the word sizes.
+    fn shift_amounts(capacity: usize) -> impl Iterator<Item = usize> {
+        [
+            0, 1, 2, 7, 8, 9, 15, 16, 17, 31, 32, 33, 63, 64, 65, 127, 128, 129,
+        ]
+        .into_iter()
+        .filter(move |&n| n < capacity)
+        .chain([capacity - 1, capacity, capacity + 1])
+    }
+
+    // Every third element, offset so that both ends of the bitset are interesting.
+    fn shift_pattern<BS: BitsetOps + FixedSizeBitset>() -> BS {
+        let mut bitset = BS::empty();
+        for i in (1..BS::fixed_capacity()).step_by(3) {
+            bitset.insert(i);
+        }
+        bitset
+    }
+
+    pub fn test_shift_left<BS: BitsetOps + ShiftOps + FixedSizeBitset + Copy>() {
+        let pattern = shift_pattern::<BS>();
+        for n in shift_amounts(BS::fixed_capacity()) {
+            let shifted = pattern << n;
+            for i in 0..BS::fixed_capacity() {
+                let expected = i >= n && pattern.contains(i - n);
+                assert_eq!(shifted.contains(i), expected, "bit {} after << {}", i, n);
+            }
+
+            let mut assigned = pattern;
+            assigned <<= n;
+            assert_eq!(assigned.count(), shifted.count());
+        }
+    }
+
+    pub fn test_shift_right<BS: BitsetOps + ShiftOps + FixedSizeBitset + Copy>() {
+        let pattern = shift_pattern::<BS>();
+        for n in shift_amounts(BS::fixed_capacity()) {
+            let shifted = pattern >> n;
+            for i in 0..BS::fixed_capacity() {
+                let expected = i + n < BS::fixed_capacity() && pattern.contains(i + n);
+                assert_eq!(shifted.contains(i), expected, "bit {} after >> {}", i, n);
+            }
+
+            let mut assigned = pattern;
+            assigned >>= n;
+            assert_eq!(assigned.count(), shifted.count());
+        }
+    }
+
+    pub fn test_rotate<BS: BitsetOps + ShiftOps + FixedSizeBitset + Copy>() {
+        let pattern = shift_pattern::<BS>();
+        let capacity = BS::fixed_capacity();
+        for n in shift_amounts(capacity) {
+            let left = pattern.rotate_left(n);
+            let right = pattern.rotate_right(n);
+            assert_eq!(left.count(), pattern.count());
+            assert_eq!(right.count(), pattern.count());
+            for i in 0..capacity {
+                assert_eq!(
+                    left.contains((i + n) % capacity),
+                    pattern.contains(i),
+                    "bit {} after rotate_left({})",
+                    i,
+                    n
+                );
+                assert_eq!(
+                    right.contains(i),
+                    pattern.contains((i + n) % capacity),
+                    "bit {} after rotate_right({})",
+                    i,
+                    n
+                );
+            }
+        }
+    }
+
+    // Bitsets with a single element at each position, plus one with every third element.
+    // Each comes with the list of its elements, to check the answers against.
+    fn rank_test_patterns<BS: BitsetOps + FixedSizeBitset>() -> Vec<(BS, Vec<usize>)> {
+        let mut patterns = vec![(BS::empty(), vec![])];
+        for i in 0..BS::fixed_capacity() {
+            let mut bitset = BS::empty();
+            bitset.insert(i);
+            patterns.push((bitset, vec![i]));
+        }
+        let mut threes = BS::empty();
+        for i in (0..BS::fixed_capacity()).step_by(3) {
+            threes.insert(i);
+        }
+        patterns.push((threes, (0..BS::fixed_capacity()).step_by(3).collect()));
+        let mut full = BS::empty();
+        for i in 0..BS::fixed_capacity() {
+            full.insert(i);
+        }
+        patterns.push((full, (0..BS::fixed_capacity()).collect()));
+        patterns
+    }
+
+    pub fn test_rank<BS: BitsetOps + BitsetRankOps + FixedSizeBitset>() {
+        for (bitset, elements) in rank_test_patterns::<BS>() {
+            for i in 0..=BS::fixed_capacity() + 1 {
+                let expected = elements.partition_point(|&e| e < i);
+                assert_eq!(bitset.rank(i), expected, "rank({})", i);
+            }
+        }
+    }
+
+    pub fn test_select<BS: BitsetOps + BitsetRankOps + FixedSizeBitset>() {
+        for (bitset, elements) in rank_test_patterns::<BS>() {
+            for (k, &e) in elements.iter().enumerate() {
+                assert_eq!(bitset.select(k), Some(e), "select({})", k);
+                assert_eq!(bitset.rank(e), k, "rank should invert select");
+            }
+            assert_eq!(bitset.select(elements.len()), None);
+        }
+    }
+
+    pub fn test_next_set_after<BS: BitsetOps + BitsetRankOps + FixedSizeBitset>() {
+        for (bitset, elements) in rank_test_patterns::<BS>() {
+            for i in 0..=BS::fixed_capacity() {
+                let expected = elements.get(elements.partition_point(|&e| e <= i)).copied();
+                assert_eq!(bitset.next_set_after(i), expected, "next_set_after({})", i);
+            }
+            assert_eq!(bitset.next_set_after(usize::MAX), None);
+        }
+    }
+
+    pub fn test_prev_set_before<BS: BitsetOps + BitsetRankOps + FixedSizeBitset>() {
+        for (bitset, elements) in rank_test_patterns::<BS>() {
+            for i in 0..=BS::fixed_capacity() + 1 {
+                let expected = elements[..elements.partition_point(|&e| e < i)]
+                    .last()
+                    .copied();
+                assert_eq!(
+                    bitset.prev_set_before(i),
+                    expected,
+                    "prev_set_before({})",
+                    i
+                );
+            }
+        }
+    }
+
     pub fn test_empty_iterator<BS: BitsetOps>()
     where
         for<'a> &'a BS: IntoIterator<Item = usize>,
@@ -1,12 +1,19 @@
-use std::ops::Bound;
+use std::ops::{BitXor, BitXorAssign, Bound, Not, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign};
 
+use super::primitives::PrimitiveBitset;
+use super::runs::RunsIterator;
 ///- Bitsets represented as an array of fixed-sized bitsets.
 use super::*;
+use num::PrimInt;
 
+/// `N` words of `P`, holding the first `LEN` elements.
+///
+/// By default every bit of every word is used. A smaller `LEN` says how many of them the bitset really holds,
+/// and the bits past it are kept clear, so that `full()`, `count()`, complement and iteration only see those.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct PackedBitset<P, const N: usize>([P; N]);
+pub struct PackedBitset<P, const N: usize, const LEN: usize = { usize::MAX }>([P; N]);
 
-impl<P: FixedSizeBitset, const N: usize> PackedBitset<P, N> {
+impl<P: FixedSizeBitset, const N: usize, const LEN: usize> PackedBitset<P, N, LEN> {
     /// Extract the index of the nested bitset corresponding to the index.
     fn element_index(&self, index: usize) -> usize {
         index / P::fixed_capacity()
@@ -16,25 +23,59 @@ impl<P: FixedSizeBitset, const N: usize> PackedBitset<P, N> {
     fn bit_index(&self, index: usize) -> usize {
         index % P::fixed_capacity()
     }
+
+    // The nested bitsets, for other bitsets that build on this one to work on directly.
+    pub(super) fn words(&self) -> &[P; N] {
+        &self.0
+    }
+
+    pub(super) fn words_mut(&mut self) -> &mut [P; N] {
+        &mut self.0
+    }
 }
 
-impl<P: FixedSizeBitset, const N: usize> FixedSizeBitset for PackedBitset<P, N> {
+impl<P: FixedSizeBitset, const N: usize, const LEN: usize> FixedSizeBitset
+    for PackedBitset<P, N, LEN>
+{
     fn fixed_capacity() -> usize {
-        N * P::fixed_capacity()
+        LEN.min(N * P::fixed_capacity())
+    }
+}
+
+impl<P: FixedSizeBitset + BitsetOps + BitsetRangeOps + Copy, const N: usize, const LEN: usize>
+    PackedBitset<P, N, LEN>
+{
+    // Clear any bits past the logical length, after an operation that may have set them.
+    // When every bit is used, there is nothing to do.
+    fn masked(mut self) -> Self {
+        let len = Self::fixed_capacity();
+        if len < N * P::fixed_capacity() {
+            let word = len / P::fixed_capacity();
+            self.0[word].remove_range(len % P::fixed_capacity()..);
+            for p in &mut self.0[word + 1..] {
+                *p = P::empty();
+            }
+        }
+        self
     }
 }
 
-impl<P: FullBitset + Copy, const N: usize> FullBitset for PackedBitset<P, N> {
+impl<
+        P: FullBitset + FixedSizeBitset + BitsetOps + BitsetRangeOps + Copy,
+        const N: usize,
+        const LEN: usize,
+    > FullBitset for PackedBitset<P, N, LEN>
+{
     fn full() -> Self {
-        Self([P::full(); N])
+        Self([P::full(); N]).masked()
     }
 }
 
-impl<P: BitAndAssign + Copy, const N: usize> BitAnd for PackedBitset<P, N> {
+impl<P: BitAndAssign + Copy, const N: usize, const LEN: usize> BitAnd for PackedBitset<P, N, LEN> {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        let mut result = self.clone();
+        let mut result = self;
         for i in 0..N {
             result.0[i] &= rhs.0[i];
         }
@@ -42,7 +83,9 @@ impl<P: BitAndAssign + Copy, const N: usize> BitAnd for PackedBitset<P, N> {
     }
 }
 
-impl<P: BitAndAssign + Copy, const N: usize> BitAndAssign for PackedBitset<P, N> {
+impl<P: BitAndAssign + Copy, const N: usize, const LEN: usize> BitAndAssign
+    for PackedBitset<P, N, LEN>
+{
     fn bitand_assign(&mut self, rhs: Self) {
         for i in 0..N {
             self.0[i] &= rhs.0[i];
@@ -50,11 +93,11 @@ impl<P: BitAndAssign + Copy, const N: usize> BitAndAssign for PackedBitset<P, N>
     }
 }
 
-impl<P: BitOrAssign + Copy, const N: usize> BitOr for PackedBitset<P, N> {
+impl<P: BitOrAssign + Copy, const N: usize, const LEN: usize> BitOr for PackedBitset<P, N, LEN> {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        let mut result = self.clone();
+        let mut result = self;
         for i in 0..N {
             result.0[i] |= rhs.0[i];
         }
@@ -62,7 +105,9 @@ impl<P: BitOrAssign + Copy, const N: usize> BitOr for PackedBitset<P, N> {
     }
 }
 
-impl<P: BitOrAssign + Copy, const N: usize> BitOrAssign for PackedBitset<P, N> {
+impl<P: BitOrAssign + Copy, const N: usize, const LEN: usize> BitOrAssign
+    for PackedBitset<P, N, LEN>
+{
     fn bitor_assign(&mut self, rhs: Self) {
         for i in 0..N {
             self.0[i] |= rhs.0[i];
@@ -70,26 +115,267 @@ impl<P: BitOrAssign + Copy, const N: usize> BitOrAssign for PackedBitset<P, N> {
     }
 }
 
-impl<P: BitAndAssign + BitOrAssign + Copy, const N: usize> BitwiseOps for PackedBitset<P, N> {}
+impl<P: BitXorAssign + Copy, const N: usize, const LEN: usize> BitXor for PackedBitset<P, N, LEN> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let mut result = self;
+        for i in 0..N {
+            result.0[i] ^= rhs.0[i];
+        }
+        result
+    }
+}
+
+impl<P: BitXorAssign + Copy, const N: usize, const LEN: usize> BitXorAssign
+    for PackedBitset<P, N, LEN>
+{
+    fn bitxor_assign(&mut self, rhs: Self) {
+        for i in 0..N {
+            self.0[i] ^= rhs.0[i];
+        }
+    }
+}
+
+impl<P, const N: usize, const LEN: usize> Not for PackedBitset<P, N, LEN>
+where
+    P: Not<Output = P> + FixedSizeBitset + BitsetOps + BitsetRangeOps + Copy,
+{
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(self.0.map(|p| !p)).masked()
+    }
+}
+
+impl<P: SubAssign + Copy, const N: usize, const LEN: usize> Sub for PackedBitset<P, N, LEN> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let mut result = self;
+        for i in 0..N {
+            result.0[i] -= rhs.0[i];
+        }
+        result
+    }
+}
+
+impl<P: SubAssign + Copy, const N: usize, const LEN: usize> SubAssign for PackedBitset<P, N, LEN> {
+    fn sub_assign(&mut self, rhs: Self) {
+        for i in 0..N {
+            self.0[i] -= rhs.0[i];
+        }
+    }
+}
+
+// The queries are answered element by element, bailing out as soon as the answer is known.
+impl<P: BitwiseOps + Copy, const N: usize, const LEN: usize> BitwiseOps
+    for PackedBitset<P, N, LEN>
+{
+    fn is_subset(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .all(|(a, b)| a.is_subset(b))
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .all(|(a, b)| a.is_disjoint(b))
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| a.intersection_count(b))
+            .sum()
+    }
+}
+
+impl<P, const N: usize, const LEN: usize> ComplementOps for PackedBitset<P, N, LEN> where
+    P: Not<Output = P> + FixedSizeBitset + BitsetOps + BitsetRangeOps + Copy
+{
+}
+
+// Shifting is done as if the words were one wide integer.
+// Each word takes the bits from the word `n / width` before it, plus the carry from the one before that.
+// These are also used by the SIMD bitset, which shifts its lanes the same way.
+pub(super) fn shift_words_left<P, const N: usize>(words: [P; N], n: usize) -> [P; N]
+where
+    P: FixedSizeBitset + BitsetOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    let width = P::fixed_capacity();
+    let (word_shift, bit_shift) = (n / width, n % width);
+    let mut result = [P::empty(); N];
+    for i in word_shift..N {
+        let mut word = words[i - word_shift] << bit_shift;
+        if bit_shift > 0 && i > word_shift {
+            word = word | (words[i - word_shift - 1] >> (width - bit_shift));
+        }
+        result[i] = word;
+    }
+    result
+}
+
+pub(super) fn shift_words_right<P, const N: usize>(words: [P; N], n: usize) -> [P; N]
+where
+    P: FixedSizeBitset + BitsetOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    let width = P::fixed_capacity();
+    let (word_shift, bit_shift) = (n / width, n % width);
+    let mut result = [P::empty(); N];
+    for i in 0..N.saturating_sub(word_shift) {
+        let mut word = words[i + word_shift] >> bit_shift;
+        if bit_shift > 0 && i + word_shift + 1 < N {
+            word = word | (words[i + word_shift + 1] << (width - bit_shift));
+        }
+        result[i] = word;
+    }
+    result
+}
+
+impl<P, const N: usize, const LEN: usize> Shl<usize> for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + BitsetRangeOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    type Output = Self;
+
+    // Bits shifted up past the logical length fall off, just as they do past the last word.
+    fn shl(self, n: usize) -> Self::Output {
+        Self(shift_words_left(self.0, n)).masked()
+    }
+}
 
-impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize> BitsetOps for PackedBitset<P, N> {
+impl<P, const N: usize, const LEN: usize> ShlAssign<usize> for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + BitsetRangeOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    fn shl_assign(&mut self, n: usize) {
+        *self = *self << n;
+    }
+}
+
+impl<P, const N: usize, const LEN: usize> Shr<usize> for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    type Output = Self;
+
+    fn shr(self, n: usize) -> Self::Output {
+        Self(shift_words_right(self.0, n))
+    }
+}
+
+impl<P, const N: usize, const LEN: usize> ShrAssign<usize> for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + ShiftOps + BitOr<Output = P> + Copy,
+{
+    fn shr_assign(&mut self, n: usize) {
+        *self = *self >> n;
+    }
+}
+
+// A rotation is the bits shifted off one end, or'd back in at the other.
+impl<P, const N: usize, const LEN: usize> ShiftOps for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset
+        + BitsetOps
+        + BitsetRangeOps
+        + ShiftOps
+        + BitOr<Output = P>
+        + BitOrAssign
+        + Copy,
+{
+    fn rotate_left(self, n: usize) -> Self {
+        let n = n % Self::fixed_capacity();
+        if n == 0 {
+            self
+        } else {
+            (self << n) | (self >> (Self::fixed_capacity() - n))
+        }
+    }
+
+    fn rotate_right(self, n: usize) -> Self {
+        let n = n % Self::fixed_capacity();
+        if n == 0 {
+            self
+        } else {
+            (self >> n) | (self << (Self::fixed_capacity() - n))
+        }
+    }
+}
+
+// Runs cross word boundaries, so we hand the whole array of words to the iterator.
+impl<U: PrimInt, const N: usize, const LEN: usize> BitsetRunOps
+    for PackedBitset<PrimitiveBitset<U>, N, LEN>
+{
+    type Runs = RunsIterator<U, N>;
+
+    fn runs(&self) -> Self::Runs {
+        RunsIterator::runs(self.0.map(|p| p.bits), Self::fixed_capacity())
+    }
+
+    fn gaps(&self) -> Self::Runs {
+        RunsIterator::gaps(self.0.map(|p| p.bits), Self::fixed_capacity())
+    }
+}
+
+// The elements are laid out in order, so the bytes are just those of each element in turn.
+impl<P, const N: usize, const LEN: usize> BitsetByteOps for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + BitsetRangeOps + BitsetByteOps + Copy,
+{
+    // Only enough bytes to cover the logical length, so the padding never shows up.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.0.iter().flat_map(|p| p.to_bytes()).collect();
+        bytes.truncate(Self::fixed_capacity().div_ceil(8));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut result = Self::empty();
+        let mut chunks = bytes.chunks(P::fixed_capacity() / 8);
+        for (p, chunk) in result.0.iter_mut().zip(&mut chunks) {
+            *p = P::from_bytes(chunk)?;
+        }
+        if chunks.flatten().any(|&b| b != 0) {
+            return None;
+        }
+        // Bits past the logical length aren't elements we can hold.
+        let masked = result.masked();
+        if masked.count() != result.count() {
+            return None;
+        }
+        Some(masked)
+    }
+}
+
+impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize, const LEN: usize> BitsetOps
+    for PackedBitset<P, N, LEN>
+{
     fn empty() -> Self {
         Self([P::empty(); N])
     }
 
     fn insert(&mut self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
         let element_index = self.element_index(index);
         let bit_index = self.bit_index(index);
         self.0[element_index].insert(bit_index)
     }
 
     fn remove(&mut self, index: usize) {
+        check_index(index, Self::fixed_capacity());
         let element_index = self.element_index(index);
         let bit_index = self.bit_index(index);
         self.0[element_index].remove(bit_index);
     }
 
     fn contains(&self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
         let element_index = self.element_index(index);
         let bit_index = self.bit_index(index);
         self.0[element_index].contains(bit_index)
@@ -104,10 +390,28 @@ impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize> BitsetOps for Packed
     }
 }
 
-impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usize> BitsetRangeOps
-    for PackedBitset<P, N>
+impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize, const LEN: usize> BitsetTryOps
+    for PackedBitset<P, N, LEN>
 {
+}
+
+impl<
+        P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset,
+        const N: usize,
+        const LEN: usize,
+    > BitsetRangeOps for PackedBitset<P, N, LEN>
+{
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps,
+    {
+        let mut bitset = Self::empty();
+        bitset.insert_range(range);
+        bitset
+    }
+
     fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(i) => *i,
             Bound::Excluded(i) => *i + 1,
@@ -119,8 +423,13 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
             Bound::Unbounded => Self::fixed_capacity(),
         };
 
+        // An empty range might sit just past the last element, so we must not touch anything.
+        if start >= end {
+            return;
+        }
+
         let mut start_element_index = self.element_index(start);
-        let mut end_element_index = self.element_index(end);
+        let end_element_index = self.element_index(end);
         let start_bit_index = self.bit_index(start);
         let end_bit_index = self.bit_index(end);
 
@@ -144,18 +453,18 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
                 start_element_index += 1;
             }
 
-            if end_bit_index < Self::fixed_capacity() {
+            if end_bit_index > 0 {
                 // The edit fell within the last element, so handle the ending fragment.
+                // When the range runs to the end of the bitset, there is no such element.
                 unsafe {
                     self.0
                         .get_unchecked_mut(end_element_index)
                         .insert_range(..end_bit_index);
                 }
-                end_element_index -= 1;
             }
 
-            // Everyting from the start to end is now an entry that needs to be fully set.
-            for i in start_element_index..=end_element_index {
+            // Everyting from the start up to the end element is now an entry that needs to be fully set.
+            for i in start_element_index..end_element_index {
                 unsafe {
                     *self.0.get_unchecked_mut(i) = P::full();
                 }
@@ -164,6 +473,7 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
     }
 
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(i) => *i,
             Bound::Excluded(i) => *i + 1,
@@ -175,8 +485,13 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
             Bound::Unbounded => Self::fixed_capacity(),
         };
 
+        // An empty range might sit just past the last element, so we must not touch anything.
+        if start >= end {
+            return;
+        }
+
         let mut start_element_index = self.element_index(start);
-        let mut end_element_index = self.element_index(end);
+        let end_element_index = self.element_index(end);
         let start_bit_index = self.bit_index(start);
         let end_bit_index = self.bit_index(end);
 
@@ -200,18 +515,18 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
                 start_element_index += 1;
             }
 
-            if end_bit_index < Self::fixed_capacity() {
+            if end_bit_index > 0 {
                 // The edit fell within the last element, so handle the ending fragment.
+                // When the range runs to the end of the bitset, there is no such element.
                 unsafe {
                     self.0
                         .get_unchecked_mut(end_element_index)
                         .remove_range(..end_bit_index);
                 }
-                end_element_index -= 1;
             }
 
-            // Everyting from the start to end is now an entry that needs to be fully unset.
-            for i in start_element_index..=end_element_index {
+            // Everyting from the start up to the end element is now an entry that needs to be fully unset.
+            for i in start_element_index..end_element_index {
                 unsafe {
                     *self.0.get_unchecked_mut(i) = P::empty();
                 }
@@ -220,8 +535,86 @@ impl<P: BitsetOps + FixedSizeBitset + BitsetRangeOps + FullBitset, const N: usiz
     }
 }
 
-impl<P: FixedSizeBitset + BitsetOpsUnsafe + Copy, const N: usize> BitsetOpsUnsafe
-    for PackedBitset<P, N>
+// Whole elements are skipped using their counts, or by checking if they are empty,
+// and then the nested bitset answers the query within the element.
+impl<P: FixedSizeBitset + BitsetOps + BitsetRankOps + Copy, const N: usize, const LEN: usize>
+    BitsetRankOps for PackedBitset<P, N, LEN>
+{
+    fn rank(&self, index: usize) -> usize {
+        if index >= Self::fixed_capacity() {
+            return self.count();
+        }
+
+        let element_index = self.element_index(index);
+        let bit_index = self.bit_index(index);
+        let before: usize = self.0[..element_index].iter().map(|p| p.count()).sum();
+        before + self.0[element_index].rank(bit_index)
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        let mut k = k;
+        for (i, p) in self.0.iter().enumerate() {
+            let count = p.count();
+            if k < count {
+                return p.select(k).map(|b| i * P::fixed_capacity() + b);
+            }
+            k -= count;
+        }
+        None
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        if index >= Self::fixed_capacity().saturating_sub(1) {
+            return None;
+        }
+
+        let element_index = self.element_index(index);
+        let bit_index = self.bit_index(index);
+        if let Some(b) = self.0[element_index].next_set_after(bit_index) {
+            return Some(element_index * P::fixed_capacity() + b);
+        }
+
+        // The first element of the following non-empty nested bitset.
+        self.0[element_index + 1..]
+            .iter()
+            .enumerate()
+            .find_map(|(i, p)| {
+                p.select(0)
+                    .map(|b| (element_index + 1 + i) * P::fixed_capacity() + b)
+            })
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        if index == 0 {
+            return None;
+        }
+
+        // Past the end, everything is before the index, so we start from beyond the last element.
+        let (element_index, bit_index) = if index >= Self::fixed_capacity() {
+            (N, 0)
+        } else {
+            (self.element_index(index), self.bit_index(index))
+        };
+        if element_index < N {
+            if let Some(b) = self.0[element_index].prev_set_before(bit_index) {
+                return Some(element_index * P::fixed_capacity() + b);
+            }
+        }
+
+        // The last element of the preceding non-empty nested bitset.
+        self.0[..element_index]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, p)| {
+                p.prev_set_before(P::fixed_capacity())
+                    .map(|b| i * P::fixed_capacity() + b)
+            })
+    }
+}
+
+impl<P: FixedSizeBitset + BitsetOpsUnsafe + Copy, const N: usize, const LEN: usize> BitsetOpsUnsafe
+    for PackedBitset<P, N, LEN>
 {
     unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
         let element_index = self.element_index(index);
@@ -242,17 +635,51 @@ impl<P: FixedSizeBitset + BitsetOpsUnsafe + Copy, const N: usize> BitsetOpsUnsaf
     unsafe fn contains_unchecked(&self, index: usize) -> bool {
         let element_index = self.element_index(index);
         let bit_index = self.bit_index(index);
-        self.0.get_unchecked(element_index).contains_unchecked(bit_index)
+        self.0
+            .get_unchecked(element_index)
+            .contains_unchecked(bit_index)
     }
 }
 
-impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize> Default for PackedBitset<P, N> {
+impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize, const LEN: usize> Default
+    for PackedBitset<P, N, LEN>
+{
     fn default() -> Self {
         Self::empty()
     }
 }
 
-impl<P: FixedSizeBitset, const N: usize> IntoIterator for &PackedBitset<P, N>
+impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize, const LEN: usize> Extend<usize>
+    for PackedBitset<P, N, LEN>
+{
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<P: FixedSizeBitset + BitsetOps + Copy, const N: usize, const LEN: usize> FromIterator<usize>
+    for PackedBitset<P, N, LEN>
+{
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<P: FixedSizeBitset, const N: usize, const LEN: usize> std::fmt::Display
+    for PackedBitset<P, N, LEN>
+where
+    for<'a> &'a P: IntoIterator<IntoIter: DoubleEndedIterator<Item = usize>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_set(f, self)
+    }
+}
+
+impl<P: FixedSizeBitset, const N: usize, const LEN: usize> IntoIterator for &PackedBitset<P, N, LEN>
 where
     for<'a> &'a P: IntoIterator<IntoIter: DoubleEndedIterator<Item = usize>>,
 {
@@ -296,41 +723,563 @@ mod tests {
 
     const TEST_PACKED_SIZE: usize = 8;
     type PackedBitsetTest<U> = PackedBitset<PrimitiveBitset<U>, TEST_PACKED_SIZE>;
-    
+
     type PackedBitsetTestU8 = PackedBitsetTest<u8>;
     type PackedBitsetTestU16 = PackedBitsetTest<u16>;
     type PackedBitsetTestU32 = PackedBitsetTest<u32>;
     type PackedBitsetTestU64 = PackedBitsetTest<u64>;
     type PackedBitsetTestU128 = PackedBitsetTest<u128>;
 
-    crate::generate_tests!(test_empty, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_empty,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_set_get,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_set_unset_get,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_unset,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
+    crate::generate_tests!(
+        test_set_all,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_get, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_and,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_unset_get, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_and_assign,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_unset, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_or,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_all, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_or_assign,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_bitwise_and, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_empty_iterator,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_bitwise_and_assign, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_empty_iterator_back,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_bitwise_or, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_set_one_bit_iterator,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_bitwise_or_assign, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_one_bit_iterator_back,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_empty_iterator, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_set_two_bit_iterator,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_empty_iterator_back, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_set_two_bit_iterator_back,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_one_bit_iterator, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_xor,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_one_bit_iterator_back, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_bitwise_xor_assign,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_two_bit_iterator, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_difference,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
 
-    crate::generate_tests!(test_set_two_bit_iterator_back, PackedBitsetTestU8, PackedBitsetTestU16, PackedBitsetTestU32, PackedBitsetTestU64, PackedBitsetTestU128);
+    crate::generate_tests!(
+        test_difference_assign,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_complement,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_subset_superset,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_disjoint,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_intersection_count,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_rank,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_select,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_next_set_after,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_prev_set_before,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_shift_left,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_shift_right,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_rotate,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_bytes_round_trip,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_bytes_padding,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_from_iterator,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_from_iterator_out_of_range,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_from_range,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_display,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_try_ops,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_conformance,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    crate::generate_tests!(
+        test_runs_and_gaps,
+        PackedBitsetTestU8,
+        PackedBitsetTestU16,
+        PackedBitsetTestU32,
+        PackedBitsetTestU64,
+        PackedBitsetTestU128
+    );
+
+    // A logical length that stops part way through a word, with a whole word of padding after it in the u16 case.
+    type PackedBitsetPaddedU16 = PackedBitset<PrimitiveBitset<u16>, 9, 130>;
+    type PackedBitsetPaddedU64 = PackedBitset<PrimitiveBitset<u64>, 3, 130>;
+    // A length that ends part way through the last word, so a whole-word fill overruns it.
+    type PackedBitsetU64Len100 = PackedBitset<PrimitiveBitset<u64>, 2, 100>;
+
+    crate::generate_tests!(test_empty, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_full, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_set_get, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_unset, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_set_range, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_set_unset_get,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_unset_range,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(test_set_all, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_bitwise_and,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_bitwise_or,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_bitwise_xor,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_difference,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_complement,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_subset_superset,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(test_disjoint, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_intersection_count,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(test_rank, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_select, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_next_set_after,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_prev_set_before,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_shift_left,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_shift_right,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(test_rotate, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_empty_iterator,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_empty_iterator_back,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_set_one_bit_iterator,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_one_bit_iterator_back,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator_back,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_from_iterator,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_from_iterator_out_of_range,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_from_range,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(test_display, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(test_try_ops, PackedBitsetPaddedU16, PackedBitsetPaddedU64);
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_aligned_ranges_stop_at_the_capacity,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64,
+        PackedBitsetU64Len100
+    );
+    crate::generate_tests!(
+        test_conformance,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_runs_and_gaps,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_padding_stays_clear,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+    crate::generate_tests!(
+        test_bytes_cover_only_the_bits,
+        PackedBitsetPaddedU16,
+        PackedBitsetPaddedU64
+    );
+
+    // When a range ends on a word boundary there's no last fragment to edit,
+    // and for a range up to the capacity the word after it is past the end of the array.
+    #[test]
+    fn ranges_can_end_at_the_capacity() {
+        let capacity = PackedBitsetTestU8::fixed_capacity();
+        let mut bitset = PackedBitsetTestU8::empty();
+        bitset.insert_range(capacity - 12..capacity);
+        assert_eq!(bitset.count(), 12);
+        assert!(bitset.contains(capacity - 1));
+        bitset.remove_range(capacity - 8..capacity);
+        assert_eq!(bitset.count(), 4);
+        assert!(!bitset.contains(capacity - 1));
+
+        bitset.insert_range(8..16);
+        assert_eq!(
+            bitset.runs().collect::<Vec<_>>(),
+            vec![8..16, capacity - 12..capacity - 8]
+        );
+    }
 }
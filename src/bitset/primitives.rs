@@ -1,3 +1,4 @@
+use super::runs::RunsIterator;
 use super::*;
 use num::{traits::WrappingSub, One, PrimInt, Unsigned};
 use std::fmt::Binary;
@@ -8,7 +9,7 @@ use std::iter::IntoIterator;
 ///- The implementations assume that you will use all the bits in the underlying integer.
 ///- They can be composed into bitsets with other behaviours, or used directly.
 use std::mem::size_of;
-use std::ops::Bound;
+use std::ops::{BitXor, BitXorAssign, Bound, Not, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign};
 
 /// A bitset implementation that uses a single unsigned integer, and contains one element per bit.
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
@@ -16,6 +17,12 @@ pub struct PrimitiveBitset<U> {
     pub bits: U,
 }
 
+impl<U: PrimInt + WrappingSub + BitAndAssign> std::fmt::Display for PrimitiveBitset<U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_set(f, self)
+    }
+}
+
 impl<U: Binary> std::fmt::Debug for PrimitiveBitset<U> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let width = core::mem::size_of::<U>() * 8;
@@ -69,9 +76,153 @@ impl<U: BitOrAssign> BitOrAssign for PrimitiveBitset<U> {
     }
 }
 
-impl<U: BitAnd<Output = U> + BitAndAssign + BitOr<Output = U> + BitOrAssign> BitwiseOps
-    for PrimitiveBitset<U>
-{
+impl<U: BitXor<Output = U>> BitXor for PrimitiveBitset<U> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits ^ rhs.bits,
+        }
+    }
+}
+
+impl<U: BitXorAssign> BitXorAssign for PrimitiveBitset<U> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.bits ^= rhs.bits;
+    }
+}
+
+impl<U: Not<Output = U>> Not for PrimitiveBitset<U> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self { bits: !self.bits }
+    }
+}
+
+// Set difference is `a & !b`, which is a single `andn` instruction on most targets.
+impl<U: BitAnd<Output = U> + Not<Output = U>> Sub for PrimitiveBitset<U> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits & !rhs.bits,
+        }
+    }
+}
+
+impl<U: BitAndAssign + Not<Output = U> + Copy> SubAssign for PrimitiveBitset<U> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.bits &= !rhs.bits;
+    }
+}
+
+impl<U: PrimInt + BitAndAssign + BitOrAssign + BitXorAssign> BitwiseOps for PrimitiveBitset<U> {
+    fn is_subset(&self, other: &Self) -> bool {
+        self.bits & !other.bits == U::zero()
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.bits & other.bits == U::zero()
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        (self.bits & other.bits).count_ones() as usize
+    }
+}
+
+impl<U: Not<Output = U>> ComplementOps for PrimitiveBitset<U> {}
+
+// Shifting an integer by its full width or more is an overflow, but for a bitset it just empties it.
+impl<U: PrimInt> Shl<usize> for PrimitiveBitset<U> {
+    type Output = Self;
+
+    fn shl(self, n: usize) -> Self::Output {
+        if n >= Self::fixed_capacity() {
+            Self::empty()
+        } else {
+            Self {
+                bits: self.bits << n,
+            }
+        }
+    }
+}
+
+impl<U: PrimInt> ShlAssign<usize> for PrimitiveBitset<U> {
+    fn shl_assign(&mut self, n: usize) {
+        *self = *self << n;
+    }
+}
+
+impl<U: PrimInt> Shr<usize> for PrimitiveBitset<U> {
+    type Output = Self;
+
+    fn shr(self, n: usize) -> Self::Output {
+        if n >= Self::fixed_capacity() {
+            Self::empty()
+        } else {
+            Self {
+                bits: self.bits >> n,
+            }
+        }
+    }
+}
+
+impl<U: PrimInt> ShrAssign<usize> for PrimitiveBitset<U> {
+    fn shr_assign(&mut self, n: usize) {
+        *self = *self >> n;
+    }
+}
+
+impl<U: PrimInt> ShiftOps for PrimitiveBitset<U> {
+    fn rotate_left(self, n: usize) -> Self {
+        Self {
+            bits: self.bits.rotate_left((n % Self::fixed_capacity()) as u32),
+        }
+    }
+
+    fn rotate_right(self, n: usize) -> Self {
+        Self {
+            bits: self.bits.rotate_right((n % Self::fixed_capacity()) as u32),
+        }
+    }
+}
+
+impl<U: PrimInt> BitsetRunOps for PrimitiveBitset<U> {
+    type Runs = RunsIterator<U, 1>;
+
+    fn runs(&self) -> Self::Runs {
+        RunsIterator::runs([self.bits], Self::fixed_capacity())
+    }
+
+    fn gaps(&self) -> Self::Runs {
+        RunsIterator::gaps([self.bits], Self::fixed_capacity())
+    }
+}
+
+impl<U: PrimInt> BitsetByteOps for PrimitiveBitset<U> {
+    // Pull the bytes out low end first, so that we're little-endian on any machine.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mask = U::from(u8::MAX).unwrap();
+        (0..Self::fixed_capacity() / 8)
+            .map(|i| ((self.bits >> (i * 8)) & mask).to_u8().unwrap())
+            .collect()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let width = Self::fixed_capacity() / 8;
+        if bytes.iter().skip(width).any(|&b| b != 0) {
+            return None;
+        }
+        let bits = bytes
+            .iter()
+            .take(width)
+            .enumerate()
+            .fold(U::zero(), |bits, (i, &b)| {
+                bits | (U::from(b).unwrap() << (i * 8))
+            });
+        Some(Self { bits })
+    }
 }
 
 impl<U: PrimInt> BitsetOps for PrimitiveBitset<U> {
@@ -80,6 +231,7 @@ impl<U: PrimInt> BitsetOps for PrimitiveBitset<U> {
     }
 
     fn insert(&mut self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
         let to_set = U::one() << index;
         let was_set = self.bits & to_set != U::zero();
         self.bits = self.bits | to_set;
@@ -87,10 +239,12 @@ impl<U: PrimInt> BitsetOps for PrimitiveBitset<U> {
     }
 
     fn remove(&mut self, index: usize) {
+        check_index(index, Self::fixed_capacity());
         self.bits = self.bits & !(U::one() << index);
     }
 
     fn contains(&self, index: usize) -> bool {
+        check_index(index, Self::fixed_capacity());
         self.bits & U::one() << index != U::zero()
     }
 
@@ -99,8 +253,36 @@ impl<U: PrimInt> BitsetOps for PrimitiveBitset<U> {
     }
 }
 
+impl<U: PrimInt> Extend<usize> for PrimitiveBitset<U> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<U: PrimInt> FromIterator<usize> for PrimitiveBitset<U> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<U: PrimInt> BitsetTryOps for PrimitiveBitset<U> {}
+
 impl<U: PrimInt> BitsetRangeOps for PrimitiveBitset<U> {
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps,
+    {
+        let mut bitset = Self::empty();
+        bitset.insert_range(range);
+        bitset
+    }
+
     fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(i) => *i,
             Bound::Excluded(i) => *i + 1,
@@ -111,7 +293,11 @@ impl<U: PrimInt> BitsetRangeOps for PrimitiveBitset<U> {
             Bound::Excluded(i) => *i,
             Bound::Unbounded => Self::fixed_capacity(),
         };
-        if end >= Self::fixed_capacity() {
+        // An empty range can start at the capacity, which would overflow the shifts below.
+        if start >= end {
+            return;
+        }
+        if end == Self::fixed_capacity() {
             self.bits = self.bits | (!U::zero() << start)
         } else {
             self.bits = self.bits | (U::one() << end) - (U::one() << start);
@@ -119,6 +305,7 @@ impl<U: PrimInt> BitsetRangeOps for PrimitiveBitset<U> {
     }
 
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(i) => *i,
             Bound::Excluded(i) => *i + 1,
@@ -129,7 +316,11 @@ impl<U: PrimInt> BitsetRangeOps for PrimitiveBitset<U> {
             Bound::Excluded(i) => *i,
             Bound::Unbounded => Self::fixed_capacity(),
         };
-        if end >= Self::fixed_capacity() {
+        // An empty range can start at the capacity, which would overflow the shifts below.
+        if start >= end {
+            return;
+        }
+        if end == Self::fixed_capacity() {
             self.bits = self.bits & !(!U::zero() << start)
         } else {
             let end_mask = U::one() << end;
@@ -139,17 +330,91 @@ impl<U: PrimInt> BitsetRangeOps for PrimitiveBitset<U> {
     }
 }
 
+// These are all a mask followed by a single `count_ones`, `trailing_zeros` or `leading_zeros`.
+// We have to be careful with the masks, as shifting by the full width of `U` overflows.
+impl<U: PrimInt + WrappingSub> BitsetRankOps for PrimitiveBitset<U> {
+    fn rank(&self, index: usize) -> usize {
+        if index >= Self::fixed_capacity() {
+            self.count()
+        } else {
+            (self.bits & (U::one() << index).wrapping_sub(&U::one())).count_ones() as usize
+        }
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        if k >= self.count() {
+            return None;
+        }
+
+        // Binary search on the counts of the low halves, shifting down past any half that's too small,
+        // so it's a handful of popcounts rather than one step per element.
+        let mut bits = self.bits;
+        let mut k = k as u32;
+        let mut offset = 0;
+        let mut width = Self::fixed_capacity();
+        while width > 8 {
+            width /= 2;
+            let low = (bits & ((U::one() << width) - U::one())).count_ones();
+            if k >= low {
+                k -= low;
+                bits = bits >> width;
+                offset += width;
+            }
+        }
+
+        // We're down to a byte, so knock off the lowest `k` elements, and the one we want is now the lowest.
+        for _ in 0..k {
+            bits = bits & bits.wrapping_sub(&U::one());
+        }
+        Some(offset + bits.trailing_zeros() as usize)
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        if index >= Self::fixed_capacity().saturating_sub(1) {
+            return None;
+        }
+
+        let above = self.bits & (!U::zero() << (index + 1));
+        if above == U::zero() {
+            None
+        } else {
+            Some(above.trailing_zeros() as usize)
+        }
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        if index == 0 {
+            return None;
+        }
+
+        let below = if index >= Self::fixed_capacity() {
+            self.bits
+        } else {
+            self.bits & (U::one() << index).wrapping_sub(&U::one())
+        };
+        if below == U::zero() {
+            None
+        } else {
+            Some(Self::fixed_capacity() - 1 - below.leading_zeros() as usize)
+        }
+    }
+}
+
 impl<U: Unsigned + PrimInt> BitsetOpsUnsafe for PrimitiveBitset<U> {
+    // The same as the safe versions, less the index check.
     unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
-        BitsetOps::insert(self, index)
+        let to_set = U::one() << index;
+        let was_set = self.bits & to_set != U::zero();
+        self.bits = self.bits | to_set;
+        !was_set
     }
 
     unsafe fn remove_unchecked(&mut self, index: usize) {
-        BitsetOps::remove(self, index);
+        self.bits = self.bits & !(U::one() << index);
     }
 
     unsafe fn contains_unchecked(&self, index: usize) -> bool {
-        BitsetOps::contains(self, index)
+        self.bits & U::one() << index != U::zero()
     }
 }
 
@@ -273,4 +538,221 @@ mod tests {
         U64Bitset,
         U128Bitset
     );
+    crate::generate_tests!(
+        test_bitwise_xor,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_bitwise_xor_assign,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_difference,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_difference_assign,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_complement,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_subset_superset,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_disjoint,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_intersection_count,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(test_rank, U8Bitset, U16Bitset, U32Bitset, U64Bitset, U128Bitset);
+    crate::generate_tests!(
+        test_select,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_next_set_after,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_prev_set_before,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_shift_left,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_shift_right,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_rotate,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_bytes_round_trip,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_bytes_padding,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_from_iterator,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_from_iterator_out_of_range,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_from_range,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_display,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_try_ops,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_conformance,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+    crate::generate_tests!(
+        test_runs_and_gaps,
+        U8Bitset,
+        U16Bitset,
+        U32Bitset,
+        U64Bitset,
+        U128Bitset
+    );
+
+    // An empty range that starts at the capacity used to shift by the full width, which overflows.
+    #[test]
+    fn empty_ranges_at_the_capacity() {
+        let mut bitset = U64Bitset::from_range(60..);
+        bitset.insert_range(64..64);
+        bitset.insert_range(64..);
+        bitset.remove_range(64..64);
+        bitset.remove_range(64..);
+        assert_eq!(bitset, U64Bitset::from_range(60..64));
+
+        let mut small = U8Bitset::empty();
+        small.insert_range(8..);
+        small.remove_range(8..);
+        assert_eq!(small, U8Bitset::empty());
+    }
 }
@@ -0,0 +1,170 @@
+///- Iterating over runs of consecutive elements, rather than one element at a time.
+///- This works on a copy of the words of a bitset, so it is shared by the primitive, packed and SIMD bitsets.
+///- Rather than testing bits one by one, we shift the current position down to bit zero of its word,
+///- then `trailing_zeros` finds where a run starts and `trailing_ones` finds where it stops.
+///- Going backwards is the same, but shifting up to the top bit and counting leading bits.
+use num::PrimInt;
+use std::mem::size_of;
+use std::ops::Range;
+
+/// The maximal runs of set bits in some words, as ranges of indices.
+///
+/// Gaps are the runs of a complemented copy of the words,
+/// which we get for free by xor-ing each word with a `flip` mask as we read it.
+#[derive(Clone, Debug)]
+pub struct RunsIterator<U, const N: usize> {
+    words: [U; N],
+    flip: U,
+    // Only runs within `front..back` are left to yield.
+    front: usize,
+    back: usize,
+}
+
+impl<U: PrimInt, const N: usize> RunsIterator<U, N> {
+    /// The runs of set bits in the first `len` bits of `words`.
+    pub(crate) fn runs(words: [U; N], len: usize) -> Self {
+        Self {
+            words,
+            flip: U::zero(),
+            front: 0,
+            back: len,
+        }
+    }
+
+    /// The runs of unset bits in the first `len` bits of `words`.
+    pub(crate) fn gaps(words: [U; N], len: usize) -> Self {
+        Self {
+            words,
+            flip: U::max_value(),
+            front: 0,
+            back: len,
+        }
+    }
+
+    const BITS: usize = size_of::<U>() * 8;
+
+    fn word(&self, index: usize) -> U {
+        self.words[index] ^ self.flip
+    }
+}
+
+impl<U: PrimInt, const N: usize> Iterator for RunsIterator<U, N> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Find the first set bit, skipping whole words of zeros.
+        let start = loop {
+            if self.front >= self.back {
+                return None;
+            }
+            let (w, b) = (self.front / Self::BITS, self.front % Self::BITS);
+            let x = self.word(w) >> b;
+            if x.is_zero() {
+                self.front = (w + 1) * Self::BITS;
+            } else {
+                break self.front + x.trailing_zeros() as usize;
+            }
+        };
+        if start >= self.back {
+            self.front = self.back;
+            return None;
+        }
+
+        // Then the first unset bit after it, skipping whole words of ones.
+        let mut end = start;
+        while end < self.back {
+            let (w, b) = (end / Self::BITS, end % Self::BITS);
+            let ones = (self.word(w) >> b).trailing_ones() as usize;
+            end += ones;
+            if ones < Self::BITS - b {
+                break;
+            }
+        }
+        let end = end.min(self.back);
+
+        self.front = end;
+        Some(start..end)
+    }
+}
+
+impl<U: PrimInt, const N: usize> DoubleEndedIterator for RunsIterator<U, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // Find the last set bit, skipping whole words of zeros.
+        let last = loop {
+            if self.back <= self.front {
+                return None;
+            }
+            let index = self.back - 1;
+            let (w, b) = (index / Self::BITS, index % Self::BITS);
+            let x = self.word(w) << (Self::BITS - 1 - b);
+            if x.is_zero() {
+                self.back = w * Self::BITS;
+            } else {
+                break index - x.leading_zeros() as usize;
+            }
+        };
+        if last < self.front {
+            self.back = self.front;
+            return None;
+        }
+
+        // Then walk down to the first bit of its run, skipping whole words of ones.
+        // `start` is one past the next bit to look at, so it can't underflow.
+        let mut start = last + 1;
+        while start > self.front {
+            let index = start - 1;
+            let (w, b) = (index / Self::BITS, index % Self::BITS);
+            let ones = (self.word(w) << (Self::BITS - 1 - b)).leading_ones() as usize;
+            start -= ones;
+            if ones < b + 1 {
+                break;
+            }
+        }
+        let start = start.max(self.front);
+
+        self.back = start;
+        Some(start..last + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_across_words() {
+        let words: [u8; 3] = [0b1100_0001, 0b1111_1111, 0b0000_0101];
+        let runs = RunsIterator::runs(words, 24);
+        assert_eq!(runs.clone().collect::<Vec<_>>(), vec![0..1, 6..17, 18..19]);
+        assert_eq!(runs.rev().collect::<Vec<_>>(), vec![18..19, 6..17, 0..1]);
+
+        let gaps = RunsIterator::gaps(words, 24);
+        assert_eq!(gaps.clone().collect::<Vec<_>>(), vec![1..6, 17..18, 19..24]);
+        assert_eq!(gaps.rev().collect::<Vec<_>>(), vec![19..24, 17..18, 1..6]);
+    }
+
+    #[test]
+    fn runs_are_cut_off_at_the_length() {
+        // Only the first 12 bits count, so the run over the word boundary stops there.
+        let words: [u8; 2] = [0b1000_0000, 0b1111_1111];
+        assert_eq!(
+            RunsIterator::runs(words, 12).collect::<Vec<_>>(),
+            vec![7..12]
+        );
+        assert_eq!(
+            RunsIterator::gaps(words, 12).collect::<Vec<_>>(),
+            vec![0..7]
+        );
+    }
+
+    #[test]
+    fn both_ends_meet_in_the_middle() {
+        let words: [u16; 1] = [0b0111_0000_1111_0011];
+        let mut runs = RunsIterator::runs(words, 16);
+        assert_eq!(runs.next(), Some(0..2));
+        assert_eq!(runs.next_back(), Some(12..15));
+        assert_eq!(runs.next_back(), Some(4..8));
+        assert_eq!(runs.next(), None);
+        assert_eq!(runs.next_back(), None);
+    }
+}
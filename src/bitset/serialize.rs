@@ -0,0 +1,291 @@
+///- Serde support for the bitsets, behind the `serde` feature.
+///- Everything is written as the bytes from `BitsetByteOps`, so the serialised form is as compact as it can be,
+///- and the same whichever format it goes into.
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::BitAndAssign;
+use std::simd::SimdElement;
+
+use num::{traits::WrappingSub, PrimInt};
+use serde::de::{self, SeqAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::atomic::AtomicBitset;
+use super::auto::AutoBitset;
+use super::dynamic::DynBitset;
+#[cfg(feature = "exact")]
+use super::exact::{packed_words, primitive_bits, ExactBitset, Layout, Select};
+use super::hierarchical::HierarchicalBitset;
+use super::matrix::BitMatrix;
+use super::packed::PackedBitset;
+use super::primitives::PrimitiveBitset;
+use super::simd::SimdBitset;
+use super::sparse::{Components, SparseBitset};
+use super::{BitsetByteOps, BitsetOps, BitsetRangeOps, FixedSizeBitset, PackedU64Bitset};
+
+fn serialize_bytes<S: Serializer, BS: BitsetByteOps>(
+    bitset: &BS,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(&bitset.to_bytes())
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>, BS: BitsetByteOps>(
+    deserializer: D,
+) -> Result<BS, D::Error> {
+    deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+}
+
+struct BytesVisitor<BS>(PhantomData<BS>);
+
+impl<'de, BS: BitsetByteOps> Visitor<'de> for BytesVisitor<BS> {
+    type Value = BS;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the bytes of a bitset")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<BS, E> {
+        BS::from_bytes(v).ok_or_else(|| E::invalid_value(Unexpected::Bytes(v), &self))
+    }
+
+    // Formats without a bytes type, like JSON, give us a sequence of numbers instead.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BS, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+impl<U: PrimInt> Serialize for PrimitiveBitset<U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, U: PrimInt> Deserialize<'de> for PrimitiveBitset<U> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl<P, const N: usize, const LEN: usize> Serialize for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + BitsetRangeOps + BitsetByteOps + Copy,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, P, const N: usize, const LEN: usize> Deserialize<'de> for PackedBitset<P, N, LEN>
+where
+    P: FixedSizeBitset + BitsetOps + BitsetRangeOps + BitsetByteOps + Copy,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl<T: SimdElement + PrimInt, const N: usize, const LEN: usize> Serialize
+    for SimdBitset<T, N, LEN>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, T: SimdElement + PrimInt, const N: usize, const LEN: usize> Deserialize<'de>
+    for SimdBitset<T, N, LEN>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt + WrappingSub + BitAndAssign> Serialize for SparseBitset<C, U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, C: Components<U>, U: PrimInt + WrappingSub + BitAndAssign> Deserialize<'de>
+    for SparseBitset<C, U>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl<const N: usize> Serialize for AutoBitset<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for AutoBitset<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+#[cfg(feature = "exact")]
+impl<const BITS: usize> Serialize for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+#[cfg(feature = "exact")]
+impl<'de, const BITS: usize> Deserialize<'de> for ExactBitset<BITS>
+where
+    Select<{ primitive_bits(BITS) }, { packed_words(BITS) }>: Layout,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl<const N: usize, const M: usize> Serialize for HierarchicalBitset<N, M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, const N: usize, const M: usize> Deserialize<'de> for HierarchicalBitset<N, M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl Serialize for DynBitset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DynBitset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+// An atomic bitset is written as a snapshot of its words, and read back into fresh atomics.
+impl<const N: usize> Serialize for AtomicBitset<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.snapshot(), serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for AtomicBitset<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes::<_, PackedU64Bitset<N>>(deserializer).map(Self::from)
+    }
+}
+
+impl<Row, const R: usize> Serialize for BitMatrix<Row, R>
+where
+    Row: BitsetOps + FixedSizeBitset + BitsetByteOps + Copy,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+}
+
+impl<'de, Row, const R: usize> Deserialize<'de> for BitMatrix<Row, R>
+where
+    Row: BitsetOps + FixedSizeBitset + BitsetByteOps + Copy,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "exact")]
+    use super::super::Bitset;
+    use super::super::{
+        ArraySparseU64Bitset, AutoBitset, DynBitset, FullBitset, HierarchicalBitset,
+        PackedU8Bitset, SimdU16Bitset4, SparseU16Bitset, U128Bitset, U16Bitset, U64Bitset,
+        U8Bitset,
+    };
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    fn round_trip<BS: Serialize + DeserializeOwned + PartialEq + fmt::Debug>(bitset: BS) {
+        let json = serde_json::to_string(&bitset).unwrap();
+        let back: BS = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, bitset, "{}", json);
+    }
+
+    fn from_values<BS: BitsetOps>(values: &[usize]) -> BS {
+        let mut bitset = BS::empty();
+        for &v in values {
+            bitset.insert(v);
+        }
+        bitset
+    }
+
+    #[test]
+    fn fixed_size_round_trips() {
+        let values = [0, 1, 9, 31, 63];
+        round_trip(from_values::<U64Bitset>(&values));
+        round_trip(from_values::<PackedU8Bitset<8>>(&values));
+        round_trip(from_values::<SimdU16Bitset4>(&values));
+        round_trip(from_values::<AutoBitset<2>>(&values));
+        round_trip(from_values::<HierarchicalBitset<2, 1>>(&values));
+        round_trip(PackedBitset::<U16Bitset, 9, 130>::full());
+        round_trip(SimdBitset::<u64, 4, 130>::full());
+        round_trip(U128Bitset::full());
+    }
+
+    #[cfg(feature = "exact")]
+    #[test]
+    fn exact_round_trips() {
+        round_trip(from_values::<Bitset<70>>(&[0, 1, 9, 31, 63]));
+        round_trip(Bitset::<130>::full());
+    }
+
+    #[test]
+    fn growable_round_trips() {
+        let values = [0, 1, 9, 1000, 100_000];
+        round_trip(from_values::<DynBitset>(&values));
+        round_trip(from_values::<SparseU16Bitset>(&values));
+        round_trip(from_values::<ArraySparseU64Bitset<4>>(&values));
+    }
+
+    #[test]
+    fn atomic_round_trips() {
+        let bitset = AtomicBitset::<2>::from(from_values::<PackedU64Bitset<2>>(&[0, 9, 64, 127]));
+        let json = serde_json::to_string(&bitset).unwrap();
+        let back: AtomicBitset<2> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.snapshot(), bitset.snapshot(), "{}", json);
+        assert_eq!(json, serde_json::to_string(&bitset.snapshot()).unwrap());
+    }
+
+    #[test]
+    fn matrix_round_trips() {
+        let mut matrix = BitMatrix::<U16Bitset, 5>::empty();
+        matrix.insert(0, 15);
+        matrix.insert(4, 3);
+        round_trip(matrix);
+    }
+
+    #[test]
+    fn serialises_as_bytes() {
+        let bitset: U16Bitset = from_values(&[0, 9]);
+        assert_eq!(serde_json::to_string(&bitset).unwrap(), "[1,2]");
+    }
+
+    #[test]
+    fn rejects_elements_that_do_not_fit() {
+        assert!(serde_json::from_str::<U8Bitset>("[1,2]").is_err());
+        assert!(serde_json::from_str::<U16Bitset>("[1,2]").is_ok());
+    }
+}
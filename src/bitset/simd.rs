@@ -1,35 +1,41 @@
+use super::packed::{shift_words_left, shift_words_right};
+use super::primitives::PrimitiveBitset;
+use super::runs::RunsIterator;
 use super::*;
-use std::fmt::{Debug, Binary};
+use num::{traits::WrappingSub, One, PrimInt};
+use std::fmt::{Binary, Debug};
 use std::iter::IntoIterator;
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Bound, Not, Shl};
-use std::simd::{Simd, SimdElement, LaneCount, SupportedLaneCount};
-use num::{traits::WrappingSub, Zero, One, PrimInt};
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, Not, Shl, ShlAssign,
+    Shr, ShrAssign, Sub, SubAssign,
+};
+use std::simd::{Simd, SimdElement};
 
 /// A bitset implementation using SIMD vector types.
 /// This provides efficient bitwise operations on large sets of bits.
+///
+/// Like `PackedBitset`, a `LEN` smaller than the lanes can hold limits the bitset to its first `LEN` elements,
+/// with the bits past it kept clear.
 #[derive(Clone, PartialEq, Eq, Copy)]
-pub struct SimdBitset<T, const N: usize>
+pub struct SimdBitset<T, const N: usize, const LEN: usize = { usize::MAX }>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount
 {
     pub bits: Simd<T, N>,
 }
 
-// impl<T, const N: usize> Default for SimdBitset<T, N>
+// impl<T, const N: usize, const LEN: usize> Default for SimdBitset<T, N, LEN>
 // where
 //     T: SimdElement,
-//     LaneCount<N>: SupportedLaneCount
 // {
 //     fn default() -> Self {
 //         Self::empty()
 //     }
 // }
 
-impl<T, const N: usize> Debug for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> Debug for SimdBitset<T, N, LEN>
 where
     T: SimdElement + Binary,
-    LaneCount<N>: SupportedLaneCount,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "SimdBitset(")?;
@@ -45,20 +51,46 @@ where
     }
 }
 
-impl<T, const N: usize> FixedSizeBitset for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> FixedSizeBitset for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
 {
     fn fixed_capacity() -> usize {
-        N * std::mem::size_of::<T>() * 8
+        LEN.min(N * std::mem::size_of::<T>() * 8)
     }
 }
 
-impl<T, const N: usize> FullBitset for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> SimdBitset<T, N, LEN>
 where
-    T: SimdElement + Zero + Not<Output = T>,
-    LaneCount<N>: SupportedLaneCount,
+    T: SimdElement + PrimInt,
+{
+    // Clear any bits past the logical length, lane by lane.
+    // When every bit is used, there is nothing to do.
+    fn masked(self) -> Self {
+        let bits_per_element = std::mem::size_of::<T>() * 8;
+        let len = Self::fixed_capacity();
+        if len == N * bits_per_element {
+            return self;
+        }
+
+        let mut lanes = self.bits.to_array();
+        for (i, x) in lanes.iter_mut().enumerate() {
+            let start = i * bits_per_element;
+            if start >= len {
+                *x = T::zero();
+            } else if len - start < bits_per_element {
+                *x = *x & !(!T::zero() << (len - start));
+            }
+        }
+        Self {
+            bits: Simd::from_array(lanes),
+        }
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> FullBitset for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
 {
     fn full() -> Self {
         // Create a value with all bits set
@@ -66,26 +98,27 @@ where
         Self {
             bits: Simd::splat(all_ones),
         }
+        .masked()
     }
 }
 
-impl<T, const N: usize> BitAnd for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitAnd for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
     Simd<T, N>: BitAnd<Output = Simd<T, N>>,
 {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        Self { bits: self.bits & rhs.bits }
+        Self {
+            bits: self.bits & rhs.bits,
+        }
     }
 }
 
-impl<T, const N: usize> BitAndAssign for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitAndAssign for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
     Simd<T, N>: BitAndAssign<Simd<T, N>>,
 {
     fn bitand_assign(&mut self, rhs: Self) {
@@ -93,23 +126,23 @@ where
     }
 }
 
-impl<T, const N: usize> BitOr for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitOr for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
     Simd<T, N>: BitOr<Output = Simd<T, N>>,
 {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        Self { bits: self.bits | rhs.bits }
+        Self {
+            bits: self.bits | rhs.bits,
+        }
     }
 }
 
-impl<T, const N: usize> BitOrAssign for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitOrAssign for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
     Simd<T, N>: BitOrAssign<Simd<T, N>>,
 {
     fn bitor_assign(&mut self, rhs: Self) {
@@ -117,18 +150,102 @@ where
     }
 }
 
-impl<T, const N: usize> BitwiseOps for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitXor for SimdBitset<T, N, LEN>
+where
+    T: SimdElement,
+    Simd<T, N>: BitXor<Output = Simd<T, N>>,
+{
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits ^ rhs.bits,
+        }
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> BitXorAssign for SimdBitset<T, N, LEN>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
-    Simd<T, N>: BitAnd<Output = Simd<T, N>> + BitOr<Output = Simd<T, N>>,
+    Simd<T, N>: BitXorAssign<Simd<T, N>>,
 {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.bits ^= rhs.bits
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> Not for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+    Simd<T, N>: Not<Output = Simd<T, N>>,
+{
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self { bits: !self.bits }.masked()
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> Sub for SimdBitset<T, N, LEN>
+where
+    T: SimdElement,
+    Simd<T, N>: BitAnd<Output = Simd<T, N>> + Not<Output = Simd<T, N>>,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            bits: self.bits & !rhs.bits,
+        }
+    }
 }
 
-impl<T, const N: usize> BitsetOps for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> SubAssign for SimdBitset<T, N, LEN>
+where
+    T: SimdElement,
+    Simd<T, N>: BitAndAssign<Simd<T, N>> + Not<Output = Simd<T, N>>,
+{
+    fn sub_assign(&mut self, rhs: Self) {
+        self.bits &= !rhs.bits
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> BitwiseOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+    Simd<T, N>: BitAnd<Output = Simd<T, N>>
+        + BitOr<Output = Simd<T, N>>
+        + BitXor<Output = Simd<T, N>>
+        + Not<Output = Simd<T, N>>,
+{
+    // The lane-wise operations are done in one go, and then we check all the lanes together.
+    fn is_subset(&self, other: &Self) -> bool {
+        (self.bits & !other.bits) == Simd::splat(T::zero())
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        (self.bits & other.bits) == Simd::splat(T::zero())
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        (self.bits & other.bits)
+            .to_array()
+            .iter()
+            .map(|&x| x.count_ones() as usize)
+            .sum()
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> ComplementOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+    Simd<T, N>: Not<Output = Simd<T, N>>,
+{
+}
+
+impl<T, const N: usize, const LEN: usize> BitsetOps for SimdBitset<T, N, LEN>
 where
     T: SimdElement + Default + PrimInt + BitAndAssign + BitOrAssign,
-    LaneCount<N>: SupportedLaneCount,
 {
     fn empty() -> Self {
         Self {
@@ -139,10 +256,7 @@ where
     fn insert(&mut self, index: usize) -> bool {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-
-        if element_index >= N {
-            panic!("Index out of bounds");
-        }
+        check_index(index, Self::fixed_capacity());
 
         let mask = T::one() << bit_index;
         let was_set = (self.bits[element_index] & mask) != T::default();
@@ -153,10 +267,7 @@ where
     fn remove(&mut self, index: usize) {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-
-        if element_index >= N {
-            panic!("Index out of bounds");
-        }
+        check_index(index, Self::fixed_capacity());
 
         let mask = !(T::one() << bit_index);
         self.bits[element_index] &= mask;
@@ -165,10 +276,7 @@ where
     fn contains(&self, index: usize) -> bool {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-
-        if element_index >= N {
-            panic!("Index out of bounds");
-        }
+        check_index(index, Self::fixed_capacity());
 
         let mask = T::one() << bit_index;
         (self.bits[element_index] & mask) != T::default()
@@ -176,17 +284,39 @@ where
 
     fn count(&self) -> usize {
         // More efficient counting using SIMD operations
-        self.bits.to_array().iter().map(|&x| x.count_ones() as usize).sum()
+        self.bits
+            .to_array()
+            .iter()
+            .map(|&x| x.count_ones() as usize)
+            .sum()
     }
 }
 
-impl<T, const N: usize> BitsetRangeOps for SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> BitsetRangeOps for SimdBitset<T, N, LEN>
 where
-    T: SimdElement + Default + Copy + Eq + One + PrimInt + Not<Output = T> + 
-       BitAnd<Output = T> + BitAndAssign + BitOr<Output = T> + BitOrAssign,
-    LaneCount<N>: SupportedLaneCount,
+    T: SimdElement
+        + Default
+        + Copy
+        + Eq
+        + One
+        + PrimInt
+        + Not<Output = T>
+        + BitAnd<Output = T>
+        + BitAndAssign
+        + BitOr<Output = T>
+        + BitOrAssign,
 {
+    fn from_range<R: RangeBounds<usize>>(range: R) -> Self
+    where
+        Self: BitsetOps,
+    {
+        let mut bitset = Self::empty();
+        bitset.insert_range(range);
+        bitset
+    }
+
     fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(&start) => start,
             Bound::Excluded(&start) => start + 1,
@@ -205,24 +335,21 @@ where
         if start % bits_per_element == 0 && end % bits_per_element == 0 {
             let start_element = start / bits_per_element;
             let end_element = end / bits_per_element;
-            
+
             for i in start_element..end_element {
-                if i < N {
-                    self.bits[i] = !T::default();
-                }
+                self.bits[i] = !T::default();
             }
             return;
         }
 
         // Slow path: set individual bits
         for i in start..end {
-            if i < Self::fixed_capacity() {
-                self.insert(i);
-            }
+            self.insert(i);
         }
     }
 
     fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        check_range(&range, Self::fixed_capacity());
         let start = match range.start_bound() {
             Bound::Included(&start) => start,
             Bound::Excluded(&start) => start + 1,
@@ -241,34 +368,252 @@ where
         if start % bits_per_element == 0 && end % bits_per_element == 0 {
             let start_element = start / bits_per_element;
             let end_element = end / bits_per_element;
-            
+
             for i in start_element..end_element {
-                if i < N {
-                    self.bits[i] = T::default();
-                }
+                self.bits[i] = T::default();
             }
             return;
         }
 
         // Slow path: clear individual bits
         for i in start..end {
-            if i < Self::fixed_capacity() {
-                self.remove(i);
+            self.remove(i);
+        }
+    }
+}
+
+// Shifts carry bits from one lane into the next, so the lanes are shifted like the words of a packed bitset.
+impl<T, const N: usize, const LEN: usize> Shl<usize> for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    type Output = Self;
+
+    fn shl(self, n: usize) -> Self::Output {
+        let lanes = shift_words_left(self.bits.to_array().map(lane), n);
+        Self {
+            bits: Simd::from_array(lanes.map(|l| l.bits)),
+        }
+        .masked()
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> ShlAssign<usize> for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    fn shl_assign(&mut self, n: usize) {
+        *self = *self << n;
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> Shr<usize> for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    type Output = Self;
+
+    fn shr(self, n: usize) -> Self::Output {
+        let lanes = shift_words_right(self.bits.to_array().map(lane), n);
+        Self {
+            bits: Simd::from_array(lanes.map(|l| l.bits)),
+        }
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> ShrAssign<usize> for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    fn shr_assign(&mut self, n: usize) {
+        *self = *self >> n;
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> ShiftOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+    Simd<T, N>: BitOr<Output = Simd<T, N>>,
+{
+    fn rotate_left(self, n: usize) -> Self {
+        let capacity = Self::fixed_capacity();
+        let n = n % capacity;
+        if n == 0 {
+            self
+        } else {
+            (self << n) | (self >> (capacity - n))
+        }
+    }
+
+    fn rotate_right(self, n: usize) -> Self {
+        let capacity = Self::fixed_capacity();
+        let n = n % capacity;
+        if n == 0 {
+            self
+        } else {
+            (self >> n) | (self << (capacity - n))
+        }
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> BitsetRunOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    type Runs = RunsIterator<T, N>;
+
+    fn runs(&self) -> Self::Runs {
+        RunsIterator::runs(self.bits.to_array(), Self::fixed_capacity())
+    }
+
+    fn gaps(&self) -> Self::Runs {
+        RunsIterator::gaps(self.bits.to_array(), Self::fixed_capacity())
+    }
+}
+
+// The lanes are laid out like the elements of a packed bitset.
+impl<T, const N: usize, const LEN: usize> BitsetByteOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self
+            .bits
+            .to_array()
+            .iter()
+            .flat_map(|&x| lane(x).to_bytes())
+            .collect();
+        bytes.truncate(Self::fixed_capacity().div_ceil(8));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut lanes = [T::zero(); N];
+        let mut chunks = bytes.chunks(std::mem::size_of::<T>());
+        for (x, chunk) in lanes.iter_mut().zip(&mut chunks) {
+            *x = PrimitiveBitset::<T>::from_bytes(chunk)?.bits;
+        }
+        if chunks.flatten().any(|&b| b != 0) {
+            return None;
+        }
+        // Bits past the logical length aren't elements we can hold.
+        let result = Self {
+            bits: Simd::from_array(lanes),
+        };
+        if result.masked() != result {
+            return None;
+        }
+        Some(result)
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> BitsetTryOps for SimdBitset<T, N, LEN> where
+    T: SimdElement + Default + PrimInt + BitAndAssign + BitOrAssign
+{
+}
+
+// Each lane is answered as if it were a `PrimitiveBitset`, skipping over lanes using their counts.
+impl<T, const N: usize, const LEN: usize> BitsetRankOps for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + PrimInt + WrappingSub,
+{
+    fn rank(&self, index: usize) -> usize {
+        let bits_per_element = std::mem::size_of::<T>() * 8;
+        let lanes = self.bits.to_array();
+        let element_index = (index / bits_per_element).min(N);
+        let before: usize = lanes[..element_index]
+            .iter()
+            .map(|&x| x.count_ones() as usize)
+            .sum();
+        if element_index == N {
+            before
+        } else {
+            before + lane(lanes[element_index]).rank(index % bits_per_element)
+        }
+    }
+
+    fn select(&self, k: usize) -> Option<usize> {
+        let bits_per_element = std::mem::size_of::<T>() * 8;
+        let mut k = k;
+        for (i, &x) in self.bits.to_array().iter().enumerate() {
+            let count = x.count_ones() as usize;
+            if k < count {
+                return lane(x).select(k).map(|b| i * bits_per_element + b);
+            }
+            k -= count;
+        }
+        None
+    }
+
+    fn next_set_after(&self, index: usize) -> Option<usize> {
+        if index >= Self::fixed_capacity().saturating_sub(1) {
+            return None;
+        }
+
+        let bits_per_element = std::mem::size_of::<T>() * 8;
+        let lanes = self.bits.to_array();
+        let element_index = index / bits_per_element;
+        if let Some(b) = lane(lanes[element_index]).next_set_after(index % bits_per_element) {
+            return Some(element_index * bits_per_element + b);
+        }
+
+        lanes[element_index + 1..]
+            .iter()
+            .position(|&x| x != T::zero())
+            .map(|i| {
+                let i = element_index + 1 + i;
+                i * bits_per_element + lanes[i].trailing_zeros() as usize
+            })
+    }
+
+    fn prev_set_before(&self, index: usize) -> Option<usize> {
+        if index == 0 {
+            return None;
+        }
+
+        let bits_per_element = std::mem::size_of::<T>() * 8;
+        let lanes = self.bits.to_array();
+        let (element_index, bit_index) = if index >= Self::fixed_capacity() {
+            (N, 0)
+        } else {
+            (index / bits_per_element, index % bits_per_element)
+        };
+        if element_index < N {
+            if let Some(b) = lane(lanes[element_index]).prev_set_before(bit_index) {
+                return Some(element_index * bits_per_element + b);
             }
         }
+
+        lanes[..element_index]
+            .iter()
+            .rposition(|&x| x != T::zero())
+            .map(|i| (i + 1) * bits_per_element - 1 - lanes[i].leading_zeros() as usize)
     }
 }
 
-impl<T, const N: usize> BitsetOpsUnsafe for SimdBitset<T, N>
+// View a single lane as a primitive bitset, so we can re-use its bit tricks.
+fn lane<T>(bits: T) -> PrimitiveBitset<T> {
+    PrimitiveBitset { bits }
+}
+
+impl<T, const N: usize, const LEN: usize> BitsetOpsUnsafe for SimdBitset<T, N, LEN>
 where
-    T: SimdElement + Default + Copy + Eq + One + PrimInt + Not<Output = T> + 
-       BitAnd<Output = T> + BitAndAssign + BitOr<Output = T> + BitOrAssign,
-    LaneCount<N>: SupportedLaneCount,
+    T: SimdElement
+        + Default
+        + Copy
+        + Eq
+        + One
+        + PrimInt
+        + Not<Output = T>
+        + BitAnd<Output = T>
+        + BitAndAssign
+        + BitOr<Output = T>
+        + BitOrAssign,
 {
     unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-        
+
         let mask = T::one() << bit_index;
         let was_set = (self.bits[element_index] & mask) != T::default();
         self.bits[element_index] |= mask;
@@ -278,7 +623,7 @@ where
     unsafe fn remove_unchecked(&mut self, index: usize) {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-        
+
         let mask = !(T::one() << bit_index);
         self.bits[element_index] &= mask;
     }
@@ -286,7 +631,7 @@ where
     unsafe fn contains_unchecked(&self, index: usize) -> bool {
         let element_index = index / (std::mem::size_of::<T>() * 8);
         let bit_index = index % (std::mem::size_of::<T>() * 8);
-        
+
         let mask = T::one() << bit_index;
         (self.bits[element_index] & mask) != T::default()
     }
@@ -295,7 +640,6 @@ where
 pub struct SimdBitsetIterator<T, const N: usize>
 where
     T: SimdElement,
-    LaneCount<N>: SupportedLaneCount,
 {
     bitset: SimdBitset<T, N>,
     current_element: usize,
@@ -305,23 +649,22 @@ where
 impl<T, const N: usize> Iterator for SimdBitsetIterator<T, N>
 where
     T: SimdElement + Default + One + PartialEq + Shl<usize, Output = T> + BitAnd<Output = T>,
-    LaneCount<N>: SupportedLaneCount,
 {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
         let bits_per_element = std::mem::size_of::<T>() * 8;
-        
+
         while self.current_element < N {
             let value = self.bitset.bits[self.current_element];
-            
+
             // Skip over if entire element is 0
             if value == T::default() {
                 self.current_element += 1;
                 self.current_bit = 0;
                 continue;
             }
-            
+
             // Find next set bit
             while self.current_bit < bits_per_element {
                 let mask = T::one() << self.current_bit;
@@ -332,71 +675,135 @@ where
                 }
                 self.current_bit += 1;
             }
-            
+
             // Move to next element
             self.current_element += 1;
             self.current_bit = 0;
         }
-        
+
         None
     }
 }
 
 impl<T, const N: usize> DoubleEndedIterator for SimdBitsetIterator<T, N>
 where
-    T: SimdElement + WrappingSub + BitAndAssign + One + Default + Copy + Eq + PrimInt + Not<Output = T> + 
-       BitAnd<Output = T> + BitAndAssign + BitOr<Output = T> + BitOrAssign,
-    LaneCount<N>: SupportedLaneCount,
+    T: SimdElement
+        + WrappingSub
+        + BitAndAssign
+        + One
+        + Default
+        + Copy
+        + Eq
+        + PrimInt
+        + Not<Output = T>
+        + BitAnd<Output = T>
+        + BitAndAssign
+        + BitOr<Output = T>
+        + BitOrAssign,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         let bits_per_element = std::mem::size_of::<T>() * 8;
-        
+
         let mut element_index = N;
         while element_index > 0 {
             element_index -= 1;
-            
+
             let value = self.bitset.bits[element_index];
-            
+
             // Skip over if entire element is 0
             if value == T::default() {
                 continue;
             }
-            
+
             // Find the highest set bit in this element
             let mut bit_index = bits_per_element;
             while bit_index > 0 {
                 bit_index -= 1;
-                
+
                 let mask = T::one() << bit_index;
                 if (value & mask) != T::default() {
                     let result = element_index * bits_per_element + bit_index;
-                    
+
                     // Create a copy of the bitset with this bit unset
-                    let mut new_bitset = self.bitset.clone();
+                    let mut new_bitset = self.bitset;
                     new_bitset.remove(result);
                     self.bitset = new_bitset;
-                    
+
                     return Some(result);
                 }
             }
         }
-        
+
         None
     }
 }
 
-impl<'a, T, const N: usize> IntoIterator for &'a SimdBitset<T, N>
+impl<T, const N: usize, const LEN: usize> Extend<usize> for SimdBitset<T, N, LEN>
 where
-    T: SimdElement + WrappingSub + BitAndAssign + One + Default + Copy + Eq + PrimInt + Not<Output = T> + 
-       BitAnd<Output = T> + BitAndAssign + BitOr<Output = T> + BitOrAssign,
-    LaneCount<N>: SupportedLaneCount,
+    T: SimdElement + Default + PrimInt + BitAndAssign + BitOrAssign,
+{
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> FromIterator<usize> for SimdBitset<T, N, LEN>
+where
+    T: SimdElement + Default + PrimInt + BitAndAssign + BitOrAssign,
+{
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> std::fmt::Display for SimdBitset<T, N, LEN>
+where
+    T: SimdElement
+        + WrappingSub
+        + BitAndAssign
+        + One
+        + Default
+        + Copy
+        + Eq
+        + PrimInt
+        + Not<Output = T>
+        + BitAnd<Output = T>
+        + BitAndAssign
+        + BitOr<Output = T>
+        + BitOrAssign,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_set(f, self)
+    }
+}
+
+impl<T, const N: usize, const LEN: usize> IntoIterator for &SimdBitset<T, N, LEN>
+where
+    T: SimdElement
+        + WrappingSub
+        + BitAndAssign
+        + One
+        + Default
+        + Copy
+        + Eq
+        + PrimInt
+        + Not<Output = T>
+        + BitAnd<Output = T>
+        + BitAndAssign
+        + BitOr<Output = T>
+        + BitOrAssign,
 {
     type IntoIter = SimdBitsetIterator<T, N>;
     type Item = usize;
 
     fn into_iter(self) -> Self::IntoIter {
+        // The padding is clear, so the iterator doesn't need to know about the logical length.
         SimdBitsetIterator {
-            bitset: self.clone(),
+            bitset: SimdBitset { bits: self.bits },
             current_element: 0,
             current_bit: 0,
         }
@@ -426,24 +833,366 @@ pub type SimdU64Bitset4 = SimdBitset<u64, 4>;
 mod tests {
     use super::super::tests::*;
     use super::*;
-    
+
     // Using fixed types with supported lane counts
-    crate::generate_tests!(test_empty, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_full, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_get, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_unset, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_range, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_unset_get, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_unset_range, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_all, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_bitwise_and, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_bitwise_and_assign, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_bitwise_or, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_bitwise_or_assign, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_empty_iterator, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_empty_iterator_back, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_one_bit_iterator, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_one_bit_iterator_back, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_two_bit_iterator, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-    crate::generate_tests!(test_set_two_bit_iterator_back, SimdU8Bitset8, SimdU16Bitset8, SimdU32Bitset8, SimdU64Bitset4);
-}
\ No newline at end of file
+    crate::generate_tests!(
+        test_empty,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_full,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_get,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_unset,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_range,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_unset_get,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_unset_range,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_all,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_and,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_and_assign,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_or,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_or_assign,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_empty_iterator,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_empty_iterator_back,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_one_bit_iterator,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_one_bit_iterator_back,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_set_two_bit_iterator_back,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_xor,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bitwise_xor_assign,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_difference,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_difference_assign,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_complement,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_subset_superset,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_disjoint,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_intersection_count,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_rank,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_select,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_next_set_after,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_prev_set_before,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_shift_left,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_shift_right,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_rotate,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bytes_round_trip,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_bytes_padding,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_from_iterator,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_from_iterator_out_of_range,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_from_range,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_display,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_try_ops,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_out_of_range_panics,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_range_out_of_range_panics,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_conformance,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+    crate::generate_tests!(
+        test_runs_and_gaps,
+        SimdU8Bitset8,
+        SimdU16Bitset8,
+        SimdU32Bitset8,
+        SimdU64Bitset4
+    );
+
+    // Logical lengths that stop part way through a lane, leaving whole lanes of padding after them.
+    type SimdU8Padded = SimdBitset<u8, 8, 30>;
+    type SimdU64Padded = SimdBitset<u64, 4, 130>;
+    type SimdU64Len100 = SimdBitset<u64, 2, 100>;
+
+    crate::generate_tests!(test_empty, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_full, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_get, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_unset, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_range, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_unset_get, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_unset_range, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_all, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_bitwise_and, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_bitwise_or, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_bitwise_xor, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_difference, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_complement, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_subset_superset, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_disjoint, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_intersection_count, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_rank, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_select, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_next_set_after, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_prev_set_before, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_shift_left, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_shift_right, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_rotate, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_empty_iterator, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_empty_iterator_back, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_one_bit_iterator, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_one_bit_iterator_back, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_two_bit_iterator, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_set_two_bit_iterator_back, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_from_iterator, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_from_iterator_out_of_range, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_from_range, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_display, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_try_ops, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_out_of_range_panics, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_range_out_of_range_panics, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(
+        test_aligned_ranges_stop_at_the_capacity,
+        SimdU8Padded,
+        SimdU64Padded,
+        SimdU64Len100
+    );
+    crate::generate_tests!(test_conformance, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_runs_and_gaps, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_padding_stays_clear, SimdU8Padded, SimdU64Padded);
+    crate::generate_tests!(test_bytes_cover_only_the_bits, SimdU8Padded, SimdU64Padded);
+}
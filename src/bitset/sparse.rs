@@ -1,17 +1,32 @@
+use std::fmt::{Binary, Debug, Display};
 use std::marker::PhantomData;
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, RangeBounds, Sub,
+    SubAssign,
+};
 
-use num::PrimInt;
+use num::{traits::WrappingSub, PrimInt};
 
 use crate::stack_vec::ArrayVec;
 
-use super::{primitives::PrimitiveBitset, BitsetOps, FixedSizeBitset};
+use super::{
+    primitives::PrimitiveBitset, write_set, BitsetByteOps, BitsetOps, BitsetOpsUnsafe,
+    BitsetRangeOps, BitwiseOps, FixedSizeBitset,
+};
 
+///- A bitset for when the elements are spread thinly over a large range.
+///- Only the blocks that contain elements are stored, as a sorted list of (block index, bits) entries.
+///- Blocks that become empty are dropped, so two sets are equal exactly when their entries are.
 pub struct SparseBitset<C, U> {
     components: C,
     _phantom: PhantomData<U>,
 }
 
-trait Components<U>
+/// The storage for the entries of a sparse bitset.
+///
+/// Implementations just need to be a list that we can insert into and remove from.
+/// The sparse bitset takes care of keeping the entries sorted by index.
+pub trait Components<U>
 where
     PrimitiveBitset<U>: FixedSizeBitset,
 {
@@ -28,12 +43,29 @@ where
 
     fn as_mut_slice(&mut self) -> &mut [SparseEntry<U>];
 
-    fn push_component(&mut self, index: usize, offset: usize);
+    /// Insert an entry at position `at`, shuffling later entries along.
+    fn insert_component(&mut self, at: usize, entry: SparseEntry<U>);
+
+    /// Remove the entry at position `at`, shuffling later entries back.
+    fn remove_component(&mut self, at: usize);
+
+    fn clear(&mut self);
+
+    fn push_component(&mut self, entry: SparseEntry<U>) {
+        self.insert_component(self.as_slice().len(), entry);
+    }
+
+    /// The most entries that can be held, or `None` if the storage can grow.
+    fn max_entries() -> Option<usize> {
+        None
+    }
 }
 
-struct SparseEntry<U> {
-    index: usize,
-    bits: PrimitiveBitset<U>,
+/// A block of bits, covering the elements from `index * bits` up to `(index + 1) * bits`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SparseEntry<U> {
+    pub index: usize,
+    pub bits: PrimitiveBitset<U>,
 }
 
 impl<U: PrimInt, const N: usize> Components<U> for ArrayVec<SparseEntry<U>, N> {
@@ -49,10 +81,24 @@ impl<U: PrimInt, const N: usize> Components<U> for ArrayVec<SparseEntry<U>, N> {
         self.as_mut_slice()
     }
 
-    fn push_component(&mut self, index: usize, offset: usize) {
-        let mut bits = PrimitiveBitset::<U>::empty();
-        bits.insert(offset);
-        unsafe { self.push_unchecked(SparseEntry { index, bits }) };
+    fn insert_component(&mut self, at: usize, entry: SparseEntry<U>) {
+        assert!(self.len() < N, "Fixed capacity of SparseBitset reached");
+        // Push onto the end, and then rotate it down into place.
+        unsafe { self.push_unchecked(entry) };
+        self.as_mut_slice()[at..].rotate_right(1);
+    }
+
+    fn remove_component(&mut self, at: usize) {
+        self.as_mut_slice()[at..].rotate_left(1);
+        self.pop();
+    }
+
+    fn clear(&mut self) {
+        ArrayVec::clear(self);
+    }
+
+    fn max_entries() -> Option<usize> {
+        Some(N)
     }
 }
 
@@ -69,10 +115,100 @@ impl<U: PrimInt> Components<U> for Vec<SparseEntry<U>> {
         self.as_mut_slice()
     }
 
-    fn push_component(&mut self, index: usize, offset: usize) {
-        let mut bits = PrimitiveBitset::<U>::empty();
-        bits.insert(offset);
-        self.push(SparseEntry { index, bits });
+    fn insert_component(&mut self, at: usize, entry: SparseEntry<U>) {
+        self.insert(at, entry);
+    }
+
+    fn remove_component(&mut self, at: usize) {
+        self.remove(at);
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> SparseBitset<C, U> {
+    /// Remove all elements, keeping any storage that has been allocated.
+    pub fn clear(&mut self) {
+        self.components.clear();
+    }
+
+    /// The entries that hold elements, in index order.
+    pub fn entries(&self) -> &[SparseEntry<U>] {
+        self.components.as_slice()
+    }
+
+    // Find the position of the entry with this index,
+    // or where it should be inserted if it is missing.
+    fn find(&self, index: usize) -> Result<usize, usize> {
+        self.components
+            .as_slice()
+            .binary_search_by_key(&index, |e| e.index)
+    }
+
+    // Build a new bitset from entries that are already in index order, dropping any that are empty.
+    fn from_sorted_entries(entries: impl Iterator<Item = SparseEntry<U>>) -> Self {
+        let mut result = Self::empty();
+        for entry in entries {
+            if entry.bits.count() > 0 {
+                result.components.push_component(entry);
+            }
+        }
+        result
+    }
+
+    // Walk the entries of two bitsets together, in index order.
+    // Each step gives the index, and the bits from each side, if that side has an entry there.
+    fn merge<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<
+        Item = (
+            usize,
+            Option<PrimitiveBitset<U>>,
+            Option<PrimitiveBitset<U>>,
+        ),
+    > + 'a {
+        let mut left = self.entries().iter().peekable();
+        let mut right = other.entries().iter().peekable();
+        std::iter::from_fn(move || match (left.peek(), right.peek()) {
+            (None, None) => None,
+            (Some(_), None) => {
+                let l = left.next().unwrap();
+                Some((l.index, Some(l.bits), None))
+            }
+            (None, Some(_)) => {
+                let r = right.next().unwrap();
+                Some((r.index, None, Some(r.bits)))
+            }
+            (Some(l), Some(r)) => {
+                if l.index < r.index {
+                    let l = left.next().unwrap();
+                    Some((l.index, Some(l.bits), None))
+                } else if r.index < l.index {
+                    let r = right.next().unwrap();
+                    Some((r.index, None, Some(r.bits)))
+                } else {
+                    let l = left.next().unwrap();
+                    let r = right.next().unwrap();
+                    Some((l.index, Some(l.bits), Some(r.bits)))
+                }
+            }
+        })
+    }
+
+    // Combine two bitsets entry by entry, where a missing entry counts as empty.
+    fn combine(
+        &self,
+        other: &Self,
+        op: impl Fn(PrimitiveBitset<U>, PrimitiveBitset<U>) -> PrimitiveBitset<U>,
+    ) -> Self {
+        let empty = PrimitiveBitset::<U>::empty();
+        Self::from_sorted_entries(self.merge(other).map(|(index, l, r)| SparseEntry {
+            index,
+            bits: op(l.unwrap_or(empty), r.unwrap_or(empty)),
+        }))
     }
 }
 
@@ -86,33 +222,35 @@ impl<C: Components<U>, U: PrimInt> BitsetOps for SparseBitset<C, U> {
 
     fn insert(&mut self, value: usize) -> bool {
         let (index, offset) = self.components.index_offset(value);
-        for SparseEntry { index: idx, bits } in self.components.as_mut_slice() {
-            if *idx == index {
-                return bits.insert(offset);
+        match self.find(index) {
+            Ok(at) => self.components.as_mut_slice()[at].bits.insert(offset),
+            Err(at) => {
+                let mut bits = PrimitiveBitset::<U>::empty();
+                bits.insert(offset);
+                self.components
+                    .insert_component(at, SparseEntry { index, bits });
+                true
             }
         }
-
-        self.components.push_component(index, offset);
-        true
     }
 
     fn remove(&mut self, value: usize) {
         let (index, offset) = self.components.index_offset(value);
-        for SparseEntry { index: idx, bits } in self.components.as_mut_slice() {
-            if *idx == index {
-                return bits.remove(offset);
+        if let Ok(at) = self.find(index) {
+            let bits = &mut self.components.as_mut_slice()[at].bits;
+            bits.remove(offset);
+            if bits.count() == 0 {
+                self.components.remove_component(at);
             }
         }
     }
 
     fn contains(&self, value: usize) -> bool {
         let (index, offset) = self.components.index_offset(value);
-        for bits in self.components.as_slice() {
-            if bits.index == index {
-                return bits.bits.contains(offset);
-            }
+        match self.find(index) {
+            Ok(at) => self.components.as_slice()[at].bits.contains(offset),
+            Err(_) => false,
         }
-        false
     }
 
     fn count(&self) -> usize {
@@ -123,3 +261,638 @@ impl<C: Components<U>, U: PrimInt> BitsetOps for SparseBitset<C, U> {
             .sum()
     }
 }
+
+// There's no capacity to check for a sparse bitset, so these are the same as the safe versions.
+impl<C: Components<U>, U: PrimInt> BitsetOpsUnsafe for SparseBitset<C, U> {
+    unsafe fn insert_unchecked(&mut self, index: usize) -> bool {
+        BitsetOps::insert(self, index)
+    }
+
+    unsafe fn remove_unchecked(&mut self, index: usize) {
+        BitsetOps::remove(self, index);
+    }
+
+    unsafe fn contains_unchecked(&self, index: usize) -> bool {
+        BitsetOps::contains(self, index)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitsetRangeOps for SparseBitset<C, U> {
+    /// Insert every element in the range.
+    ///
+    /// Panics if the range has no end, as that would need an unbounded number of entries.
+    fn insert_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => *i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => *i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => panic!("Can not insert an unbounded range into a SparseBitset"),
+        };
+        if start >= end {
+            return;
+        }
+
+        let (first_index, first_offset) = self.components.index_offset(start);
+        let (last_index, last_offset) = self.components.index_offset(end - 1);
+        for index in first_index..=last_index {
+            let lo = if index == first_index {
+                first_offset
+            } else {
+                0
+            };
+            let hi = if index == last_index {
+                last_offset + 1
+            } else {
+                PrimitiveBitset::<U>::fixed_capacity()
+            };
+            match self.find(index) {
+                Ok(at) => self.components.as_mut_slice()[at].bits.insert_range(lo..hi),
+                Err(at) => {
+                    let mut bits = PrimitiveBitset::<U>::empty();
+                    bits.insert_range(lo..hi);
+                    self.components
+                        .insert_component(at, SparseEntry { index, bits });
+                }
+            }
+        }
+    }
+
+    fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => *i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => Some(*i + 1),
+            Bound::Excluded(i) => Some(*i),
+            Bound::Unbounded => None,
+        };
+        if end.is_some_and(|end| start >= end) {
+            return;
+        }
+
+        // Only the entries that already exist need to change, so we walk those that overlap the range.
+        let (first_index, first_offset) = self.components.index_offset(start);
+        let last = end.map(|end| self.components.index_offset(end - 1));
+        let mut at = self.find(first_index).unwrap_or_else(|at| at);
+        while at < self.components.as_slice().len() {
+            let entry = &mut self.components.as_mut_slice()[at];
+            if last.is_some_and(|(last_index, _)| entry.index > last_index) {
+                break;
+            }
+            let lo = if entry.index == first_index {
+                first_offset
+            } else {
+                0
+            };
+            match last {
+                Some((last_index, last_offset)) if entry.index == last_index => {
+                    entry.bits.remove_range(lo..last_offset + 1)
+                }
+                _ => entry.bits.remove_range(lo..),
+            }
+            if entry.bits.count() == 0 {
+                self.components.remove_component(at);
+            } else {
+                at += 1;
+            }
+        }
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitAnd for SparseBitset<C, U> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.combine(&rhs, |l, r| l & r)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitAndAssign for SparseBitset<C, U> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = self.combine(&rhs, |l, r| l & r);
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitOr for SparseBitset<C, U> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.combine(&rhs, |l, r| l | r)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitOrAssign for SparseBitset<C, U> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.combine(&rhs, |l, r| l | r);
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitXor for SparseBitset<C, U> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.combine(&rhs, |l, r| l ^ r)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> BitXorAssign for SparseBitset<C, U> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        *self = self.combine(&rhs, |l, r| l ^ r);
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> Sub for SparseBitset<C, U> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.combine(&rhs, |l, r| l - r)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> SubAssign for SparseBitset<C, U> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.combine(&rhs, |l, r| l - r);
+    }
+}
+
+impl<C: Components<U>, U: PrimInt + BitAndAssign + BitOrAssign + BitXorAssign> BitwiseOps
+    for SparseBitset<C, U>
+{
+    fn is_subset(&self, other: &Self) -> bool {
+        self.merge(other).all(|(_, l, r)| match (l, r) {
+            (Some(l), Some(r)) => l.is_subset(&r),
+            (Some(_), None) => false,
+            _ => true,
+        })
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.merge(other).all(|(_, l, r)| match (l, r) {
+            (Some(l), Some(r)) => l.is_disjoint(&r),
+            _ => true,
+        })
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.merge(other)
+            .map(|(_, l, r)| match (l, r) {
+                (Some(l), Some(r)) => l.intersection_count(&r),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// The most entries that decoding will make, whatever the storage.
+/// That's 16MB of `Vec` storage, which is plenty for anything we've written out.
+pub const MAX_DECODED_ENTRIES: usize = 1 << 20;
+
+/// A run-length encoding, as a sparse set can cover far more elements than we'd want to write out.
+///
+/// Each run of consecutive elements is written as two LEB128 varints:
+/// the gap from the last element of the previous run (or from zero, for the first run),
+/// and then the length of the run less one.
+/// The runs are as long as they can be, so every set has exactly one encoding.
+impl<C: Components<U>, U: PrimInt + WrappingSub + BitAndAssign> BitsetByteOps
+    for SparseBitset<C, U>
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut previous: Option<usize> = None;
+        let mut run: Option<(usize, usize)> = None;
+        let mut write_run = |previous: Option<usize>, (first, last): (usize, usize)| {
+            write_varint(&mut bytes, first - previous.unwrap_or(0));
+            write_varint(&mut bytes, last - first);
+        };
+
+        for i in self {
+            match run {
+                Some((first, last)) if last + 1 == i => run = Some((first, i)),
+                _ => {
+                    if let Some(r) = run {
+                        write_run(previous, r);
+                        previous = Some(r.1);
+                    }
+                    run = Some((i, i));
+                }
+            }
+        }
+        if let Some(r) = run {
+            write_run(previous, r);
+        }
+        bytes
+    }
+
+    /// Returns `None` if the bytes are cut short, overflow, or have runs that should have been merged.
+    /// It also returns `None` if the runs need more entries than the storage can hold,
+    /// or more than `MAX_DECODED_ENTRIES`, so that a few bytes can't ask for a huge set.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let block_bits = PrimitiveBitset::<U>::fixed_capacity();
+        let max_entries =
+            C::max_entries().map_or(MAX_DECODED_ENTRIES, |n| n.min(MAX_DECODED_ENTRIES));
+
+        let mut bytes = bytes;
+        let mut result = Self::empty();
+        let mut previous: Option<usize> = None;
+        let mut entries = 0;
+        while !bytes.is_empty() {
+            let gap = read_varint(&mut bytes)?;
+            let length = read_varint(&mut bytes)?;
+            let first = match previous {
+                None => gap,
+                Some(_) if gap < 2 => return None,
+                Some(p) => p.checked_add(gap)?,
+            };
+            let last = first.checked_add(length)?;
+
+            // Count the entries before inserting anything.
+            // Runs are in order, so only the previous run can have already made the first block.
+            let (first_block, last_block) = (first / block_bits, last / block_bits);
+            let shared = previous.is_some_and(|p| p / block_bits == first_block);
+            entries += last_block - first_block + 1 - shared as usize;
+            if entries > max_entries {
+                return None;
+            }
+
+            // The last element can be usize::MAX, so we can't use an inclusive range.
+            result.insert_range(first..last);
+            result.insert(last);
+            previous = Some(last);
+        }
+        Some(result)
+    }
+}
+
+// Seven bits to a byte, low bits first, with the top bit set on all but the last byte.
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<usize> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&b, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let part = (b & 0x7f) as usize;
+        // Any bits shifted off the top mean the value is too big.
+        if (part << shift) >> shift != part {
+            return None;
+        }
+        value |= part << shift;
+        if b & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+impl<C: Components<U> + Clone, U: Clone> Clone for SparseBitset<C, U> {
+    fn clone(&self) -> Self {
+        SparseBitset {
+            components: self.components.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> Default for SparseBitset<C, U> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+// The entries are kept sorted, with no empty entries, so equal sets have equal entries.
+impl<C: Components<U>, U: PrimInt> PartialEq for SparseBitset<C, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries() == other.entries()
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> Eq for SparseBitset<C, U> {}
+
+// A sparse set can hold any index, so the only limit is the capacity of the storage.
+impl<C: Components<U>, U: PrimInt> Extend<usize> for SparseBitset<C, U> {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for index in iter {
+            self.insert(index);
+        }
+    }
+}
+
+impl<C: Components<U>, U: PrimInt> FromIterator<usize> for SparseBitset<C, U> {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut bitset = Self::empty();
+        bitset.extend(iter);
+        bitset
+    }
+}
+
+impl<C: Components<U>, U: PrimInt + WrappingSub + BitAndAssign> Display for SparseBitset<C, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_set(f, self)
+    }
+}
+
+impl<C: Components<U>, U: PrimInt + Binary> Debug for SparseBitset<C, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.entries().iter().map(|e| (e.index, e.bits)))
+            .finish()
+    }
+}
+
+impl<'a, C: Components<U>, U: PrimInt + WrappingSub + BitAndAssign + 'a> IntoIterator
+    for &'a SparseBitset<C, U>
+{
+    // As with the packed bitset, the iterator chain has a vile type, so we hide it behind a newtype.
+    type IntoIter = SparseBitsetIterator<impl DoubleEndedIterator<Item = usize> + 'a>;
+    type Item = usize;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SparseBitsetIterator(self.entries().iter().flat_map(|e| {
+            let i = e.index * PrimitiveBitset::<U>::fixed_capacity();
+            e.bits.into_iter().map(move |b| i + b)
+        }))
+    }
+}
+
+/// An iterator over the elements of a sparse bitset.
+pub struct SparseBitsetIterator<I>(I);
+
+impl<I: Iterator<Item = usize>> Iterator for SparseBitsetIterator<I> {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = usize>> DoubleEndedIterator for SparseBitsetIterator<I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::super::tests::*;
+    use super::*;
+
+    type VecSparse = SparseBitset<Vec<SparseEntry<u16>>, u16>;
+    type ArraySparse = SparseBitset<ArrayVec<SparseEntry<u16>, 16>, u16>;
+
+    crate::generate_tests!(test_empty_iterator, VecSparse, ArraySparse);
+    crate::generate_tests!(test_empty_iterator_back, VecSparse, ArraySparse);
+
+    fn from_values<BS: BitsetOps>(values: &[usize]) -> BS {
+        let mut bitset = BS::empty();
+        for &v in values {
+            bitset.insert(v);
+        }
+        bitset
+    }
+
+    fn test_against_btree<BS: BitsetOps + Debug>()
+    where
+        for<'a> &'a BS: IntoIterator<Item = usize, IntoIter: DoubleEndedIterator>,
+    {
+        let mut sparse = BS::empty();
+        let mut oracle = BTreeSet::new();
+
+        // Spread out enough to need several entries, and inserted out of order.
+        let values = [1000, 5, 0, 10_000, 17, 16, 999, 15, 1001, usize::MAX, 42];
+        for &v in &values {
+            assert_eq!(sparse.insert(v), oracle.insert(v), "insert {}", v);
+            assert_eq!(sparse.insert(v), oracle.insert(v), "re-insert {}", v);
+        }
+        for v in (0..1100).chain([10_000, usize::MAX - 1, usize::MAX]) {
+            assert_eq!(sparse.contains(v), oracle.contains(&v), "contains {}", v);
+        }
+        assert_eq!(sparse.count(), oracle.len());
+        assert!((&sparse).into_iter().eq(oracle.iter().copied()));
+        assert!((&sparse).into_iter().rev().eq(oracle.iter().rev().copied()));
+
+        for v in [0, 16, 1000, 1001, usize::MAX, 3] {
+            sparse.remove(v);
+            oracle.remove(&v);
+        }
+        assert_eq!(sparse.count(), oracle.len());
+        assert!(
+            (&sparse).into_iter().eq(oracle.iter().copied()),
+            "{:?}",
+            sparse
+        );
+    }
+
+    crate::generate_tests!(test_against_btree, VecSparse, ArraySparse);
+
+    #[test]
+    fn conformance() {
+        // 256 elements is the most the 16 entries of the array version can always hold.
+        crate::bitset::conformance::check_bitset::<VecSparse>(256);
+        crate::bitset::conformance::check_bitset::<ArraySparse>(256);
+    }
+
+    #[test]
+    fn removing_the_last_element_drops_the_entry() {
+        let mut sparse: VecSparse = from_values(&[3, 100]);
+        assert_eq!(sparse.entries().len(), 2);
+        sparse.remove(100);
+        assert_eq!(sparse.entries().len(), 1);
+        assert_eq!(sparse, from_values(&[3]));
+    }
+
+    #[test]
+    fn insert_and_remove_ranges() {
+        let mut sparse = VecSparse::empty();
+        sparse.insert_range(10..40);
+        sparse.insert_range(100..=100);
+        assert_eq!(sparse.count(), 31);
+        assert!((&sparse).into_iter().eq((10..40).chain([100])));
+
+        sparse.remove_range(12..38);
+        assert!((&sparse).into_iter().eq([10, 11, 38, 39, 100]));
+
+        // The entry covering 16..32 is now empty, and should have gone.
+        assert_eq!(sparse.entries().len(), 3);
+
+        sparse.remove_range(39..);
+        assert!((&sparse).into_iter().eq([10, 11, 38]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_unbounded_range_panics() {
+        let mut sparse = VecSparse::empty();
+        sparse.insert_range(10..);
+    }
+
+    #[test]
+    fn bitwise_ops() {
+        let a: VecSparse = from_values(&[1, 2, 20, 300, 5000]);
+        let b: VecSparse = from_values(&[2, 21, 300, 6000]);
+
+        assert_eq!(a.clone() & b.clone(), from_values(&[2, 300]));
+        assert_eq!(
+            a.clone() | b.clone(),
+            from_values(&[1, 2, 20, 21, 300, 5000, 6000])
+        );
+        assert_eq!(a.clone() ^ b.clone(), from_values(&[1, 20, 21, 5000, 6000]));
+        assert_eq!(a.clone() - b.clone(), from_values(&[1, 20, 5000]));
+
+        let mut c = a.clone();
+        c &= b.clone();
+        assert_eq!(c, from_values(&[2, 300]));
+        // Intersections that cancel out whole entries shouldn't leave empty entries behind.
+        assert_eq!(c.entries().len(), 2);
+
+        let mut c = a.clone();
+        c -= a.clone();
+        assert_eq!(c, VecSparse::empty());
+        assert!(c.entries().is_empty());
+
+        assert_eq!(a.intersection_count(&b), 2);
+        assert!(!a.is_subset(&b));
+        assert!(from_values::<VecSparse>(&[2, 300]).is_subset(&a));
+        assert!(a.is_superset(&from_values(&[5000])));
+        assert!(a.is_disjoint(&from_values(&[0, 21, 7000])));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn collection_constructors() {
+        let values = [usize::MAX, 5, 1, 10_000];
+        let collected: VecSparse = values.into_iter().collect();
+        assert_eq!(collected, from_values(&values));
+        assert_eq!(ArraySparse::from_slice(&values), from_values(&values));
+        assert_eq!(
+            collected.to_string(),
+            format!("{{1, 5, 10000, {}}}", usize::MAX)
+        );
+
+        let mut extended = VecSparse::from_range(1000..1003);
+        extended.extend([7, 1001]);
+        assert_eq!(extended, from_values(&[7, 1000, 1001, 1002]));
+        assert_eq!(VecSparse::empty().to_string(), "{}");
+    }
+
+    #[test]
+    #[should_panic(expected = "Fixed capacity of SparseBitset reached")]
+    fn collecting_past_array_capacity_panics() {
+        let _: SparseBitset<ArrayVec<SparseEntry<u16>, 2>, u16> =
+            [0, 100, 1000].into_iter().collect();
+    }
+
+    #[test]
+    fn run_length_encoding() {
+        let sparse: VecSparse = from_values(&[3, 4, 5, 10, 200, 201]);
+        assert_eq!(sparse.to_bytes(), vec![3, 2, 5, 0, 190, 1, 1]);
+        assert_eq!(VecSparse::from_bytes(&sparse.to_bytes()), Some(sparse));
+
+        assert!(VecSparse::empty().to_bytes().is_empty());
+        assert_eq!(VecSparse::from_bytes(&[]), Some(VecSparse::empty()));
+
+        // A long run costs no more than a short one.
+        let mut long = VecSparse::empty();
+        long.insert_range(1_000_000..2_000_000);
+        assert_eq!(long.to_bytes().len(), 6);
+        assert_eq!(VecSparse::from_bytes(&long.to_bytes()), Some(long));
+
+        let edge: VecSparse = from_values(&[0, usize::MAX - 1, usize::MAX]);
+        assert_eq!(VecSparse::from_bytes(&edge.to_bytes()), Some(edge));
+    }
+
+    #[test]
+    fn run_length_encoding_round_trips() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(2024);
+        for _ in 0..100 {
+            // Mix up single elements and runs, over ranges both small and large.
+            let spread = 1 << rng.gen_range(4..40);
+            let mut sparse = VecSparse::empty();
+            for _ in 0..rng.gen_range(0..50) {
+                let start = rng.gen_range(0..spread);
+                sparse.insert_range(start..start + rng.gen_range(1..100));
+            }
+            let bytes = sparse.to_bytes();
+            assert_eq!(VecSparse::from_bytes(&bytes), Some(sparse));
+        }
+
+        // The encoding doesn't depend on how the entries are stored.
+        assert_eq!(
+            ArraySparse::from_bytes(&[3, 2, 5, 0]),
+            Some(from_values(&[3, 4, 5, 10]))
+        );
+    }
+
+    #[test]
+    fn run_length_encoding_rejects_bad_bytes() {
+        // Cut short, in the middle of a varint and between the two halves of a run.
+        assert_eq!(VecSparse::from_bytes(&[0x83]), None);
+        assert_eq!(VecSparse::from_bytes(&[3]), None);
+        // Runs that touch should have been written as one.
+        assert_eq!(VecSparse::from_bytes(&[3, 2, 1, 0]), None);
+        // Too big for a usize.
+        assert_eq!(VecSparse::from_bytes(&[0xff; 11]), None);
+        let mut past_the_end = vec![0xff; 9];
+        past_the_end.extend([0x01, 0x01]);
+        assert_eq!(VecSparse::from_bytes(&past_the_end), None);
+    }
+
+    #[test]
+    fn run_length_encoding_rejects_huge_runs() {
+        // One run of nearly every usize, which would take forever to insert.
+        let mut huge = vec![0];
+        write_varint(&mut huge, usize::MAX - 1);
+        assert_eq!(VecSparse::from_bytes(&huge), None);
+
+        // Just past the limit, and then just inside it.
+        let bits = PrimitiveBitset::<u16>::fixed_capacity();
+        let mut too_many = vec![0];
+        write_varint(&mut too_many, MAX_DECODED_ENTRIES * bits);
+        assert_eq!(VecSparse::from_bytes(&too_many), None);
+        let mut just_fits = vec![0];
+        write_varint(&mut just_fits, MAX_DECODED_ENTRIES * bits - 1);
+        assert_eq!(
+            VecSparse::from_bytes(&just_fits).map(|s| s.count()),
+            Some(MAX_DECODED_ENTRIES * bits)
+        );
+    }
+
+    #[test]
+    fn run_length_encoding_checks_array_capacity() {
+        type TwoEntries = SparseBitset<ArrayVec<SparseEntry<u16>, 2>, u16>;
+
+        // Two runs in two blocks fit.
+        let fits = from_values::<VecSparse>(&[0, 100]).to_bytes();
+        assert_eq!(TwoEntries::from_bytes(&fits).map(|s| s.count()), Some(2));
+        // Sharing a block with the run before doesn't need another entry.
+        let shared = from_values::<VecSparse>(&[0, 5, 100]).to_bytes();
+        assert_eq!(TwoEntries::from_bytes(&shared).map(|s| s.count()), Some(3));
+        // A third block doesn't, and neither does one long run over three blocks.
+        let three_blocks = from_values::<VecSparse>(&[0, 100, 1000]).to_bytes();
+        assert_eq!(TwoEntries::from_bytes(&three_blocks), None);
+        assert_eq!(TwoEntries::from_bytes(&[0, 40]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fixed capacity of SparseBitset reached")]
+    fn array_capacity_is_checked() {
+        let mut sparse = SparseBitset::<ArrayVec<SparseEntry<u16>, 2>, u16>::empty();
+        sparse.insert(0);
+        sparse.insert(100);
+        sparse.insert(1000);
+    }
+}
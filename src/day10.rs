@@ -3,87 +3,29 @@ use std::collections::HashSet;
 use aoc_runner_derive::aoc;
 
 use crate::{
-    bitset::{primitives::PrimitiveBitset, BitsetOps, FixedSizeBitset},
-    stack_vec::ArrayVec,
+    bitset::{ArraySparseU16Bitset, BitsetOps},
+    direction::{Compass, DirectionIter},
+    grid::Grid,
+    stack_vec::{ArrayVec, SmallVec},
 };
 
-const MAP_SIZE: usize = 59;
-
 #[aoc(day10, part1)]
 pub fn part1(input: &str) -> usize {
-    unsafe { solve_part1(input, MAP_SIZE) }
+    unsafe { solve_part1(input) }
 }
 
 #[aoc(day10, part2)]
 pub fn part2(input: &str) -> usize {
-    unsafe { solve_part2(input, MAP_SIZE) }
+    unsafe { solve_part2(input) }
 }
 
-const SPARSE_BITSET_CAPACITY: usize = 12;
 // I think we need a micro-set implementation.
-#[derive(Debug, Default)]
-pub struct SparseBitset {
-    elements: [(usize, PrimitiveBitset<u16>); SPARSE_BITSET_CAPACITY],
-    used: usize,
-}
-
-impl SparseBitset {
-    fn new() -> Self {
-        Self {
-            elements: [(0, PrimitiveBitset::empty()); SPARSE_BITSET_CAPACITY],
-            used: 0,
-        }
-    }
-
-    fn clear(&mut self) {
-        self.used = 0;
-    }
-
-    fn insert(&mut self, value: usize) -> bool {
-        let index = value / PrimitiveBitset::<u16>::fixed_capacity();
-        let offset = value % PrimitiveBitset::<u16>::fixed_capacity();
-        for e in self.elements[..self.used].iter_mut() {
-            if e.0 == index {
-                return e.1.insert(offset);
-            }
-        }
-
-        debug_assert!(
-            self.used < self.elements.len(),
-            "Fixed capacity of SparseBitset reached"
-        );
-        let new_block = unsafe { self.elements.get_unchecked_mut(self.used) };
-        self.used += 1;
-        new_block.0 = index;
-        new_block.1 = PrimitiveBitset::<u16>::empty();
-        new_block.1.insert(offset)
-    }
-
-    fn contains(&self, value: &usize) -> bool {
-        let index = value / PrimitiveBitset::<u16>::fixed_capacity();
-        let offset = value % PrimitiveBitset::<u16>::fixed_capacity();
-
-        // println!("Checking {} {} {} {}", value, index, offset, self.used);
-        for (i, e) in self.elements[..self.used].iter().enumerate() {
-            if e.0 == index {
-                // println!("Found {:?} at {}", e, i);
-                return e.1.contains(offset);
-            }
-        }
-        false
-    }
-
-    fn remove(&mut self, value: &usize) {
-        let index = value / PrimitiveBitset::<u16>::fixed_capacity();
-        let offset = value % PrimitiveBitset::<u16>::fixed_capacity();
-        for e in self.elements[..self.used].iter_mut() {
-            if e.0 == index {
-                e.1.remove(offset);
-                return;
-            }
-        }
-    }
-}
+// The sparse bitset keeps a short sorted list of u16 blocks on the stack.
+// A trail can only wander 9 steps from its trailhead, so it covers at most 19 rows,
+// and each row is at most 19 bytes wide, which can straddle 3 blocks.
+// My input only ever needs 16 blocks, but 12 wasn't enough, so let's be safe.
+const SPARSE_BITSET_CAPACITY: usize = 19 * 3;
+type SeenPlaces = ArraySparseU16Bitset<SPARSE_BITSET_CAPACITY>;
 
 // This is the faster implementation for me.
 // - 1.9155 µs
@@ -101,45 +43,12 @@ pub fn trailhead_iterator<'b>(input: &'b [u8]) -> impl Iterator<Item = usize> +
         .map(|(i, _)| i)
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub enum Direction {
-    #[default]
-    Up,
-    Right,
-    Down,
-    Left,
-}
-
-#[derive(Clone, Copy, Debug)]
-struct DirectionIter(Option<Direction>);
-
-impl Iterator for DirectionIter {
-    type Item = Direction;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let current = self.0;
-        self.0 = match current {
-            None => None,
-            Some(Direction::Up) => Some(Direction::Right),
-            Some(Direction::Right) => Some(Direction::Down),
-            Some(Direction::Down) => Some(Direction::Left),
-            Some(Direction::Left) => None,
-        };
-        current
-    }
-}
-
-impl Default for DirectionIter {
-    fn default() -> Self {
-        Self(Some(Direction::Up))
-    }
-}
-
-pub unsafe fn solve_part1(input: &str, map_size: usize) -> usize {
+pub unsafe fn solve_part1(input: &str) -> usize {
     let input = input.as_bytes();
     let input_len = input.len() as isize;
     let mut heights = 0;
-    let bytes_width = (map_size + 1) as isize;
+    // The width of the map is however long the first line is.
+    let bytes_width = Grid::from_bytes(input).stride() as isize;
 
     #[derive(Debug, Default, Clone, Copy)]
     struct StackFrame {
@@ -153,7 +62,8 @@ pub unsafe fn solve_part1(input: &str, map_size: usize) -> usize {
             pos: trailhead as isize,
             current_dir: DirectionIter::default(),
         });
-        let mut seen_heights: ArrayVec<isize, 10> = ArrayVec::new();
+        // A trailhead could reach any number of peaks, so this spills onto the heap if there are lots.
+        let mut seen_heights: SmallVec<isize, 10> = SmallVec::new();
 
         loop {
             match stack.get_last_mut() {
@@ -168,13 +78,7 @@ pub unsafe fn solve_part1(input: &str, map_size: usize) -> usize {
                             stack.pop();
                         }
                         Some(dir) => {
-                            let new_pos = here.pos
-                                + match dir {
-                                    Direction::Up => -bytes_width,
-                                    Direction::Right => 1,
-                                    Direction::Down => bytes_width,
-                                    Direction::Left => -1,
-                                };
+                            let new_pos = here.pos + dir.offset(bytes_width);
                             if new_pos >= 0 // not off the beginning of the input
                                 && new_pos < input_len
                             // not off the end of the input
@@ -188,7 +92,7 @@ pub unsafe fn solve_part1(input: &str, map_size: usize) -> usize {
                                     // println!("Was gently uphill");
                                     if height == 9 {
                                         if !seen_heights.contains(&new_pos) {
-                                            seen_heights.push_unchecked(new_pos);
+                                            seen_heights.push(new_pos);
                                             // println!("{} -> {}", trailhead, new_pos);
                                             heights += 1;
                                         }
@@ -211,11 +115,11 @@ pub unsafe fn solve_part1(input: &str, map_size: usize) -> usize {
     heights
 }
 
-pub unsafe fn solve_part1_pruning(input: &str, map_size: usize) -> usize {
+pub unsafe fn solve_part1_pruning(input: &str) -> usize {
     let input = input.as_bytes();
     let input_len = input.len() as isize;
     let mut heights = 0;
-    let bytes_width = (map_size + 1) as isize;
+    let bytes_width = Grid::from_bytes(input).stride() as isize;
 
     #[derive(Debug, Default, Clone, Copy)]
     struct StackFrame {
@@ -223,7 +127,7 @@ pub unsafe fn solve_part1_pruning(input: &str, map_size: usize) -> usize {
         current_dir: DirectionIter,
     }
 
-    let mut seen_places = SparseBitset::new();
+    let mut seen_places = SeenPlaces::empty();
     for trailhead in trailhead_memchr(input) {
         // println!("New trailhead: {}", trailhead);
         seen_places.clear();
@@ -250,17 +154,12 @@ pub unsafe fn solve_part1_pruning(input: &str, map_size: usize) -> usize {
                             stack.pop();
                         }
                         Some(dir) => {
-                            let delta = match dir {
-                                Direction::Up => -bytes_width,
-                                Direction::Right => 1,
-                                Direction::Down => bytes_width,
-                                Direction::Left => -1,
-                            };
+                            let delta = dir.offset(bytes_width);
                             let new_pos = (here.pos as isize) + delta;
                             // println!("{} trying {} to {}", height, here.pos, new_pos);
                             if new_pos >= 0
                                 && new_pos < input_len
-                                && !seen_places.contains(&(new_pos as usize))
+                                && !seen_places.contains(new_pos as usize)
                             {
                                 let new_pos = new_pos as usize;
                                 // We use saturating_sub here so that \n looks like 0 which is a safe value
@@ -293,11 +192,11 @@ pub unsafe fn solve_part1_pruning(input: &str, map_size: usize) -> usize {
     heights
 }
 
-pub unsafe fn solve_part2_recursive(input: &str, map_size: usize) -> usize {
+pub unsafe fn solve_part2_recursive(input: &str) -> usize {
     let input = input.as_bytes();
     let input_len = input.len() as isize;
     let mut ratings = 0;
-    let bytes_width = (map_size + 1) as isize;
+    let bytes_width = Grid::from_bytes(input).stride() as isize;
 
     fn recursive_walk(
         input: &[u8],
@@ -311,13 +210,7 @@ pub unsafe fn solve_part2_recursive(input: &str, map_size: usize) -> usize {
         } else {
             DirectionIter::default()
                 .map(|dir| {
-                    let there = here
-                        + match dir {
-                            Direction::Up => -bytes_width,
-                            Direction::Right => 1,
-                            Direction::Down => bytes_width,
-                            Direction::Left => -1,
-                        };
+                    let there = here + dir.offset(bytes_width);
                     if there >= 0 // not off the beginning of the input
                         && there < input_len
                     {
@@ -340,11 +233,11 @@ pub unsafe fn solve_part2_recursive(input: &str, map_size: usize) -> usize {
         .sum()
 }
 
-pub unsafe fn solve_part2(input: &str, map_size: usize) -> usize {
+pub unsafe fn solve_part2(input: &str) -> usize {
     let input = input.as_bytes();
     let input_len = input.len() as isize;
     let mut ratings = 0;
-    let bytes_width = (map_size + 1) as isize;
+    let bytes_width = Grid::from_bytes(input).stride() as isize;
 
     #[derive(Debug, Default, Clone, Copy)]
     struct StackFrame {
@@ -372,13 +265,7 @@ pub unsafe fn solve_part2(input: &str, map_size: usize) -> usize {
                             stack.pop();
                         }
                         Some(dir) => {
-                            let new_pos = here.pos
-                                + match dir {
-                                    Direction::Up => -bytes_width,
-                                    Direction::Right => 1,
-                                    Direction::Down => bytes_width,
-                                    Direction::Left => -1,
-                                };
+                            let new_pos = here.pos + dir.offset(bytes_width);
                             if new_pos >= 0 // not off the beginning of the input
                                 && new_pos < input_len
                             // not off the end of the input
@@ -497,33 +384,33 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(unsafe { solve_part1(EXAMPLE_1, 8) }, 36);
+        assert_eq!(unsafe { solve_part1(EXAMPLE_1) }, 36);
     }
 
     #[test]
     fn part1_example_y() {
-        assert_eq!(unsafe { solve_part1(EXAMPLE_1_Y, 7) }, 2);
+        assert_eq!(unsafe { solve_part1(EXAMPLE_1_Y) }, 2);
     }
 
     #[test]
     fn part1_example_4() {
-        assert_eq!(unsafe { solve_part1(EXAMPLE_1_4, 7) }, 4);
+        assert_eq!(unsafe { solve_part1(EXAMPLE_1_4) }, 4);
     }
 
     #[test]
     fn part1_example_cross() {
-        assert_eq!(unsafe { solve_part1(EXAMPLE_1_CROSS, 7) }, 3);
+        assert_eq!(unsafe { solve_part1(EXAMPLE_1_CROSS) }, 3);
     }
 
     #[test]
     fn part1_testcase_1() {
-        assert_eq!(unsafe { solve_part1(TESTCASE_1, 5) }, TESTCASE_1_SOLUTION);
+        assert_eq!(unsafe { solve_part1(TESTCASE_1) }, TESTCASE_1_SOLUTION);
     }
 
     #[test]
     fn part1_testcase_1_pruning() {
         assert_eq!(
-            unsafe { solve_part1_pruning(TESTCASE_1, 5) },
+            unsafe { solve_part1_pruning(TESTCASE_1) },
             TESTCASE_1_SOLUTION
         );
     }
@@ -535,15 +422,12 @@ mod tests {
 
     #[test]
     fn part1_noprune_test() {
-        assert_eq!(unsafe { solve_part1(INPUT, MAP_SIZE) }, PART1_SOLUTION);
+        assert_eq!(unsafe { solve_part1(INPUT) }, PART1_SOLUTION);
     }
 
     #[test]
     fn part1_pruning_test() {
-        assert_eq!(
-            unsafe { solve_part1_pruning(INPUT, MAP_SIZE) },
-            PART1_SOLUTION
-        );
+        assert_eq!(unsafe { solve_part1_pruning(INPUT) }, PART1_SOLUTION);
     }
 
     #[test]
@@ -554,26 +438,22 @@ mod tests {
     #[ignore]
     #[test]
     fn part2_recursive_test() {
-        assert_eq!(
-            unsafe { solve_part2_recursive(INPUT, MAP_SIZE) },
-            PART2_SOLUTION
-        );
+        assert_eq!(unsafe { solve_part2_recursive(INPUT) }, PART2_SOLUTION);
     }
 
-    #[ignore]
     #[test]
     fn test_sparse_bitset() {
-        let mut sparse = SparseBitset::new();
+        let mut sparse = SeenPlaces::empty();
         let mut hash = HashSet::new();
 
         // Test empty sets
-        assert_eq!(sparse.contains(&0), hash.contains(&0));
-        assert_eq!(sparse.contains(&1000), hash.contains(&1000));
+        assert_eq!(sparse.contains(0), hash.contains(&0));
+        assert_eq!(sparse.contains(1000), hash.contains(&1000));
 
         // Test adding single elements
         sparse.insert(5);
         hash.insert(5);
-        assert_eq!(sparse.contains(&5), hash.contains(&5));
+        assert_eq!(sparse.contains(5), hash.contains(&5));
 
         // Test adding multiple elements
         let test_values = vec![0, 1, 10, 100, 1000, 10000];
@@ -582,12 +462,12 @@ mod tests {
             let si = sparse.insert(*val);
             let hi = hash.insert(*val);
             // println!("Insert return values {} {}", si, hi);
-            let sc = sparse.contains(val);
+            let sc = sparse.contains(*val);
             let hc = hash.contains(val);
             // println!("Contains return values {} {}", sc, hc);
             assert_eq!(si, hi, "Insert return value differed");
             assert_eq!(
-                sparse.contains(val),
+                sparse.contains(*val),
                 hash.contains(val),
                 "Value {} not present in {:?} vs {:?}",
                 val,
@@ -601,11 +481,11 @@ mod tests {
             println!(
                 "Verifying {} {} {}",
                 val,
-                sparse.contains(val),
+                sparse.contains(*val),
                 hash.contains(val)
             );
             assert_eq!(
-                sparse.contains(val),
+                sparse.contains(*val),
                 hash.contains(val),
                 "Value {} not present in {:?} vs {:?}",
                 val,
@@ -617,24 +497,24 @@ mod tests {
         // Test non-existent values
         let non_existent = vec![2, 3, 101, 102, 1001];
         for &val in &non_existent {
-            assert_eq!(sparse.contains(&val), hash.contains(&val));
+            assert_eq!(sparse.contains(val), hash.contains(&val));
         }
 
         // Test clearing specific elements
         let to_clear = vec![0, 10, 1000];
         for &val in &to_clear {
-            sparse.remove(&val);
+            sparse.remove(val);
             hash.remove(&val);
         }
 
         // Verify cleared elements
         for val in &to_clear {
-            assert_eq!(sparse.contains(val), hash.contains(val));
+            assert_eq!(sparse.contains(*val), hash.contains(val));
         }
 
         // Verify remaining elements
         for val in &test_values {
-            assert_eq!(sparse.contains(val), hash.contains(val));
+            assert_eq!(sparse.contains(*val), hash.contains(val));
         }
 
         // Test edge cases
@@ -642,10 +522,10 @@ mod tests {
         for &val in &edge_cases {
             sparse.insert(val);
             hash.insert(val);
-            assert_eq!(sparse.contains(&val), hash.contains(&val));
-            sparse.remove(&val);
+            assert_eq!(sparse.contains(val), hash.contains(&val));
+            sparse.remove(val);
             hash.remove(&val);
-            assert_eq!(sparse.contains(&val), hash.contains(&val));
+            assert_eq!(sparse.contains(val), hash.contains(&val));
         }
 
         // Test repeated operations on same values
@@ -653,10 +533,10 @@ mod tests {
         for _ in 0..3 {
             sparse.insert(repeat_val);
             hash.insert(repeat_val);
-            assert_eq!(sparse.contains(&repeat_val), hash.contains(&repeat_val));
-            sparse.remove(&repeat_val);
+            assert_eq!(sparse.contains(repeat_val), hash.contains(&repeat_val));
+            sparse.remove(repeat_val);
             hash.remove(&repeat_val);
-            assert_eq!(sparse.contains(&repeat_val), hash.contains(&repeat_val));
+            assert_eq!(sparse.contains(repeat_val), hash.contains(&repeat_val));
         }
     }
 }
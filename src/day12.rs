@@ -0,0 +1,309 @@
+use aoc_runner_derive::aoc;
+
+use crate::bitset::{BitsetOps, DynBitset};
+
+// In day 12, there's a map of garden plots.
+//
+// ```
+// AAAA
+// BBCD
+// BBCC
+// EEEC
+// ```
+//
+// Each letter is the type of plant growing in that plot.
+// Plots of the same plant that touch horizontally or vertically form a region.
+// The same plant can appear in several regions that don't touch.
+//
+// For part 1, the price of fencing a region is its area multiplied by its perimeter.
+// For part 2, the bulk discount kicks in, and the price is the area multiplied by the number of sides.
+//
+// Both parts need us to find the regions, which is a flood fill.
+// We walk over every plot, and if we've not already seen it, we flood out from there to find the whole region.
+// We track the plots we've seen in a bitset, indexed by the byte offset into the input.
+// This wastes one bit per row for the newline, but saves us converting to and from (row, col) all the time.
+
+// One bit for every byte of the input, including the newlines.
+// The real data is 140 plots square, but it's sized from the input, so any map will do.
+type Visited = DynBitset;
+
+/// A read-only view over the garden map.
+struct Garden<'a> {
+    plots: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Garden<'a> {
+    fn new(input: &'a str) -> Self {
+        let plots = input.as_bytes();
+        // All rows are the same length, so the first newline tells us the width.
+        let width = memchr::memchr(b'\n', plots).unwrap_or(plots.len());
+        let height = plots.len().div_ceil(width + 1);
+
+        Garden {
+            plots,
+            width,
+            height,
+        }
+    }
+
+    fn stride(&self) -> usize {
+        self.width + 1
+    }
+
+    // The plant at a (row, col) position, or None if we've stepped off the map.
+    // Taking signed coordinates lets callers look one step past the edge without underflowing.
+    fn plant_at(&self, row: isize, col: isize) -> Option<u8> {
+        if row < 0 || col < 0 || row as usize >= self.height || col as usize >= self.width {
+            None
+        } else {
+            Some(unsafe {
+                *self
+                    .plots
+                    .get_unchecked(row as usize * self.stride() + col as usize)
+            })
+        }
+    }
+}
+
+// The four orthogonal steps, listed clockwise so that each step and the next one form a corner.
+const STEPS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// The statistics for a single region that we need to price the fencing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Region {
+    area: usize,
+    perimeter: usize,
+    corners: usize,
+}
+
+// Flood fill out from `start`, marking every plot in the region as visited.
+//
+// The perimeter is the number of plot edges that face a different plant (or the edge of the map).
+//
+// The number of sides is harder to count directly, as a side spans many plots.
+// However, a polygon has exactly as many corners as it has sides, and corners are local.
+// For each pair of adjacent steps (e.g. up and right) around a plot:
+// - if neither neighbour is in the region, it's an outside (convex) corner
+// - if both neighbours are in the region, but the diagonal between them is not, it's an inside (concave) corner
+fn flood_region(
+    garden: &Garden,
+    visited: &mut Visited,
+    stack: &mut Vec<usize>,
+    start: usize,
+) -> Region {
+    let plant = garden.plots[start];
+    let stride = garden.stride();
+    let mut region = Region::default();
+
+    visited.insert(start);
+    stack.push(start);
+
+    while let Some(pos) = stack.pop() {
+        let row = (pos / stride) as isize;
+        let col = (pos % stride) as isize;
+        region.area += 1;
+
+        // Look at each of the neighbours once, and remember which are in the region.
+        let mut same = [false; 4];
+        for (i, &(dr, dc)) in STEPS.iter().enumerate() {
+            same[i] = garden.plant_at(row + dr, col + dc) == Some(plant);
+            if same[i] {
+                let next = ((row + dr) as usize) * stride + (col + dc) as usize;
+                // `insert` tells us if this is the first time we've seen the plot.
+                if visited.insert(next) {
+                    stack.push(next);
+                }
+            } else {
+                region.perimeter += 1;
+            }
+        }
+
+        for i in 0..4 {
+            let j = (i + 1) % 4;
+            if !same[i] && !same[j] {
+                region.corners += 1;
+            } else if same[i] && same[j] {
+                let (dr, dc) = (STEPS[i].0 + STEPS[j].0, STEPS[i].1 + STEPS[j].1);
+                if garden.plant_at(row + dr, col + dc) != Some(plant) {
+                    region.corners += 1;
+                }
+            }
+        }
+    }
+
+    region
+}
+
+// Walk the whole map, calling `price` on each region as we find it, and summing up the results.
+fn total_price(input: &str, price: impl Fn(Region) -> usize) -> usize {
+    let garden = Garden::new(input);
+    let mut visited = Visited::with_capacity(garden.plots.len());
+    // The stack is re-used between regions, so that we only allocate it once.
+    let mut stack = Vec::with_capacity(garden.width * garden.height);
+    let mut total = 0;
+
+    for row in 0..garden.height {
+        for col in 0..garden.width {
+            let pos = row * garden.stride() + col;
+            if !visited.contains(pos) {
+                let region = flood_region(&garden, &mut visited, &mut stack, pos);
+                total += price(region);
+            }
+        }
+    }
+
+    total
+}
+
+#[aoc(day12, part1)]
+pub fn part1(input: &str) -> usize {
+    total_price(input, |region| region.area * region.perimeter)
+}
+
+#[aoc(day12, part2)]
+pub fn part2(input: &str) -> usize {
+    total_price(input, |region| region.area * region.corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const INPUT: &str = include_str!("../input/2024/day12.txt");
+    const PART1_SOLUTION: usize = 1522850;
+    const PART2_SOLUTION: usize = 953738;
+
+    const EXAMPLE_1: &str = indoc! {
+        "AAAA
+        BBCD
+        BBCC
+        EEEC
+        "
+    };
+
+    const EXAMPLE_2: &str = indoc! {
+        "OOOOO
+        OXOXO
+        OOOOO
+        OXOXO
+        OOOOO
+        "
+    };
+
+    const EXAMPLE_3: &str = indoc! {
+        "RRRRIICCFF
+        RRRRIICCCF
+        VVRRRCCFFF
+        VVRCCCJFFF
+        VVVVCJJCFE
+        VVIVCCJJEE
+        VVIIICJJEE
+        MIIIIIJJEE
+        MIIISIJEEE
+        MMMISSJEEE
+        "
+    };
+
+    const EXAMPLE_E: &str = indoc! {
+        "EEEEE
+        EXXXX
+        EEEEE
+        EXXXX
+        EEEEE
+        "
+    };
+
+    const EXAMPLE_AB: &str = indoc! {
+        "AAAAAA
+        AAABBA
+        AAABBA
+        ABBAAA
+        ABBAAA
+        AAAAAA
+        "
+    };
+
+    #[test]
+    fn flood_example_1() {
+        let garden = Garden::new(EXAMPLE_1);
+        let mut visited = Visited::empty();
+        let mut stack = Vec::new();
+        let region = flood_region(&garden, &mut visited, &mut stack, 0);
+        assert_eq!(
+            region,
+            Region {
+                area: 4,
+                perimeter: 10,
+                corners: 4
+            }
+        );
+        assert_eq!(visited.count(), 4);
+    }
+
+    #[test]
+    fn part1_example_1() {
+        assert_eq!(part1(EXAMPLE_1), 140);
+    }
+
+    #[test]
+    fn part1_example_2() {
+        assert_eq!(part1(EXAMPLE_2), 772);
+    }
+
+    #[test]
+    fn part1_example_3() {
+        assert_eq!(part1(EXAMPLE_3), 1930);
+    }
+
+    #[test]
+    fn part1_test() {
+        assert_eq!(part1(INPUT), PART1_SOLUTION);
+    }
+
+    #[test]
+    fn part2_example_1() {
+        assert_eq!(part2(EXAMPLE_1), 80);
+    }
+
+    #[test]
+    fn part2_example_2() {
+        assert_eq!(part2(EXAMPLE_2), 436);
+    }
+
+    #[test]
+    fn part2_example_e() {
+        assert_eq!(part2(EXAMPLE_E), 236);
+    }
+
+    #[test]
+    fn part2_example_ab() {
+        assert_eq!(part2(EXAMPLE_AB), 368);
+    }
+
+    #[test]
+    fn part2_example_3() {
+        assert_eq!(part2(EXAMPLE_3), 1206);
+    }
+
+    #[test]
+    fn bigger_than_the_real_data() {
+        // Stripes of alternating plants, 200 plots square, so every row is its own region.
+        let size = 200;
+        let input = (0..size)
+            .map(|row| {
+                let plant = if row % 2 == 0 { 'A' } else { 'B' };
+                std::iter::repeat_n(plant, size).collect::<String>() + "\n"
+            })
+            .collect::<String>();
+        assert_eq!(part1(&input), size * size * (2 * size + 2));
+        assert_eq!(part2(&input), size * size * 4);
+    }
+
+    #[test]
+    fn part2_test() {
+        assert_eq!(part2(INPUT), PART2_SOLUTION);
+    }
+}
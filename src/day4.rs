@@ -1,5 +1,7 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::grid::Grid;
+
 #[aoc(day4, part1)]
 pub fn part1(input: &str) -> usize {
     // We've got an input that is a wordsearch grid.
@@ -10,27 +12,14 @@ pub fn part1(input: &str) -> usize {
     // The total number of lines is found by dividing the total length by the row length,
     // taking into account the possible absense of a newline at the very end.
 
-    let input = input.as_bytes();
-    let line_len = input.iter().take_while(|&&b| b != b'\n').count();
-    let row_len = line_len + 1;
-
-    #[cfg(debug_assertions)]
-    {
-        let lines = (input.len() + 1) / row_len;
-        let overhang = (input.len() + 1) % row_len;
-        assert!(
-            overhang == 0 || overhang == 1,
-            "Not expecting a trailing fragment: line_len: {}, row_len: {}, lines: {}, overhang: {}",
-            line_len,
-            row_len,
-            lines,
-            overhang
-        );
-    }
+    // The grid checks that the lines are all the same length for us.
+    let grid = Grid::new(input);
+    let input = grid.bytes();
+    let row_len = grid.stride();
 
     // println!(
-    //     "line length: {}, lines: {}, overhang: {}",
-    //     line_len, lines, overhang
+    //     "line length: {}, lines: {}",
+    //     grid.width(), grid.height()
     // );
 
     // We can avoid reversing input data by matching against the reverse target.
@@ -131,23 +120,10 @@ pub fn part1(input: &str) -> usize {
 
 #[aoc(day4, part2)]
 pub fn part2(input: &str) -> usize {
-    let input = input.as_bytes();
-    let line_len = input.iter().take_while(|&&b| b != b'\n').count();
-    let row_len = line_len + 1;
-
-    #[cfg(debug_assertions)]
-    {
-        let lines = (input.len() + 1) / row_len;
-        let overhang = (input.len() + 1) % row_len;
-        debug_assert!(
-            overhang == 0 || overhang == 1,
-            "Not expecting a trailing fragment: line_len: {}, row_len: {}, lines: {}, overhang: {}",
-            line_len,
-            row_len,
-            lines,
-            overhang
-        );
-    }
+    // The grid checks that the lines are all the same length for us.
+    let grid = Grid::new(input);
+    let input = grid.bytes();
+    let row_len = grid.stride();
 
     // This time, we're looking for all occurances of:
     //
@@ -3,6 +3,8 @@ use packed::PackedBitset;
 use primitives::PrimitiveBitset;
 
 use crate::bitset::*;
+use crate::direction::{Compass, Direction};
+use crate::grid::Grid;
 
 // In day 6, there's a map of a lab.
 //
@@ -40,72 +42,51 @@ use crate::bitset::*;
 // This means that the cost of calculating the bitfield for all columns is higher than
 // simply looping over the small number that have a collision.
 //
-// To optimize the representation, we can pad out each row(column) of the map to a multiple of 8 bytes.
-// Any overhang doesn't matter for collision detection.
-// We need to subtract the padding only for the case where the guard exits to the right or bottom.
+// To optimize the representation, we can pad out each row(column) of the map to a whole number of words.
+// The bitset is told the real row length, so it keeps the overhang clear and never counts it.
 
 // A position on the map, for example, where the guard is.
 
 /// A map row needs to cover 130 columns.
 
 // Vital statistics
+// My input is 130 square, so that's the size the fast path is built for.
+// Anything bigger falls back to rows that are sized when the map is read.
 const MAP_SIZE: usize = 130;
 type BitsetRep = u16;
 const COLUMN_BYTES: usize = 9;
-type RowBitset = PackedBitset<PrimitiveBitset<BitsetRep>, COLUMN_BYTES>;
-const UNUSED_BITS: usize = std::mem::size_of::<u16>() * COLUMN_BYTES * 8 - MAP_SIZE;
-
-#[derive(PartialEq, Eq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+type RowBitset = PackedBitset<PrimitiveBitset<BitsetRep>, COLUMN_BYTES, MAP_SIZE>;
 
-impl Default for Direction {
-    fn default() -> Self {
-        Direction::Right
-    }
+/// What the map and the visited set need from a row bitset.
+/// The rest of the walk doesn't care how big the map is, so it's generic over this.
+pub trait LabRow: BitsetOps + BitsetOpsUnsafe + BitsetRankOps + Clone {
+    /// An empty row with room for `width` columns.
+    fn with_width(width: usize) -> Self;
 }
 
-impl Direction {
-    fn turn_right(&mut self) {
-        *self = match self {
-            Direction::Up => Direction::Right,
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-        }
+impl LabRow for RowBitset {
+    fn with_width(width: usize) -> Self {
+        debug_assert!(width <= MAP_SIZE);
+        RowBitset::empty()
     }
 }
 
-impl std::fmt::Debug for Direction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Direction::Up => write!(f, "^"),
-            Direction::Right => write!(f, ">"),
-            Direction::Down => write!(f, "v"),
-            Direction::Left => write!(f, "<"),
-        }
+impl LabRow for DynBitset {
+    fn with_width(width: usize) -> Self {
+        // The visited rows use insert_unchecked, so they have to be big enough up front.
+        DynBitset::with_capacity(width)
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct LabMapRow(
+#[derive(Clone)]
+pub struct LabMapRow<Row = RowBitset>(
     // I've benchmarked performance for various bitset implementations, and on my machine, this is an optimal trade-off.
     // For operations within the bitset, u16 to u64 behave almost equivalently.
     // However, for arrays of bitsets 130 elements long, u8 and u16 are 10x faster than u32 or larger.
-    RowBitset,
+    Row,
 );
 
-impl Default for LabMapRow {
-    fn default() -> Self {
-        LabMapRow(RowBitset::default())
-    }
-}
-
-impl LabMapRow {
+impl<Row: LabRow> LabMapRow<Row> {
     fn set_obstacle(&mut self, index: usize) {
         self.0.insert(index);
     }
@@ -115,19 +96,17 @@ impl LabMapRow {
     }
 }
 
-pub struct LabMap {
-    rows: [LabMapRow; MAP_SIZE],
+pub struct LabMap<Row = RowBitset> {
+    rows: Vec<LabMapRow<Row>>,
 }
 
-impl Default for LabMap {
-    fn default() -> Self {
+impl<Row: LabRow> LabMap<Row> {
+    fn with_size(width: usize, height: usize) -> Self {
         LabMap {
-            rows: [LabMapRow::default(); MAP_SIZE],
+            rows: vec![LabMapRow(Row::with_width(width)); height],
         }
     }
-}
 
-impl LabMap {
     fn obstacle_at(&self, row: usize, col: usize) -> bool {
         unsafe { self.rows.get_unchecked(row).is_obstacle(col) }
     }
@@ -144,16 +123,15 @@ impl LabMap {
                 .take_while(|&r| !self.obstacle_at(r, col))
                 .map(|r| (r, col))
                 .next(),
+            // Horizontally, the row bitset can jump straight to the next obstacle.
             Direction::Right => unsafe {
                 self.rows
                     .get_unchecked(row)
                     .0
-                    .into_iter()
-                    .filter(|&c| c > col)
+                    .next_set_after(col)
                     .map(|c| (row, c))
-                    .next()
             },
-            Direction::Down => (row + 1..MAP_SIZE)
+            Direction::Down => (row + 1..self.rows.len())
                 .take_while(|&r| !self.obstacle_at(r, col))
                 .map(|r| (r, col))
                 .next(),
@@ -161,20 +139,17 @@ impl LabMap {
                 self.rows
                     .get_unchecked(row)
                     .0
-                    .into_iter()
-                    .rev()
-                    .filter(|&c| c < col)
+                    .prev_set_before(col)
                     .map(|c| (row, c))
-                    .next()
             },
         }
     }
 }
 
-#[derive(Clone, Copy, Default)]
-pub struct VisitedRow(RowBitset);
+#[derive(Clone)]
+pub struct VisitedRow<Row = RowBitset>(Row);
 
-impl VisitedRow {
+impl<Row: LabRow> VisitedRow<Row> {
     fn visit(&mut self, col: usize) -> bool {
         unsafe {
             let unvisited = !self.0.contains_unchecked(col);
@@ -184,19 +159,17 @@ impl VisitedRow {
     }
 }
 
-pub struct Visited {
-    rows: [VisitedRow; MAP_SIZE],
+pub struct Visited<Row = RowBitset> {
+    rows: Vec<VisitedRow<Row>>,
 }
 
-impl Default for Visited {
-    fn default() -> Self {
+impl<Row: LabRow> Visited<Row> {
+    fn with_size(width: usize, height: usize) -> Self {
         Visited {
-            rows: [VisitedRow::default(); MAP_SIZE],
+            rows: vec![VisitedRow(Row::with_width(width)); height],
         }
     }
-}
 
-impl Visited {
     fn visit(&mut self, row: usize, col: usize) -> bool {
         unsafe { self.rows.get_unchecked_mut(row).visit(col) }
     }
@@ -207,57 +180,46 @@ pub struct Guard {
     direction: Direction,
 }
 
-pub struct WalkState {
-    map: LabMap,
-    visited: Visited,
+pub struct WalkState<Row = RowBitset> {
+    map: LabMap<Row>,
+    visited: Visited<Row>,
     guard: Guard,
 }
 
+/// Read a map that fits the fast path, which is all of the real inputs.
 pub fn parse_lab_map(input: &str) -> (LabMap, Guard) {
-    let input = input.as_bytes();
+    let grid = Grid::new(input);
+    assert!(
+        grid.width() <= MAP_SIZE && grid.height() <= MAP_SIZE,
+        "The map is {} by {}, which is too big for the fast path",
+        grid.height(),
+        grid.width()
+    );
+    parse_lab_map_rows(grid)
+}
 
-    // let mut rows = unsafe { std::mem::MaybeUninit::<[LabMapRow; 130]>::uninit().assume_init() };
-    let mut rows = [LabMapRow::default(); MAP_SIZE];
+/// Read a map of any size, with whatever rows it's been told to use.
+pub fn parse_lab_map_rows<Row: LabRow>(grid: Grid) -> (LabMap<Row>, Guard) {
+    let mut map = LabMap::with_size(grid.width(), grid.height());
 
-    let mut i = 0;
     let mut pos = (0, 0);
     let mut direction = Direction::Right;
-    for row in 0..MAP_SIZE {
-        let map_row = unsafe { rows.get_unchecked_mut(row) };
-        for col in 0..MAP_SIZE {
-            match unsafe { input.get_unchecked(i) } {
+    for (row, (map_row, bytes)) in map.rows.iter_mut().zip(grid.rows()).enumerate() {
+        for (col, &b) in bytes.iter().enumerate() {
+            match b {
                 b'.' => {}
                 b'#' => map_row.set_obstacle(col),
-                b'\n' => {
-                    break;
-                }
-                b'^' => {
-                    pos = (row, col);
-                    direction = Direction::Up
-                }
-                b'v' => {
-                    pos = (row, col);
-                    direction = Direction::Down
-                }
-                b'<' => {
-                    pos = (row, col);
-                    direction = Direction::Left
-                }
-                b'>' => {
-                    pos = (row, col);
-                    direction = Direction::Right
-                }
-                c => unreachable!("Unexpected character: {:?}", *c as char),
+                c => match Direction::from_byte(c) {
+                    Some(d) => {
+                        pos = (row, col);
+                        direction = d
+                    }
+                    None => unreachable!("Unexpected character: {:?}", c as char),
+                },
             }
-            i += 1;
-        }
-        i += 1; // skip the newline
-        if i >= input.len() {
-            break;
         }
     }
 
-    let map = LabMap { rows };
     let guard = Guard { pos, direction };
 
     (map, guard)
@@ -265,19 +227,25 @@ pub fn parse_lab_map(input: &str) -> (LabMap, Guard) {
 
 #[aoc(day6, part1)]
 pub fn part1(input: &str) -> usize {
-    part1_with_size(input, MAP_SIZE)
+    let grid = Grid::new(input);
+    if grid.width() <= MAP_SIZE && grid.height() <= MAP_SIZE {
+        walk::<RowBitset>(grid)
+    } else {
+        walk::<DynBitset>(grid)
+    }
 }
 
 // This is solved by simply walking around the map.
 // I can't see any obvious optimizations.
-// It is possible to use bitwise operations to zoom horizontally,
+// The row bitsets let us zoom horizontally straight to the next obstacle,
 // but we still need to fill in all the visited bits, unless I'm missing someting obvious.
 // This is becuase paths intersect, so we need to not double-count where our path crosses itself.
-pub fn part1_with_size(input: &str, map_size: usize) -> usize {
-    let (lab_map, mut guard) = parse_lab_map(input);
+fn walk<Row: LabRow>(grid: Grid) -> usize {
+    let (lab_map, mut guard) = parse_lab_map_rows::<Row>(grid);
+    let (width, height) = (grid.width(), grid.height());
 
     // We've visited the staring position.
-    let mut visited = Visited::default();
+    let mut visited = Visited::<Row>::with_size(width, height);
     let mut visit_count = 0;
     loop {
         // We always mark the current position as visited.
@@ -292,43 +260,47 @@ pub fn part1_with_size(input: &str, map_size: usize) -> usize {
                 }
                 let new_row = row - 1;
                 if lab_map.obstacle_at(new_row, col) {
-                    guard.direction.turn_right();
+                    guard.direction = guard.direction.turn_right();
                 } else {
                     guard.pos.0 = new_row;
                 }
             }
             Direction::Right => {
-                if col == map_size - 1 {
-                    break;
+                let stop = lab_map
+                    .next_obstacle(row, col, Direction::Right)
+                    .map_or(width, |(_, c)| c);
+                for c in col + 1..stop {
+                    visit_count += visited.visit(row, c) as usize;
                 }
-                let new_col = col + 1;
-                if lab_map.obstacle_at(row, new_col) {
-                    guard.direction.turn_right();
-                } else {
-                    guard.pos.1 = new_col;
+                if stop == width {
+                    break;
                 }
+                guard.pos.1 = stop - 1;
+                guard.direction = guard.direction.turn_right();
             }
             Direction::Down => {
-                if row == map_size - 1 {
+                if row == height - 1 {
                     break;
                 }
                 let new_row = row + 1;
                 if lab_map.obstacle_at(new_row, col) {
-                    guard.direction.turn_right();
+                    guard.direction = guard.direction.turn_right();
                 } else {
                     guard.pos.0 = new_row;
                 }
             }
             Direction::Left => {
-                if col == 0 {
-                    break;
+                let stop = lab_map
+                    .next_obstacle(row, col, Direction::Left)
+                    .map_or(0, |(_, c)| c + 1);
+                for c in stop..col {
+                    visit_count += visited.visit(row, c) as usize;
                 }
-                let new_col = col - 1;
-                if lab_map.obstacle_at(row, new_col) {
-                    guard.direction.turn_right();
-                } else {
-                    guard.pos.1 = new_col;
+                if stop == 0 {
+                    break;
                 }
+                guard.pos.1 = stop;
+                guard.direction = guard.direction.turn_right();
             }
         }
     }
@@ -336,7 +308,6 @@ pub fn part1_with_size(input: &str, map_size: usize) -> usize {
     visit_count
 }
 
-
 // #[aoc(day6, part2)]
 // pub fn part2(input: &str) -> usize {
 //     part2_with_size(input, MAP_SIZE)
@@ -479,6 +450,30 @@ mod tests {
         assert_eq!(guard.direction, Direction::Up);
     }
 
+    #[test]
+    fn test_next_obstacle_horizontal() {
+        let input = indoc! {
+            "....#.....
+            .........#
+            ..........
+            ..#.......
+            .......#..
+            ..........
+            .#..^.....
+            ........#.
+            #.........
+            ......#...
+            "
+        };
+
+        let (lab_map, _) = parse_lab_map(input);
+        assert_eq!(lab_map.next_obstacle(6, 4, Direction::Left), Some((6, 1)));
+        assert_eq!(lab_map.next_obstacle(6, 4, Direction::Right), None);
+        assert_eq!(lab_map.next_obstacle(1, 0, Direction::Right), Some((1, 9)));
+        assert_eq!(lab_map.next_obstacle(1, 9, Direction::Right), None);
+        assert_eq!(lab_map.next_obstacle(3, 2, Direction::Left), None);
+    }
+
     #[test]
     fn test_part1_example() {
         let input = indoc! {
@@ -495,10 +490,49 @@ mod tests {
             "
         };
 
-        let visited = part1_with_size(input, 10);
+        let visited = part1(input);
         assert_eq!(visited, 41);
     }
 
+    #[test]
+    fn test_part1_example_with_dynamic_rows() {
+        let input = indoc! {
+            "....#.....
+            .........#
+            ..........
+            ..#.......
+            .......#..
+            ..........
+            .#..^.....
+            ........#.
+            #.........
+            ......#...
+            "
+        };
+
+        assert_eq!(walk::<DynBitset>(Grid::new(input)), 41);
+    }
+
+    #[test]
+    fn test_part1_bigger_than_fast_path() {
+        // She starts in the bottom left corner, walks up to the obstacle in the top left,
+        // and then turns right and walks off the edge.
+        let size = MAP_SIZE + 20;
+        let mut input = String::new();
+        for row in 0..size {
+            for col in 0..size {
+                input.push(match (row, col) {
+                    (0, 0) => '#',
+                    (r, 0) if r == size - 1 => '^',
+                    _ => '.',
+                });
+            }
+            input.push('\n');
+        }
+
+        assert_eq!(part1(&input), 2 * (size - 1));
+    }
+
     #[test]
     fn test_part1() {
         let input = include_str!("../input/2024/day6.txt");
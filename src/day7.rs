@@ -1,9 +1,11 @@
 use aoc_runner_derive::aoc;
 
-use crate::stack_vec::ArrayVec;
+use crate::stack_vec::SmallVec;
 
+// The most numbers on any line of my input.
+// Longer lines still work, but spill onto the heap.
 const MAX_NUMBERS: usize = 12;
-type NumberVec = ArrayVec<u64, MAX_NUMBERS>;
+type NumberVec = SmallVec<u64, MAX_NUMBERS>;
 
 #[derive(Debug)]
 pub struct CalibrationData {
@@ -40,21 +42,21 @@ impl<'a> Iterator for CalibrationDataIterator<'a> {
         }
 
         // Parse in the numbers.
-        let mut numbers = ArrayVec::new();
+        let mut numbers = NumberVec::new();
         'numbers: loop {
             let mut n = 0;
             'number: loop {
                 if self.pos >= self.input.len() {
-                    unsafe { numbers.push_unchecked(n) };
+                    numbers.push(n);
                     break 'numbers;
                 }
                 let &c = unsafe { self.input.get_unchecked(self.pos) };
                 if c == b' ' {
-                    unsafe { numbers.push_unchecked(n) };
+                    numbers.push(n);
                     self.pos += 1;
                     break 'number;
                 } else if c == b'\n' {
-                    unsafe { numbers.push_unchecked(n) };
+                    numbers.push(n);
                     self.pos += 1;
                     break 'numbers;
                 } else {
@@ -114,8 +116,7 @@ pub fn find_solution_1(data: &CalibrationData) -> bool {
         state: State,
     }
 
-    debug_assert!(data.numbers.len() <= MAX_NUMBERS);
-    let mut stack = [StackFrame::default(); MAX_NUMBERS];
+    let mut stack: SmallVec<StackFrame, MAX_NUMBERS> = SmallVec::zeros(data.numbers.len());
     let mut stack_pos = data.numbers.len() - 1;
     unsafe { stack.get_unchecked_mut(stack_pos).current_target = data.test_value };
 
@@ -228,8 +229,7 @@ pub fn find_solution_2(data: &CalibrationData) -> bool {
         state: State,
     }
 
-    debug_assert!(data.numbers.len() <= MAX_NUMBERS);
-    let mut stack = [StackFrame::default(); MAX_NUMBERS];
+    let mut stack: SmallVec<StackFrame, MAX_NUMBERS> = SmallVec::zeros(data.numbers.len());
     let mut stack_pos = data.numbers.len() - 1;
     unsafe { stack.get_unchecked_mut(stack_pos).current_target = data.test_value };
 
@@ -377,6 +377,17 @@ mod tests {
         assert_eq!(find_solution_1(&data), true);
     }
 
+    #[test]
+    fn test_more_numbers_than_max() {
+        // Fourteen ones, which only sum to 14.
+        let data = parse_calibration_data("14: 1 1 1 1 1 1 1 1 1 1 1 1 1 1\n")
+            .next()
+            .unwrap();
+        assert!(data.numbers.len() > MAX_NUMBERS);
+        assert!(find_solution_1(&data));
+        assert!(find_solution_2(&data));
+    }
+
     #[test]
     fn test_input() {
         let max_numbers = parse_calibration_data(include_str!("../input/2024/day7.txt"))
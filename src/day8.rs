@@ -1,10 +1,10 @@
-use std::collections::{HashMap, HashSet};
-
 use aoc_runner_derive::aoc;
 
-use crate::{bitset::*, stack_vec::ArrayVec};
+use crate::{bitset::*, grid::Grid, stack_vec::SmallVec};
 
-pub const MAP_SIZE: usize = 50;
+// My input is 50 square, so a row fits in a u64, and the rows fit on the stack.
+// Maps up to this size get that, and anything bigger gets rows that are sized to fit.
+pub const FAST_MAP_SIZE: usize = 64;
 const ANTENNA_TYPES: usize = 10 + 26 + 26;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,8 +50,9 @@ pub fn parse_skip(input: &str) -> SkipParser {
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct RC {
-    pub row: i8,
-    pub col: i8,
+    // An i8 would do for my input, but not for a map over 127 wide.
+    pub row: i16,
+    pub col: i16,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,8 +63,8 @@ pub struct PackedRC {
 
 pub struct RCParser<'a> {
     remaining: std::slice::Iter<'a, u8>,
-    row: i8,
-    col: i8,
+    row: i16,
+    col: i16,
 }
 
 impl<'a> Iterator for RCParser<'a> {
@@ -166,14 +167,37 @@ pub fn usize_to_antenna(index: usize) -> u8 {
     "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz".as_bytes()[index]
 }
 
-pub fn part1_solve_rc(input: &str, size: usize) -> u64 {
-    debug_assert!(size <= MAP_SIZE);
-    debug_assert!(size > 0);
+/// Run `solve` with a bitset for each row of the map, to mark the antinodes in.
+/// Maps that fit the fast path get u64 rows on the stack, and bigger maps get rows on the heap.
+/// The rows have room for the newline too, in case a solver lands on it.
+fn with_antinode_rows<R>(
+    grid: Grid,
+    fast: impl FnOnce(&mut [U64Bitset]) -> R,
+    slow: impl FnOnce(&mut [DynBitset]) -> R,
+) -> R {
+    if grid.stride() <= FAST_MAP_SIZE && grid.height() <= FAST_MAP_SIZE {
+        fast(&mut [U64Bitset::empty(); FAST_MAP_SIZE])
+    } else {
+        slow(&mut vec![
+            DynBitset::with_capacity(grid.stride());
+            grid.height()
+        ])
+    }
+}
+
+pub fn part1_solve_rc(input: &str) -> u64 {
+    let grid = Grid::new(input);
+    with_antinode_rows(
+        grid,
+        |antinodes| part1_solve_rc_rows(input, grid, antinodes),
+        |antinodes| part1_solve_rc_rows(input, grid, antinodes),
+    )
+}
 
+fn part1_solve_rc_rows<B: BitsetOps>(input: &str, grid: Grid, antinodes: &mut [B]) -> u64 {
     // Some sanity checks on the input
     #[cfg(debug_assertions)]
     {
-        let mut count = HashMap::new();
         for a in parse_rc(input) {
             debug_assert!(
                 (b'0'..=b'9').contains(&a.antenna)
@@ -182,18 +206,13 @@ pub fn part1_solve_rc(input: &str, size: usize) -> u64 {
                 "Antenna has unexpected type {:?}",
                 a.antenna as char
             );
-            let c = count.entry(a.antenna).and_modify(|v| *v += 1).or_insert(1);
-            assert!(
-                *c <= 4,
-                "Not expecting more than 4 antennas of type {:?}",
-                a.antenna
-            );
         }
     }
 
     // Parse the antennas input into a table indexed by antenna type.
-    // There are up to 4 antenna of each type, so we make room for exactly that.
-    let mut antennas: [ArrayVec<RC, 4>; ANTENNA_TYPES] = [ArrayVec::new(); ANTENNA_TYPES];
+    // My input has up to 4 antenna of each type, so we make room for that,
+    // and spill onto the heap for any input that has more.
+    let mut antennas: [SmallVec<RC, 4>; ANTENNA_TYPES] = [const { SmallVec::new() }; ANTENNA_TYPES];
 
     for a in parse_rc(input) {
         debug_assert!(
@@ -207,29 +226,25 @@ pub fn part1_solve_rc(input: &str, size: usize) -> u64 {
 
         // Then push each one into a list with all others of the same type.
         debug_assert!(antenna_index < ANTENNA_TYPES);
-        debug_assert!(antennas[antenna_index].len() < 4);
         debug_assert!(
-            a.antenna == (input.as_bytes()[(a.rc.row as usize) * (size + 1) + a.rc.col as usize]),
+            a.antenna == (input.as_bytes()[grid.offset(a.rc.row as usize, a.rc.col as usize)]),
             "Antenna `{}` at position ({}, {}) does not match input {}  `{}`",
             a.antenna as char,
             a.rc.row,
             a.rc.col,
-            (a.rc.row as usize) * (size + 1) + a.rc.col as usize,
-            input.as_bytes()[(a.rc.row as usize) * (size + 1) + a.rc.col as usize] as char
+            grid.offset(a.rc.row as usize, a.rc.col as usize),
+            input.as_bytes()[grid.offset(a.rc.row as usize, a.rc.col as usize)] as char
         );
         unsafe {
-            antennas
-                .get_unchecked_mut(antenna_index)
-                .push_unchecked(a.rc);
+            antennas.get_unchecked_mut(antenna_index).push(a.rc);
         }
     }
 
     // For each antenna, calculate the antinodes.
     let mut antinode_count = 0;
-    // We also need to keep track of which positions contain antinodes.
-    let mut antinodes: [U64Bitset; MAP_SIZE] = [U64Bitset::empty(); MAP_SIZE];
+    // The antinodes are kept in the row bitsets that we were given.
     unsafe {
-        let size = size as i64;
+        let (height, width) = (grid.height() as i64, grid.width() as i64);
         for ans in antennas {
             for i in 0..ans.len() {
                 let an_i = ans.get_unchecked(i);
@@ -247,14 +262,14 @@ pub fn part1_solve_rc(input: &str, size: usize) -> u64 {
                     // So we know that the row of i is always lteq the row of j.
                     // This means that we only need check the lower bound for the first antinode and
                     // the upper bound for the second antinode.
-                    if ra1 >= 0 && ca1 >= 0 && ca1 < size {
+                    if ra1 >= 0 && ca1 >= 0 && ca1 < width {
                         let became_set =
                             antinodes.get_unchecked_mut(ra1 as usize).insert(ca1 as usize);
 
                         antinode_count += became_set as u64;
                     }
 
-                    if ca2 >= 0 && ra2 < size && ca2 < size {
+                    if ca2 >= 0 && ra2 < height && ca2 < width {
                         let became_set =
                             antinodes.get_unchecked_mut(ra2 as usize).insert(ca2 as usize);
                         antinode_count += became_set as u64;
@@ -267,13 +282,19 @@ pub fn part1_solve_rc(input: &str, size: usize) -> u64 {
     antinode_count
 }
 
-pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
-    debug_assert!(size <= MAP_SIZE);
+pub fn part2_solve_rc(input: &str) -> u64 {
+    let grid = Grid::new(input);
+    with_antinode_rows(
+        grid,
+        |antinodes| part2_solve_rc_rows(input, grid, antinodes),
+        |antinodes| part2_solve_rc_rows(input, grid, antinodes),
+    )
+}
 
+fn part2_solve_rc_rows<B: BitsetOps>(input: &str, grid: Grid, antinodes: &mut [B]) -> u64 {
     // Some sanity checks on the input
     #[cfg(debug_assertions)]
     {
-        let mut count = HashMap::new();
         for a in parse_rc(input) {
             debug_assert!(
                 (b'0'..=b'9').contains(&a.antenna)
@@ -282,18 +303,13 @@ pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
                 "Antenna has unexpected type {:?}",
                 a.antenna as char
             );
-            let c = count.entry(a.antenna).and_modify(|v| *v += 1).or_insert(1);
-            assert!(
-                *c <= 4,
-                "Not expecting more than 4 antennas of type {:?}",
-                a.antenna
-            );
         }
     }
 
     // Parse the antennas input into a table indexed by antenna type.
-    // There are up to 4 antenna of each type, so we make room for exactly that.
-    let mut antennas: [ArrayVec<RC, 4>; ANTENNA_TYPES] = [ArrayVec::new(); ANTENNA_TYPES];
+    // My input has up to 4 antenna of each type, so we make room for that,
+    // and spill onto the heap for any input that has more.
+    let mut antennas: [SmallVec<RC, 4>; ANTENNA_TYPES] = [const { SmallVec::new() }; ANTENNA_TYPES];
 
     for a in parse_rc(input) {
         debug_assert!(
@@ -307,29 +323,25 @@ pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
 
         // Then push each one into a list with all others of the same type.
         debug_assert!(antenna_index < ANTENNA_TYPES);
-        debug_assert!(antennas[antenna_index].len() < 4);
         debug_assert!(
-            a.antenna == (input.as_bytes()[(a.rc.row as usize) * (size + 1) + a.rc.col as usize]),
+            a.antenna == (input.as_bytes()[grid.offset(a.rc.row as usize, a.rc.col as usize)]),
             "Antenna `{}` at position ({}, {}) does not match input {}  `{}`",
             a.antenna as char,
             a.rc.row,
             a.rc.col,
-            (a.rc.row as usize) * (size + 1) + a.rc.col as usize,
-            input.as_bytes()[(a.rc.row as usize) * (size + 1) + a.rc.col as usize] as char
+            grid.offset(a.rc.row as usize, a.rc.col as usize),
+            input.as_bytes()[grid.offset(a.rc.row as usize, a.rc.col as usize)] as char
         );
         unsafe {
-            antennas
-                .get_unchecked_mut(antenna_index as usize)
-                .push_unchecked(a.rc);
+            antennas.get_unchecked_mut(antenna_index).push(a.rc);
         }
     }
 
     // For each antenna, calculate the antinodes.
     let mut antinode_count = 0;
-    // We also need to keep track of which positions contain antinodes.
-    let mut antinodes: [U64Bitset; MAP_SIZE] = [U64Bitset::empty(); MAP_SIZE];
+    // The antinodes are kept in the row bitsets that we were given.
     unsafe {
-        let size = size as i64;
+        let (height, width) = (grid.height() as i64, grid.width() as i64);
         for ans in antennas {
             for i in 0..ans.len() {
                 let an_i = ans.get_unchecked(i);
@@ -352,7 +364,7 @@ pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
 
                         r1 -= rd;
                         c1 -= cd;
-                        if r1 < 0 || c1 < 0 || c1 >= size {
+                        if r1 < 0 || c1 < 0 || c1 >= width {
                             break;
                         }
                     }
@@ -363,7 +375,7 @@ pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
 
                         r2 += rd;
                         c2 += cd;
-                        if r2 >= size || c2 >= size || c2 < 0 {
+                        if r2 >= height || c2 >= width || c2 < 0 {
                             break;
                         }
                     }
@@ -378,11 +390,20 @@ pub fn part2_solve_rc(input: &str, size: usize) -> u64 {
 // I thought that this implementation would be faster, but it is consistently slower than the _rc implementation.
 // Without profiling, I don't know why.
 // It must be something to do with memory access patterns, as as far as I can tell this does less calculation.
-pub fn part1_solve_enumerated(input: &str, size: usize) -> u64 {
-    debug_assert!(size <= MAP_SIZE);
-    debug_assert!(size > 0);
+pub fn part1_solve_enumerated(input: &str) -> u64 {
+    // This marks the antinodes by their offset into the input, so the bitset needs a bit for every byte.
+    if input.len() <= PackedU64Bitset::<40>::fixed_capacity() {
+        part1_solve_enumerated_in(input, PackedU64Bitset::<40>::empty())
+    } else {
+        part1_solve_enumerated_in(input, DynBitset::with_capacity(input.len()))
+    }
+}
 
-    let mut antennas: [ArrayVec<i64, 4>; ANTENNA_TYPES] = [ArrayVec::new(); ANTENNA_TYPES];
+fn part1_solve_enumerated_in<B: BitsetOpsUnsafe>(input: &str, mut antinodes: B) -> u64 {
+    let grid = Grid::new(input);
+
+    let mut antennas: [SmallVec<i64, 4>; ANTENNA_TYPES] =
+        [const { SmallVec::new() }; ANTENNA_TYPES];
 
     // Loop over the input.
     // We only want the radio antennas, and their offset into the input.
@@ -400,17 +421,14 @@ pub fn part1_solve_enumerated(input: &str, size: usize) -> u64 {
         // First thing is to convert the antenna letters into an index 0..(10+26+26)
         let antenna_index = antenna_to_index_usize_early(*c);
         unsafe {
-            antennas
-                .get_unchecked_mut(antenna_index)
-                .push_unchecked(pos);
+            antennas.get_unchecked_mut(antenna_index).push(pos);
         }
     }
 
     let mut antinode_count = 0;
-    let mut antinodes = PackedU64Bitset::<40>::empty();
     unsafe {
-        let size = size as i64;
-        let row_byte_count = size + 1;
+        let size = grid.width() as i64;
+        let row_byte_count = grid.stride() as i64;
         for ans in antennas {
             for i in 0..ans.len() {
                 let an_i = ans.get_unchecked(i);
@@ -460,11 +478,22 @@ pub fn part1_solve_enumerated(input: &str, size: usize) -> u64 {
 // There's possibly a speedup here where we use an array of bitsets rather than a packed bitset for the antennas mask.
 // But nothing statistically significant.
 // So whatever makes this slower than the _rc version is a mystery to me.
-pub fn part1_solve_enumerated2(input: &str, size: usize) -> u64 {
-    debug_assert!(size <= MAP_SIZE);
-    debug_assert!(size > 0);
+pub fn part1_solve_enumerated2(input: &str) -> u64 {
+    let grid = Grid::new(input);
+    with_antinode_rows(
+        grid,
+        |antinodes| part1_solve_enumerated2_rows(input, grid, antinodes),
+        |antinodes| part1_solve_enumerated2_rows(input, grid, antinodes),
+    )
+}
 
-    let mut antennas: [ArrayVec<i64, 4>; ANTENNA_TYPES] = [ArrayVec::new(); ANTENNA_TYPES];
+fn part1_solve_enumerated2_rows<B: BitsetOpsUnsafe>(
+    input: &str,
+    grid: Grid,
+    antinodes: &mut [B],
+) -> u64 {
+    let mut antennas: [SmallVec<i64, 4>; ANTENNA_TYPES] =
+        [const { SmallVec::new() }; ANTENNA_TYPES];
 
     // Loop over the input.
     // We only want the radio antennas, and their offset into the input.
@@ -482,18 +511,15 @@ pub fn part1_solve_enumerated2(input: &str, size: usize) -> u64 {
         // First thing is to convert the antenna letters into an index 0..(10+26+26)
         let antenna_index = antenna_to_index_usize_early(*c);
         unsafe {
-            antennas
-                .get_unchecked_mut(antenna_index)
-                .push_unchecked(pos);
+            antennas.get_unchecked_mut(antenna_index).push(pos);
         }
     }
 
     let mut antinode_count = 0;
-    let mut antinodes: [U64Bitset; MAP_SIZE] = [U64Bitset::empty(); MAP_SIZE];
 
     unsafe {
-        let size = size as i64;
-        let row_byte_count = size + 1;
+        let size = grid.width() as i64;
+        let row_byte_count = grid.stride() as i64;
         for ans in antennas {
             for i in 0..ans.len() {
                 let an_i = ans.get_unchecked(i);
@@ -548,16 +574,17 @@ pub fn part1_solve_enumerated2(input: &str, size: usize) -> u64 {
 
 #[aoc(day8, part1)]
 pub fn part1(input: &str) -> u64 {
-    part1_solve_rc(input, MAP_SIZE)
+    part1_solve_rc(input)
 }
 
 #[aoc(day8, part2)]
 pub fn part2(input: &str) -> u64 {
-    part2_solve_rc(input, MAP_SIZE)
+    part2_solve_rc(input)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::f32::consts::PI;
 
     use super::*;
@@ -728,17 +755,93 @@ mod tests {
 
     #[test]
     fn test_part1_rc_example() {
-        let count = part1_solve_rc(EXAMPLE, 12);
+        let count = part1_solve_rc(EXAMPLE);
         assert_eq!(count, 14);
     }
 
+    #[test]
+    fn test_part1_rc_example_with_dynamic_rows() {
+        let grid = Grid::new(EXAMPLE);
+        let mut antinodes = vec![DynBitset::with_capacity(grid.stride()); grid.height()];
+        assert_eq!(part1_solve_rc_rows(EXAMPLE, grid, &mut antinodes), 14);
+    }
+
+    #[test]
+    fn test_bigger_than_fast_path() {
+        // Two antennas on the diagonal, 15 apart, past where an i8 would overflow.
+        let size = 150;
+        let mut input = String::new();
+        for row in 0..size {
+            for col in 0..size {
+                input.push(match (row, col) {
+                    (110, 110) | (125, 125) => 'a',
+                    _ => '.',
+                });
+            }
+            input.push('\n');
+        }
+
+        // One step either side of the pair.
+        assert_eq!(part1_solve_rc(&input), 2);
+        // Every 15 steps along the diagonal, from 5 up to 140.
+        assert_eq!(part2_solve_rc(&input), 10);
+    }
+
+    #[test]
+    fn test_more_than_four_antennas_of_a_type() {
+        let input = indoc! {
+           "............
+            ...a........
+            ............
+            ....a.......
+            .......a....
+            ............
+            .....a......
+            ............
+            ..........a.
+            ............
+            ............
+            ............
+            "
+        };
+
+        // Every pair, done the slow way.
+        let grid = Grid::new(input);
+        let antennas = grid.find_all(b'a').collect::<Vec<_>>();
+        assert_eq!(antennas.len(), 5);
+        let (height, width) = (grid.height() as isize, grid.width() as isize);
+        let mut part1_antinodes = HashSet::new();
+        let mut part2_antinodes = HashSet::new();
+        for (i, &(r1, c1)) in antennas.iter().enumerate() {
+            for &(r2, c2) in &antennas[i + 1..] {
+                let (rd, cd) = (r2 as isize - r1 as isize, c2 as isize - c1 as isize);
+                for (start, step) in [((r1, c1), (-rd, -cd)), ((r2, c2), (rd, cd))] {
+                    let (mut r, mut c) = (start.0 as isize, start.1 as isize);
+                    let mut steps = 0;
+                    while r >= 0 && c >= 0 && r < height && c < width {
+                        if steps == 1 {
+                            part1_antinodes.insert((r, c));
+                        }
+                        part2_antinodes.insert((r, c));
+                        r += step.0;
+                        c += step.1;
+                        steps += 1;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(part1_solve_rc(input), part1_antinodes.len() as u64);
+        assert_eq!(part2_solve_rc(input), part2_antinodes.len() as u64);
+    }
+
     #[test]
     fn test_part1_rc() {
         assert_eq!(part1(DAY8_INPUT), 323);
     }
 
     fn test_part1_enumerated() {
-        assert_eq!(part1_solve_enumerated(DAY8_INPUT, MAP_SIZE), 323);
+        assert_eq!(part1_solve_enumerated(DAY8_INPUT), 323);
     }
 
     #[test]
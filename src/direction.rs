@@ -0,0 +1,316 @@
+///- Compass directions for walking around grids.
+///- Both kinds of direction are numbered clockwise from up, so turning is just adding to the index.
+///- The index is also small enough to use as a bit, so a set of directions fits in a `u8`,
+///- and a solver can remember which way it was facing when it visited somewhere.
+use std::marker::PhantomData;
+
+/// The things all the directions can do, worked out from their index clockwise from up.
+pub trait Compass: Copy + 'static {
+    /// Every direction, clockwise from up.
+    const ALL: &'static [Self];
+
+    /// The `(dr, dc)` step for each direction, in the same order as `ALL`.
+    const DELTAS: &'static [(isize, isize)];
+
+    /// The position of this direction in `ALL`.
+    fn index(self) -> usize;
+
+    /// The direction at `index` in `ALL`, wrapping round.
+    fn from_index(index: usize) -> Self {
+        Self::ALL[index % Self::ALL.len()]
+    }
+
+    /// A quarter turn clockwise.
+    fn turn_right(self) -> Self {
+        Self::from_index(self.index() + Self::ALL.len() / 4)
+    }
+
+    /// A quarter turn anticlockwise.
+    fn turn_left(self) -> Self {
+        Self::from_index(self.index() + Self::ALL.len() * 3 / 4)
+    }
+
+    /// Turn around.
+    fn turn_back(self) -> Self {
+        Self::from_index(self.index() + Self::ALL.len() / 2)
+    }
+
+    /// The `(dr, dc)` of one step this way.
+    fn delta(self) -> (isize, isize) {
+        Self::DELTAS[self.index()]
+    }
+
+    /// How far one step this way moves through the bytes of a grid with `stride` bytes per row.
+    fn offset(self, stride: isize) -> isize {
+        let (dr, dc) = self.delta();
+        dr * stride + dc
+    }
+
+    /// This direction as a single bit, for keeping sets of directions in a `u8`.
+    fn bit(self) -> u8 {
+        1 << self.index()
+    }
+
+    /// All the directions, clockwise from up.
+    fn all() -> CompassIter<Self> {
+        CompassIter::default()
+    }
+}
+
+/// The 4 directions you can go in without going diagonally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Direction {
+    #[default]
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Compass for Direction {
+    const ALL: &'static [Self] = &[
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    const DELTAS: &'static [(isize, isize)] = &[(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Direction {
+    /// The direction drawn as `^`, `>`, `v` or `<`, or `None` for anything else.
+    pub fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            b'^' => Some(Direction::Up),
+            b'>' => Some(Direction::Right),
+            b'v' => Some(Direction::Down),
+            b'<' => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    /// The arrow for this direction, the other way round from `from_byte`.
+    pub fn to_byte(self) -> u8 {
+        b"^>v<"[self.index()]
+    }
+}
+
+impl TryFrom<u8> for Direction {
+    type Error = u8;
+
+    fn try_from(b: u8) -> Result<Self, u8> {
+        Direction::from_byte(b).ok_or(b)
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_byte() as char)
+    }
+}
+
+/// The 8 directions, including the diagonals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Direction8 {
+    #[default]
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Compass for Direction8 {
+    const ALL: &'static [Self] = &[
+        Direction8::Up,
+        Direction8::UpRight,
+        Direction8::Right,
+        Direction8::DownRight,
+        Direction8::Down,
+        Direction8::DownLeft,
+        Direction8::Left,
+        Direction8::UpLeft,
+    ];
+
+    const DELTAS: &'static [(isize, isize)] = &[
+        (-1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+        (1, 0),
+        (1, -1),
+        (0, -1),
+        (-1, -1),
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Direction8 {
+    /// An eighth of a turn clockwise.
+    pub fn turn_right_45(self) -> Self {
+        Self::from_index(self.index() + 1)
+    }
+
+    /// An eighth of a turn anticlockwise.
+    pub fn turn_left_45(self) -> Self {
+        Self::from_index(self.index() + 7)
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        self.index() % 2 == 1
+    }
+}
+
+impl From<Direction> for Direction8 {
+    fn from(d: Direction) -> Self {
+        Direction8::from_index(d.index() * 2)
+    }
+}
+
+/// Walks through `ALL`.
+/// It's `Copy`, unlike the array iterator, so it can live in a stack frame and remember where it's got to.
+#[derive(Clone, Copy, Debug)]
+pub struct CompassIter<D> {
+    next: u8,
+    _direction: PhantomData<D>,
+}
+
+pub type DirectionIter = CompassIter<Direction>;
+pub type Direction8Iter = CompassIter<Direction8>;
+
+impl<D> Default for CompassIter<D> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            _direction: PhantomData,
+        }
+    }
+}
+
+impl<D: Compass> Iterator for CompassIter<D> {
+    type Item = D;
+
+    fn next(&mut self) -> Option<D> {
+        let d = D::ALL.get(self.next as usize).copied();
+        if d.is_some() {
+            self.next += 1;
+        }
+        d
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = D::ALL.len() - self.next as usize;
+        (left, Some(left))
+    }
+}
+
+impl<D: Compass> ExactSizeIterator for CompassIter<D> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turning() {
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Left.turn_right(), Direction::Up);
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::Right.turn_back(), Direction::Left);
+
+        assert_eq!(Direction8::UpLeft.turn_right(), Direction8::UpRight);
+        assert_eq!(Direction8::Up.turn_left(), Direction8::Left);
+        assert_eq!(Direction8::DownRight.turn_back(), Direction8::UpLeft);
+        assert_eq!(Direction8::UpLeft.turn_right_45(), Direction8::Up);
+        assert_eq!(Direction8::Up.turn_left_45(), Direction8::UpLeft);
+
+        for d in Direction::all() {
+            assert_eq!(d.turn_right().turn_left(), d);
+            assert_eq!(d.turn_right().turn_right(), d.turn_back());
+            assert_eq!(
+                Direction8::from(d).turn_right(),
+                Direction8::from(d.turn_right())
+            );
+        }
+    }
+
+    #[test]
+    fn all_goes_clockwise() {
+        assert_eq!(
+            Direction::all().collect::<Vec<_>>(),
+            vec![
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left
+            ]
+        );
+        assert_eq!(Direction8::all().len(), 8);
+        for (i, d) in Direction8::all().enumerate() {
+            assert_eq!(d.index(), i);
+            assert_eq!(Direction8::from_index(i), d);
+            assert_eq!(d.is_diagonal(), i % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn the_iterator_can_be_copied_part_way() {
+        let mut dirs = Direction::all();
+        dirs.next();
+        let mut copy = dirs;
+        assert_eq!(copy.next(), Some(Direction::Right));
+        assert_eq!(dirs.len(), 3);
+        assert_eq!(dirs.nth(2), Some(Direction::Left));
+        assert_eq!(dirs.next(), None);
+    }
+
+    #[test]
+    fn deltas_and_offsets() {
+        assert_eq!(Direction::Up.delta(), (-1, 0));
+        assert_eq!(Direction::Left.delta(), (0, -1));
+        assert_eq!(Direction8::DownLeft.delta(), (1, -1));
+        assert_eq!(Direction::Down.offset(11), 11);
+        assert_eq!(Direction::Left.offset(11), -1);
+        assert_eq!(Direction8::UpRight.offset(11), -10);
+
+        for d in Direction8::all() {
+            let (dr, dc) = d.delta();
+            assert_eq!(d.turn_back().delta(), (-dr, -dc));
+        }
+        for d in Direction::all() {
+            assert_eq!(Direction8::from(d).delta(), d.delta());
+        }
+    }
+
+    #[test]
+    fn arrows() {
+        for d in Direction::all() {
+            assert_eq!(Direction::from_byte(d.to_byte()), Some(d));
+        }
+        assert_eq!(Direction::try_from(b'v'), Ok(Direction::Down));
+        assert_eq!(Direction::try_from(b'#'), Err(b'#'));
+        assert_eq!(format!("{}", Direction::Left), "<");
+    }
+
+    #[test]
+    fn bits_are_distinct() {
+        let all = Direction8::all().fold(0u8, |bits, d| {
+            assert_eq!(bits & d.bit(), 0);
+            bits | d.bit()
+        });
+        assert_eq!(all, u8::MAX);
+        assert_eq!(Direction::Down.bit(), 0b0100);
+    }
+}
@@ -0,0 +1,345 @@
+///- A grid of bytes, laid out the way the puzzle inputs give it to us.
+///- Each row is `width` bytes followed by a newline, so row `r` starts `r * stride` bytes in, where `stride = width + 1`.
+///- The last newline may or may not be there.
+///- `Grid` is just a view over the input, so it's free to make, and the byte offsets it hands out index the input directly.
+use std::ops::Index;
+
+use crate::direction::Compass;
+
+/// The `(dr, dc)` steps to the 4 neighbours that share an edge, clockwise from up.
+pub const NEIGHBOURS_4: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// The `(dr, dc)` steps to all 8 neighbours, clockwise from up.
+pub const NEIGHBOURS_8: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid<'a> {
+    bytes: &'a [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl<'a> Grid<'a> {
+    /// A view over `input`, with the width taken from the first line.
+    ///
+    /// Panics if the input doesn't divide into whole rows of that width.
+    pub fn new(input: &'a str) -> Self {
+        Self::from_bytes(input.as_bytes())
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        let width = memchr::memchr(b'\n', bytes).unwrap_or(bytes.len());
+        let stride = width + 1;
+
+        // Pretend there's always a final newline, and then it should be a whole number of rows.
+        let height = (bytes.len() + 1) / stride;
+        let overhang = (bytes.len() + 1) % stride;
+        assert!(
+            overhang == 0 || overhang == 1,
+            "Not expecting a trailing fragment: width: {}, stride: {}, height: {}, overhang: {}",
+            width,
+            stride,
+            height,
+            overhang
+        );
+
+        Self {
+            bytes,
+            width,
+            height,
+            stride,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The number of bytes from the start of one row to the start of the next, including the newline.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// All of the input, newlines included.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Is `(row, col)` on the grid?
+    /// This takes signed coordinates, so that a step off the top or left can be checked too.
+    pub fn in_bounds(&self, row: isize, col: isize) -> bool {
+        row >= 0 && col >= 0 && (row as usize) < self.height && (col as usize) < self.width
+    }
+
+    /// The offset into the input of `(row, col)`.
+    pub fn offset(&self, row: usize, col: usize) -> usize {
+        debug_assert!(row < self.height && col < self.width);
+        row * self.stride + col
+    }
+
+    /// The `(row, col)` of an offset into the input.
+    /// An offset of a newline comes out with `col == width`.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        (offset / self.stride, offset % self.stride)
+    }
+
+    /// The byte at `(row, col)`, or `None` if that's off the grid.
+    pub fn get(&self, row: isize, col: isize) -> Option<u8> {
+        if self.in_bounds(row, col) {
+            Some(self.bytes[self.offset(row as usize, col as usize)])
+        } else {
+            None
+        }
+    }
+
+    /// # Safety
+    /// `(row, col)` must be on the grid.
+    pub unsafe fn get_unchecked(&self, row: usize, col: usize) -> u8 {
+        *self.bytes.get_unchecked(self.offset(row, col))
+    }
+
+    /// The bytes of row `row`, without the newline.
+    pub fn row(&self, row: usize) -> &'a [u8] {
+        assert!(
+            row < self.height,
+            "row (is {}) should be < height (is {})",
+            row,
+            self.height
+        );
+        let start = row * self.stride;
+        &self.bytes[start..start + self.width]
+    }
+
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &'a [u8]> + ExactSizeIterator + 'a {
+        let grid = *self;
+        (0..self.height).map(move |r| grid.row(r))
+    }
+
+    /// The bytes of column `col`, from the top down.
+    pub fn column(&self, col: usize) -> impl Iterator<Item = u8> + 'a {
+        assert!(
+            col < self.width,
+            "column (is {}) should be < width (is {})",
+            col,
+            self.width
+        );
+        self.bytes[col..]
+            .iter()
+            .step_by(self.stride)
+            .take(self.height)
+            .copied()
+    }
+
+    /// The bytes from `(row, col)` heading down and to the right, until we fall off the grid.
+    pub fn diagonal(&self, row: usize, col: usize) -> impl Iterator<Item = u8> + 'a {
+        self.line(row, col, 1, 1)
+    }
+
+    /// The bytes from `(row, col)` heading down and to the left, until we fall off the grid.
+    pub fn anti_diagonal(&self, row: usize, col: usize) -> impl Iterator<Item = u8> + 'a {
+        self.line(row, col, 1, -1)
+    }
+
+    /// The bytes from `(row, col)` in steps of `(dr, dc)`, until we fall off the grid.
+    pub fn line(
+        &self,
+        row: usize,
+        col: usize,
+        dr: isize,
+        dc: isize,
+    ) -> impl Iterator<Item = u8> + 'a {
+        let grid = *self;
+        (0..)
+            .map(move |i| (row as isize + i * dr, col as isize + i * dc))
+            .map_while(move |(r, c)| grid.get(r, c))
+    }
+
+    /// The positions next to `(row, col)`, stepping by each of `steps`, leaving out any that are off the grid.
+    pub fn neighbours<const N: usize>(
+        &self,
+        row: usize,
+        col: usize,
+        steps: [(isize, isize); N],
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let grid = *self;
+        steps
+            .into_iter()
+            .map(move |(dr, dc)| (row as isize + dr, col as isize + dc))
+            .filter(move |&(r, c)| grid.in_bounds(r, c))
+            .map(|(r, c)| (r as usize, c as usize))
+    }
+
+    /// The up to 4 positions that share an edge with `(row, col)`.
+    pub fn neighbours_4(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.neighbours(row, col, NEIGHBOURS_4)
+    }
+
+    /// The up to 8 positions that share an edge or a corner with `(row, col)`.
+    pub fn neighbours_8(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.neighbours(row, col, NEIGHBOURS_8)
+    }
+
+    /// One step from `(row, col)` in direction `d`, or `None` if that's off the grid.
+    pub fn step<D: Compass>(&self, row: usize, col: usize, d: D) -> Option<(usize, usize)> {
+        let (dr, dc) = d.delta();
+        let (r, c) = (row as isize + dr, col as isize + dc);
+        self.in_bounds(r, c).then_some((r as usize, c as usize))
+    }
+
+    /// The positions of every `byte` on the grid, in reading order.
+    pub fn find_all(&self, byte: u8) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let grid = *self;
+        memchr::memchr_iter(byte, self.bytes).map(move |offset| grid.position(offset))
+    }
+}
+
+impl Index<(usize, usize)> for Grid<'_> {
+    type Output = u8;
+
+    fn index(&self, (row, col): (usize, usize)) -> &u8 {
+        assert!(
+            row < self.height && col < self.width,
+            "({}, {}) is off a grid of height {} and width {}",
+            row,
+            col,
+            self.height,
+            self.width
+        );
+        &self.bytes[self.offset(row, col)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const EXAMPLE: &str = indoc! {"
+        abcd
+        efgh
+        ijkl
+    "};
+
+    #[test]
+    fn dimensions() {
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!((grid.width(), grid.height(), grid.stride()), (4, 3, 5));
+
+        // Without the final newline, it's the same grid.
+        let grid = Grid::new(EXAMPLE.trim_end());
+        assert_eq!((grid.width(), grid.height(), grid.stride()), (4, 3, 5));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not expecting a trailing fragment")]
+    fn ragged_input_panics() {
+        Grid::new("abcd\nefgh\nij\n");
+    }
+
+    #[test]
+    fn offsets_and_positions() {
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!(grid.offset(1, 2), 7);
+        assert_eq!(grid.bytes()[7], b'g');
+        assert_eq!(grid.position(7), (1, 2));
+        // The newline at the end of the first row.
+        assert_eq!(grid.position(4), (0, 4));
+        for row in 0..grid.height() {
+            for col in 0..grid.width() {
+                assert_eq!(grid.position(grid.offset(row, col)), (row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_checked_access() {
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!(grid.get(0, 0), Some(b'a'));
+        assert_eq!(grid.get(2, 3), Some(b'l'));
+        assert_eq!(grid.get(-1, 0), None);
+        assert_eq!(grid.get(0, -1), None);
+        assert_eq!(grid.get(3, 0), None);
+        // That's the newline, which isn't on the grid.
+        assert_eq!(grid.get(0, 4), None);
+        assert_eq!(grid[(1, 1)], b'f');
+        assert_eq!(unsafe { grid.get_unchecked(2, 0) }, b'i');
+    }
+
+    #[test]
+    #[should_panic(expected = "(0, 4) is off a grid of height 3 and width 4")]
+    fn index_off_the_grid_panics() {
+        let _ = Grid::new(EXAMPLE)[(0, 4)];
+    }
+
+    #[test]
+    fn rows_columns_and_diagonals() {
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!(grid.row(1), b"efgh");
+        assert_eq!(grid.rows().next_back(), Some(&b"ijkl"[..]));
+        assert_eq!(grid.column(2).collect::<Vec<_>>(), b"cgk");
+        assert_eq!(grid.diagonal(0, 0).collect::<Vec<_>>(), b"afk");
+        assert_eq!(grid.diagonal(0, 2).collect::<Vec<_>>(), b"ch");
+        assert_eq!(grid.anti_diagonal(0, 3).collect::<Vec<_>>(), b"dgj");
+        assert_eq!(grid.line(2, 3, 0, -1).collect::<Vec<_>>(), b"lkji");
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!(
+            grid.neighbours_4(0, 0).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0)]
+        );
+        assert_eq!(
+            grid.neighbours_4(1, 1).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 2), (2, 1), (1, 0)]
+        );
+        assert_eq!(grid.neighbours_8(1, 1).count(), 8);
+        assert_eq!(
+            grid.neighbours_8(2, 3).collect::<Vec<_>>(),
+            vec![(1, 3), (2, 2), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn stepping() {
+        use crate::direction::{Direction, Direction8};
+
+        let grid = Grid::new(EXAMPLE);
+        assert_eq!(grid.step(0, 0, Direction::Right), Some((0, 1)));
+        assert_eq!(grid.step(0, 0, Direction::Up), None);
+        assert_eq!(grid.step(2, 3, Direction::Right), None);
+        assert_eq!(grid.step(1, 1, Direction8::DownLeft), Some((2, 0)));
+    }
+
+    #[test]
+    fn find_all() {
+        let grid = Grid::new("a.a\n.a.\n");
+        assert_eq!(
+            grid.find_all(b'a').collect::<Vec<_>>(),
+            vec![(0, 0), (0, 2), (1, 1)]
+        );
+    }
+}
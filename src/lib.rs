@@ -4,11 +4,16 @@
 #![feature(strict_overflow_ops)]
 #![feature(slice_internals)]
 #![feature(portable_simd)]
+// Only the exact bitset picks its storage with const expressions, so the incomplete feature comes with it.
+#![cfg_attr(feature = "exact", feature(generic_const_exprs))]
+#![cfg_attr(feature = "exact", allow(incomplete_features))]
 
 use aoc_runner;
 use aoc_runner_derive::aoc_lib;
 
 pub mod bitset;
+pub mod direction;
+pub mod grid;
 pub mod stack_vec;
 
 pub mod day1;
@@ -1,42 +1,56 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::{self, Debug, Formatter};
+use std::iter::Chain;
 use std::mem::MaybeUninit;
-use std::slice::Iter;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::ptr;
+use std::slice::{self, Iter, IterMut, SliceIndex};
 
-#[derive(Clone, Copy)]
+/// An element that didn't fit, handed back along with the capacity it didn't fit in.
+///
+/// Where there's no single element to give back, such as extending from a slice, the element is `()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError<T = ()> {
+    pub element: T,
+    pub capacity: usize,
+}
+
+impl<T> fmt::Display for CapacityError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Already full, with capacity {}", self.capacity)
+    }
+}
+
+impl<T: Debug> std::error::Error for CapacityError<T> {}
+
+/// A `Vec` with a fixed capacity of `N`, stored inline.
+///
+/// Only the first `len` slots hold elements. The rest are uninitialized, and are never read or dropped.
+/// For `Copy` elements, like the solvers use, dropping is a no-op and cloning is a plain copy of each element,
+/// so this costs nothing over the bare array it used to be.
 pub struct ArrayVec<T, const N: usize> {
     len: usize,
-    data: [T; N],
+    data: [MaybeUninit<T>; N],
 }
 
 impl<T: Default + Copy, const N: usize> ArrayVec<T, N> {
     pub fn zeros(len: usize) -> Self {
+        assert!(
+            len <= N,
+            "length (is {}) should be <= capacity (is {})",
+            len,
+            N
+        );
         Self {
-            data: [T::default(); N],
-            len: len,
-        }
-    }
-}
-
-impl<T: Copy, const N: usize> ArrayVec<T, N> {
-    pub fn pop(&mut self) -> Option<T> {
-        if self.len == 0 {
-            None
-        } else {
-            self.len -= 1;
-            unsafe { Some(*self.data.get_unchecked(self.len)) }
+            data: [MaybeUninit::new(T::default()); N],
+            len,
         }
     }
-
-    pub unsafe fn pop_unsafe(&mut self) -> T {
-        self.len -= 1;
-        *self.data.get_unchecked(self.len)
-    }
 }
 
 impl<T, const N: usize> ArrayVec<T, N> {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
-            data: unsafe { MaybeUninit::uninit().assume_init() },
+            data: [const { MaybeUninit::uninit() }; N],
             len: 0,
         }
     }
@@ -44,28 +58,45 @@ impl<T, const N: usize> ArrayVec<T, N> {
 
 #[allow(dead_code)]
 impl<T, const N: usize> ArrayVec<T, N> {
-    pub unsafe fn clear(&mut self) {
-        self.len = 0;
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            unsafe { Some(self.pop_unsafe()) }
+        }
+    }
+
+    /// # Safety
+    /// The vec must not be empty.
+    pub unsafe fn pop_unsafe(&mut self) -> T {
+        self.len -= 1;
+        ptr::read(self.as_ptr().add(self.len))
     }
 
+    /// # Safety
+    /// The vec must not be full.
     pub unsafe fn push_unchecked(&mut self, value: T) {
-        *self.data.get_unchecked_mut(self.len) = value;
+        self.data.get_unchecked_mut(self.len).write(value);
         self.len += 1;
     }
 
+    /// # Safety
+    /// `index` must be less than `len`.
     pub unsafe fn get_unchecked(&self, index: usize) -> &T {
-        &self.data.get_unchecked(index)
+        self.data.get_unchecked(index).assume_init_ref()
     }
 
+    /// # Safety
+    /// `index` must be less than `len`.
     pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut T {
-        self.data.get_unchecked_mut(index)
+        self.data.get_unchecked_mut(index).assume_init_mut()
     }
 
     pub fn get_last(&self) -> Option<&T> {
         if self.len == 0 {
             None
         } else {
-            unsafe { Some(self.data.get_unchecked(self.len - 1)) }
+            unsafe { Some(self.get_unchecked(self.len - 1)) }
         }
     }
 
@@ -73,24 +104,291 @@ impl<T, const N: usize> ArrayVec<T, N> {
         if self.len == 0 {
             None
         } else {
-            unsafe { Some(self.data.get_unchecked_mut(self.len - 1)) }
+            unsafe { Some(self.get_unchecked_mut(self.len - 1)) }
         }
     }
 
     pub fn iter(&self) -> Iter<'_, T> {
-        self.data[..self.len()].iter()
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn remaining_capacity(&self) -> usize {
+        N - self.len
+    }
+
     pub fn as_slice(&self) -> &[T] {
-        &self.data[..self.len]
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
     }
 
     pub fn as_mut_slice(&mut self) -> &mut [T] {
-        &mut self.data[..self.len]
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    // Everything that moves elements about goes through these, rather than the slices,
+    // as the slots past `len` don't hold elements.
+    // `MaybeUninit<T>` has the same layout as `T`, so the array is an array of `T`s, some of them not there yet.
+    fn as_ptr(&self) -> *const T {
+        self.data.as_ptr() as *const T
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr() as *mut T
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// Only the elements are cloned, as the rest of the slots have nothing in them.
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::new();
+        for value in self.iter() {
+            // If a clone panics, `vec` drops the ones made so far.
+            unsafe { vec.push_unchecked(value.clone()) };
+        }
+        vec
+    }
+}
+
+// The safe API.
+// Everything that can overflow the capacity has a `try_` form that hands the element back in a `CapacityError`,
+// and a plain form that panics with it, just like `Vec` does when it runs out of memory.
+impl<T, const N: usize> ArrayVec<T, N> {
+    pub fn push(&mut self, value: T) {
+        if let Err(e) = self.try_push(value) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_push(&mut self, value: T) -> Result<(), CapacityError<T>> {
+        if self.len < N {
+            unsafe { self.push_unchecked(value) };
+            Ok(())
+        } else {
+            Err(CapacityError {
+                element: value,
+                capacity: N,
+            })
+        }
+    }
+
+    /// Insert at `index`, shifting everything after it up by one.
+    ///
+    /// Panics if `index > len`, or if there's no room.
+    pub fn insert(&mut self, index: usize, value: T) {
+        if let Err(e) = self.try_insert(index, value) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Insert at `index`, shifting everything after it up by one.
+    ///
+    /// An `index` past the end is a bug rather than a lack of room, so that still panics.
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), CapacityError<T>> {
+        assert!(
+            index <= self.len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            self.len
+        );
+        if self.len == N {
+            return Err(CapacityError {
+                element: value,
+                capacity: N,
+            });
+        }
+
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), self.len - index);
+            ptr::write(p, value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove and return the element at `index`, shifting everything after it down by one.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len,
+            "removal index (is {}) should be < len (is {})",
+            index,
+            self.len
+        );
+
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            let value = ptr::read(p);
+            ptr::copy(p.add(1), p, self.len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    /// Remove and return the element at `index`, replacing it with the last element.
+    /// This doesn't preserve the order, but is O(1).
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len,
+            "swap_remove index (is {}) should be < len (is {})",
+            index,
+            self.len
+        );
+
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        unsafe { self.pop_unsafe() }
+    }
+
+    /// Drop everything from `len` onwards.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            let tail = ptr::slice_from_raw_parts_mut(
+                unsafe { self.as_mut_ptr().add(len) },
+                self.len - len,
+            );
+            // Shorten first, so that if a drop panics, we don't drop anything twice.
+            self.len = len;
+            unsafe { ptr::drop_in_place(tail) };
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Keep only the elements for which `keep` returns true, in their original order.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        let len = self.len;
+        // If `keep` panics, the elements we haven't got to yet are leaked, rather than dropped twice.
+        self.len = 0;
+
+        // Taking a fresh pointer each time round would invalidate the last one, so we work from one base.
+        let base = self.as_mut_ptr();
+        let mut kept = 0;
+        for i in 0..len {
+            unsafe {
+                let p = base.add(i);
+                if keep(&*p) {
+                    ptr::copy(p, base.add(kept), 1);
+                    kept += 1;
+                } else {
+                    ptr::drop_in_place(p);
+                }
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl<T: Clone, const N: usize> ArrayVec<T, N> {
+    /// Append clones of all of `other`, or nothing at all if they won't all fit.
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), CapacityError> {
+        if other.len() > self.remaining_capacity() {
+            return Err(CapacityError {
+                element: (),
+                capacity: N,
+            });
+        }
+        for value in other {
+            unsafe { self.push_unchecked(value.clone()) };
+        }
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for ArrayVec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for ArrayVec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> Index<I> for ArrayVec<T, N> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> IndexMut<I> for ArrayVec<T, N> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+// Extending past the capacity panics, like `push`.
+impl<T, const N: usize> Extend<T> for ArrayVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for ArrayVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
     }
 }
 
@@ -111,6 +409,1208 @@ impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
     type IntoIter = core::slice::Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.data[..self.len].iter()
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for ArrayVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            vec: self,
+            front: 0,
+        }
+    }
+}
+
+/// Moves the elements out of an `ArrayVec`, from either end.
+pub struct IntoIter<T, const N: usize> {
+    // The elements still to come are `front..vec.len`.
+    vec: ArrayVec<T, N>,
+    front: usize,
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front < self.vec.len {
+            let value = unsafe { ptr::read(self.vec.as_ptr().add(self.front)) };
+            self.front += 1;
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front < self.vec.len {
+            unsafe { Some(self.vec.pop_unsafe()) }
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+// The elements before `front` have already been moved out, so we drop the rest ourselves,
+// and leave the vec thinking it's empty.
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        let len = self.vec.len;
+        self.vec.len = 0;
+        unsafe {
+            let rest = ptr::slice_from_raw_parts_mut(
+                self.vec.as_mut_ptr().add(self.front),
+                len - self.front,
+            );
+            ptr::drop_in_place(rest);
+        }
+    }
+}
+
+/// Up to `N` elements inline in an `ArrayVec`, spilling over into a `Vec` on the heap once it runs out of room.
+///
+/// This has the same API as `ArrayVec`, except that pushing never runs out of room, so there's no need for `try_` forms.
+/// The solvers size `N` for the inputs we've seen, and pay for the heap only on inputs bigger than that.
+#[derive(Clone)]
+pub struct SmallVec<T, const N: usize>(SmallVecData<T, N>);
+
+#[derive(Clone)]
+enum SmallVecData<T, const N: usize> {
+    Inline(ArrayVec<T, N>),
+    Spilled(Vec<T>),
+}
+
+use SmallVecData::{Inline, Spilled};
+
+impl<T: Default + Copy, const N: usize> SmallVec<T, N> {
+    pub fn zeros(len: usize) -> Self {
+        if len <= N {
+            Self(Inline(ArrayVec::zeros(len)))
+        } else {
+            Self(Spilled(vec![T::default(); len]))
+        }
+    }
+}
+
+impl<T, const N: usize> SmallVec<T, N> {
+    pub const fn new() -> Self {
+        Self(Inline(ArrayVec::new()))
+    }
+
+    /// Have the elements moved out onto the heap?
+    pub fn spilled(&self) -> bool {
+        matches!(self.0, Spilled(_))
+    }
+
+    // Move the inline elements onto the heap, with room for as many again.
+    fn spill(&mut self) -> &mut Vec<T> {
+        if let Inline(inline) = &mut self.0 {
+            let mut vec = Vec::with_capacity(N * 2);
+            vec.extend(std::mem::take(inline));
+            self.0 = Spilled(vec);
+        }
+        match &mut self.0 {
+            Spilled(vec) => vec,
+            Inline(_) => unreachable!(),
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        match &mut self.0 {
+            Inline(inline) => {
+                if let Err(e) = inline.try_push(value) {
+                    self.spill().push(e.element);
+                }
+            }
+            Spilled(vec) => vec.push(value),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.0 {
+            Inline(inline) => inline.pop(),
+            Spilled(vec) => vec.pop(),
+        }
+    }
+
+    /// Insert at `index`, shifting everything after it up by one.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        match &mut self.0 {
+            Inline(inline) => {
+                if let Err(e) = inline.try_insert(index, value) {
+                    self.spill().insert(index, e.element);
+                }
+            }
+            Spilled(vec) => vec.insert(index, value),
+        }
+    }
+
+    /// Remove and return the element at `index`, shifting everything after it down by one.
+    pub fn remove(&mut self, index: usize) -> T {
+        match &mut self.0 {
+            Inline(inline) => inline.remove(index),
+            Spilled(vec) => vec.remove(index),
+        }
+    }
+
+    /// Remove and return the element at `index`, replacing it with the last element.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        match &mut self.0 {
+            Inline(inline) => inline.swap_remove(index),
+            Spilled(vec) => vec.swap_remove(index),
+        }
+    }
+
+    /// Drop everything from `len` onwards.
+    /// Once spilled, we stay on the heap, keeping the room we've made.
+    pub fn truncate(&mut self, len: usize) {
+        match &mut self.0 {
+            Inline(inline) => inline.truncate(len),
+            Spilled(vec) => vec.truncate(len),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Keep only the elements for which `keep` returns true, in their original order.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, keep: F) {
+        match &mut self.0 {
+            Inline(inline) => inline.retain(keep),
+            Spilled(vec) => vec.retain(keep),
+        }
+    }
+
+    pub fn get_last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+
+    pub fn get_last_mut(&mut self) -> Option<&mut T> {
+        self.as_mut_slice().last_mut()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many elements fit before we next have to find more room.
+    pub fn capacity(&self) -> usize {
+        match &self.0 {
+            Inline(_) => N,
+            Spilled(vec) => vec.capacity(),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match &self.0 {
+            Inline(inline) => inline.as_slice(),
+            Spilled(vec) => vec.as_slice(),
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.0 {
+            Inline(inline) => inline.as_mut_slice(),
+            Spilled(vec) => vec.as_mut_slice(),
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> SmallVec<T, N> {
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        match &mut self.0 {
+            Inline(inline) => {
+                if inline.try_extend_from_slice(other).is_err() {
+                    self.spill().extend_from_slice(other);
+                }
+            }
+            Spilled(vec) => vec.extend_from_slice(other),
+        }
+    }
+}
+
+impl<T, const N: usize> Default for SmallVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for SmallVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for SmallVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for SmallVec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for SmallVec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> Index<I> for SmallVec<T, N> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> IndexMut<I> for SmallVec<T, N> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+// Where the elements live doesn't matter, only what they are.
+impl<T: PartialEq, const N: usize> PartialEq for SmallVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for SmallVec<T, N> {}
+
+impl<T, const N: usize> Extend<T> for SmallVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for SmallVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T: PartialEq, const N: usize> SmallVec<T, N> {
+    pub fn contains(&self, value: &T) -> bool {
+        self.iter().any(|v| v == value)
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for SmallVec<T, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a SmallVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut SmallVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for SmallVec<T, N> {
+    type Item = T;
+    type IntoIter = SmallVecIntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SmallVecIntoIter(match self.0 {
+            Inline(inline) => SmallVecIntoIterData::Inline(inline.into_iter()),
+            Spilled(vec) => SmallVecIntoIterData::Spilled(vec.into_iter()),
+        })
+    }
+}
+
+/// Moves the elements out of a `SmallVec`, from either end.
+pub struct SmallVecIntoIter<T, const N: usize>(SmallVecIntoIterData<T, N>);
+
+enum SmallVecIntoIterData<T, const N: usize> {
+    Inline(IntoIter<T, N>),
+    Spilled(std::vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Iterator for SmallVecIntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match &mut self.0 {
+            SmallVecIntoIterData::Inline(it) => it.next(),
+            SmallVecIntoIterData::Spilled(it) => it.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            SmallVecIntoIterData::Inline(it) => it.size_hint(),
+            SmallVecIntoIterData::Spilled(it) => it.size_hint(),
+        }
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for SmallVecIntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        match &mut self.0 {
+            SmallVecIntoIterData::Inline(it) => it.next_back(),
+            SmallVecIntoIterData::Spilled(it) => it.next_back(),
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for SmallVecIntoIter<T, N> {}
+
+/// A ring buffer with a fixed capacity of `N`, stored inline, for pushing and popping at both ends.
+///
+/// The elements are the `len` slots starting from `head`, wrapping around the end of the array back to the start.
+/// Like `ArrayVec`, it has `try_` forms that hand back an element that doesn't fit, plain forms that panic,
+/// and unchecked forms for the hot loops.
+pub struct ArrayDeque<T, const N: usize> {
+    head: usize,
+    len: usize,
+    data: [MaybeUninit<T>; N],
+}
+
+impl<T, const N: usize> ArrayDeque<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            head: 0,
+            len: 0,
+            data: [const { MaybeUninit::uninit() }; N],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn remaining_capacity(&self) -> usize {
+        N - self.len
+    }
+
+    // The slot holding the element `index` places from the front.
+    // This is cheaper than `%`, as we never wrap more than once.
+    fn slot(&self, index: usize) -> usize {
+        let slot = self.head + index;
+        if slot >= N {
+            slot - N
+        } else {
+            slot
+        }
+    }
+
+    fn as_ptr(&self) -> *const T {
+        self.data.as_ptr() as *const T
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr() as *mut T
+    }
+
+    /// # Safety
+    /// The deque must not be full.
+    pub unsafe fn push_back_unchecked(&mut self, value: T) {
+        let slot = self.slot(self.len);
+        self.data.get_unchecked_mut(slot).write(value);
+        self.len += 1;
+    }
+
+    /// # Safety
+    /// The deque must not be full.
+    pub unsafe fn push_front_unchecked(&mut self, value: T) {
+        self.head = if self.head == 0 { N - 1 } else { self.head - 1 };
+        self.data.get_unchecked_mut(self.head).write(value);
+        self.len += 1;
+    }
+
+    /// # Safety
+    /// The deque must not be empty.
+    pub unsafe fn pop_front_unchecked(&mut self) -> T {
+        let value = self.data.get_unchecked(self.head).assume_init_read();
+        self.head = self.slot(1);
+        self.len -= 1;
+        value
+    }
+
+    /// # Safety
+    /// The deque must not be empty.
+    pub unsafe fn pop_back_unchecked(&mut self) -> T {
+        self.len -= 1;
+        self.data
+            .get_unchecked(self.slot(self.len))
+            .assume_init_read()
+    }
+
+    /// # Safety
+    /// `index` must be less than `len`.
+    pub unsafe fn get_unchecked(&self, index: usize) -> &T {
+        self.data.get_unchecked(self.slot(index)).assume_init_ref()
+    }
+
+    /// # Safety
+    /// `index` must be less than `len`.
+    pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut T {
+        let slot = self.slot(index);
+        self.data.get_unchecked_mut(slot).assume_init_mut()
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        if let Err(e) = self.try_push_back(value) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_push_back(&mut self, value: T) -> Result<(), CapacityError<T>> {
+        if self.len < N {
+            unsafe { self.push_back_unchecked(value) };
+            Ok(())
+        } else {
+            Err(CapacityError {
+                element: value,
+                capacity: N,
+            })
+        }
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        if let Err(e) = self.try_push_front(value) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_push_front(&mut self, value: T) -> Result<(), CapacityError<T>> {
+        if self.len < N {
+            unsafe { self.push_front_unchecked(value) };
+            Ok(())
+        } else {
+            Err(CapacityError {
+                element: value,
+                capacity: N,
+            })
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            unsafe { Some(self.pop_front_unchecked()) }
+        }
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            unsafe { Some(self.pop_back_unchecked()) }
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            unsafe { Some(self.get_unchecked(index)) }
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len {
+            unsafe { Some(self.get_unchecked_mut(index)) }
+        } else {
+            None
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.len.checked_sub(1).and_then(|i| self.get_mut(i))
+    }
+
+    /// The elements in order, as the run up to the end of the array, and then the run that wrapped round to the start.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let first = self.len.min(N - self.head);
+        unsafe {
+            let p = self.as_ptr();
+            (
+                slice::from_raw_parts(p.add(self.head), first),
+                slice::from_raw_parts(p, self.len - first),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let first = self.len.min(N - self.head);
+        // Both halves come from the one pointer, so that neither invalidates the other.
+        unsafe {
+            let p = self.as_mut_ptr();
+            (
+                slice::from_raw_parts_mut(p.add(self.head), first),
+                slice::from_raw_parts_mut(p, self.len - first),
+            )
+        }
+    }
+
+    pub fn iter(&self) -> Chain<Iter<'_, T>, Iter<'_, T>> {
+        let (a, b) = self.as_slices();
+        a.iter().chain(b.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> Chain<IterMut<'_, T>, IterMut<'_, T>> {
+        let (a, b) = self.as_mut_slices();
+        a.iter_mut().chain(b.iter_mut())
+    }
+
+    pub fn clear(&mut self) {
+        let (a, b) = self.as_mut_slices();
+        let (a, b) = (a as *mut [T], b as *mut [T]);
+        // Empty first, so that if a drop panics, we don't drop anything twice.
+        self.head = 0;
+        self.len = 0;
+        unsafe {
+            ptr::drop_in_place(a);
+            ptr::drop_in_place(b);
+        }
+    }
+}
+
+impl<T: PartialEq, const N: usize> ArrayDeque<T, N> {
+    pub fn contains(&self, value: &T) -> bool {
+        self.iter().any(|v| v == value)
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayDeque<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayDeque<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T, const N: usize> Default for ArrayDeque<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Index<usize> for ArrayDeque<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index (is {}) should be < len (is {})", index, self.len),
+        }
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for ArrayDeque<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(value) => value,
+            None => panic!("index (is {}) should be < len (is {})", index, len),
+        }
+    }
+}
+
+// Equal if they hold the same elements in the same order, wherever they've wrapped round.
+impl<T: PartialEq, const N: usize> PartialEq for ArrayDeque<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayDeque<T, N> {}
+
+// Extending past the capacity panics, like `push_back`.
+impl<T, const N: usize> Extend<T> for ArrayDeque<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for ArrayDeque<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut deque = Self::new();
+        deque.extend(iter);
+        deque
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for ArrayDeque<T, N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayDeque<T, N> {
+    type Item = &'a T;
+    type IntoIter = Chain<Iter<'a, T>, Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayDeque<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = Chain<IterMut<'a, T>, IterMut<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for ArrayDeque<T, N> {
+    type Item = T;
+    type IntoIter = ArrayDequeIntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ArrayDequeIntoIter(self)
+    }
+}
+
+/// Moves the elements out of an `ArrayDeque`, from either end.
+/// Popping already does everything we need, and the deque drops whatever is left.
+pub struct ArrayDequeIntoIter<T, const N: usize>(ArrayDeque<T, N>);
+
+impl<T, const N: usize> Iterator for ArrayDequeIntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for ArrayDequeIntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for ArrayDequeIntoIter<T, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn one_two_three() -> ArrayVec<u32, 4> {
+        [1, 2, 3].into_iter().collect()
+    }
+
+    #[test]
+    fn push_until_full() {
+        let mut v: ArrayVec<u32, 2> = ArrayVec::new();
+        assert!(v.is_empty());
+        v.push(1);
+        assert_eq!(v.try_push(2), Ok(()));
+        assert!(v.is_full());
+        assert_eq!(
+            v.try_push(3),
+            Err(CapacityError {
+                element: 3,
+                capacity: 2
+            })
+        );
+        assert_eq!(v.as_slice(), &[1, 2]);
+        assert_eq!(v.remaining_capacity(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already full, with capacity 2")]
+    fn push_past_capacity_panics() {
+        let mut v: ArrayVec<u32, 2> = ArrayVec::new();
+        v.extend([1, 2, 3]);
+    }
+
+    #[test]
+    fn pop_from_the_end() {
+        let mut v = one_two_three();
+        assert_eq!(v.pop(), Some(3));
+        assert_eq!(v.pop(), Some(2));
+        assert_eq!(v.pop(), Some(1));
+        assert_eq!(v.pop(), None);
+    }
+
+    #[test]
+    fn insert_and_remove_shift_the_rest() {
+        let mut v = one_two_three();
+        v.insert(1, 10);
+        assert_eq!(v.as_slice(), &[1, 10, 2, 3]);
+        assert_eq!(
+            v.try_insert(0, 20),
+            Err(CapacityError {
+                element: 20,
+                capacity: 4
+            })
+        );
+        assert_eq!(v.remove(0), 1);
+        assert_eq!(v.as_slice(), &[10, 2, 3]);
+        v.insert(3, 4);
+        assert_eq!(v.as_slice(), &[10, 2, 3, 4]);
+        assert_eq!(v.swap_remove(0), 10);
+        assert_eq!(v.as_slice(), &[4, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 4) should be <= len (is 3)")]
+    fn insert_past_the_end_panics() {
+        one_two_three().insert(4, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index (is 3) should be < len (is 3)")]
+    fn remove_past_the_end_panics() {
+        one_two_three().remove(3);
+    }
+
+    #[test]
+    fn truncate_retain_and_clear() {
+        let mut v: ArrayVec<u32, 8> = (0..8).collect();
+        v.retain(|&x| x % 2 == 0);
+        assert_eq!(v.as_slice(), &[0, 2, 4, 6]);
+        v.truncate(10);
+        assert_eq!(v.len(), 4);
+        v.truncate(2);
+        assert_eq!(v.as_slice(), &[0, 2]);
+        v.clear();
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn extend_from_slice_is_all_or_nothing() {
+        let mut v = one_two_three();
+        assert_eq!(
+            v.try_extend_from_slice(&[4, 5]),
+            Err(CapacityError {
+                element: (),
+                capacity: 4
+            })
+        );
+        assert_eq!(v.as_slice(), &[1, 2, 3]);
+        assert_eq!(v.try_extend_from_slice(&[4]), Ok(()));
+        assert_eq!(v.as_slice(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn index_and_deref_like_a_slice() {
+        let mut v = one_two_three();
+        v[1] = 20;
+        assert_eq!(v[1], 20);
+        assert_eq!(&v[1..], &[20, 3]);
+        v.sort_by(|a, b| b.cmp(a));
+        assert_eq!(v.first(), Some(&20));
+        for x in &mut v {
+            *x += 1;
+        }
+        assert_eq!(v, [21, 4, 2].into_iter().collect());
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_past_len_panics() {
+        let v = one_two_three();
+        let _ = v[3];
+    }
+
+    #[test]
+    fn into_iter_by_value_from_both_ends() {
+        let mut it = one_two_three().into_iter();
+        assert_eq!(it.len(), 3);
+        assert_eq!(it.next(), Some(1));
+        assert_eq!(it.next_back(), Some(3));
+        assert_eq!(it.next(), Some(2));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next_back(), None);
+    }
+
+    // Each element holds a clone of the `Rc`, so its strong count says how many are still alive.
+    fn counted<const N: usize>(rc: &Rc<()>, n: usize) -> ArrayVec<Rc<()>, N> {
+        (0..n).map(|_| rc.clone()).collect()
+    }
+
+    #[test]
+    fn drops_only_the_elements() {
+        let rc = Rc::new(());
+        let v: ArrayVec<Rc<()>, 8> = counted(&rc, 3);
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn removing_drops_or_hands_back() {
+        let rc = Rc::new(());
+        let mut v: ArrayVec<Rc<()>, 8> = counted(&rc, 6);
+        v.truncate(4);
+        assert_eq!(Rc::strong_count(&rc), 5);
+        let removed = v.remove(0);
+        let swapped = v.swap_remove(0);
+        let popped = v.pop();
+        assert_eq!(Rc::strong_count(&rc), 5);
+        drop((removed, swapped, popped));
+        assert_eq!(Rc::strong_count(&rc), 2);
+
+        let mut first = true;
+        v.push(rc.clone());
+        v.retain(|_| std::mem::take(&mut first));
+        assert_eq!(v.len(), 1);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        v.clear();
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn clone_clones_only_the_elements() {
+        let rc = Rc::new(());
+        let v: ArrayVec<Rc<()>, 8> = counted(&rc, 2);
+        let w = v.clone();
+        assert_eq!(w.len(), 2);
+        assert_eq!(Rc::strong_count(&rc), 5);
+        drop(v);
+        drop(w);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn into_iter_drops_what_it_did_not_yield() {
+        let rc = Rc::new(());
+        let mut it = counted::<8>(&rc, 4).into_iter();
+        let first = it.next();
+        let last = it.next_back();
+        assert_eq!(Rc::strong_count(&rc), 5);
+        drop(it);
+        assert_eq!(Rc::strong_count(&rc), 3);
+        drop((first, last));
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn holds_owned_values() {
+        let mut strings: ArrayVec<String, 3> = ["a", "b"].iter().map(|s| s.to_string()).collect();
+        strings.insert(1, "c".to_string());
+        assert_eq!(strings.clone().as_slice(), &["a", "c", "b"]);
+        assert_eq!(
+            strings.into_iter().rev().collect::<Vec<_>>(),
+            vec!["b", "c", "a"]
+        );
+    }
+
+    #[test]
+    fn zeros_are_elements() {
+        let v: ArrayVec<u64, 4> = ArrayVec::zeros(3);
+        assert_eq!(v.as_slice(), &[0, 0, 0]);
+        assert_eq!(v.clone(), v);
+    }
+
+    #[test]
+    fn small_vec_spills_when_full() {
+        let mut v: SmallVec<u32, 2> = SmallVec::new();
+        v.push(1);
+        v.push(2);
+        assert!(!v.spilled());
+        assert_eq!(v.capacity(), 2);
+        v.push(3);
+        assert!(v.spilled());
+        assert!(v.capacity() >= 3);
+        assert_eq!(v.as_slice(), &[1, 2, 3]);
+        assert_eq!(v.pop(), Some(3));
+        assert_eq!(v.get_last(), Some(&2));
+    }
+
+    #[test]
+    fn small_vec_spills_on_insert_and_extend() {
+        let mut v: SmallVec<u32, 3> = [1, 2, 3].into_iter().collect();
+        v.insert(1, 10);
+        assert!(v.spilled());
+        assert_eq!(v.as_slice(), &[1, 10, 2, 3]);
+
+        let mut w: SmallVec<u32, 3> = SmallVec::new();
+        w.extend_from_slice(&[1, 2]);
+        assert!(!w.spilled());
+        w.extend_from_slice(&[3, 4]);
+        assert!(w.spilled());
+        assert_eq!(
+            w,
+            v.iter().copied().filter(|&x| x != 10).chain([4]).collect()
+        );
+    }
+
+    #[test]
+    fn small_vec_edits_like_a_vec() {
+        for n in [4, 8] {
+            // Once while still inline, and once spilled.
+            let mut v: SmallVec<u32, 6> = (0..n).collect();
+            assert_eq!(v.spilled(), n > 6);
+            assert_eq!(v.remove(0), 0);
+            assert_eq!(v.swap_remove(0), 1);
+            v.retain(|&x| x != 2);
+            assert_eq!(v[0], n - 1);
+            v.truncate(1);
+            assert_eq!(v.as_slice(), &[n - 1]);
+            v.clear();
+            assert!(v.is_empty());
+        }
+    }
+
+    #[test]
+    fn small_vec_zeros_spill_if_too_long() {
+        let v: SmallVec<u64, 4> = SmallVec::zeros(3);
+        assert!(!v.spilled());
+        assert_eq!(v.as_slice(), &[0, 0, 0]);
+        let w: SmallVec<u64, 4> = SmallVec::zeros(5);
+        assert!(w.spilled());
+        assert_eq!(w.len(), 5);
+    }
+
+    #[test]
+    fn small_vec_into_iter_from_both_ends() {
+        for n in [3, 6] {
+            let mut it = (0..n).collect::<SmallVec<u32, 4>>().into_iter();
+            assert_eq!(it.len(), n as usize);
+            assert_eq!(it.next(), Some(0));
+            assert_eq!(it.next_back(), Some(n - 1));
+            assert_eq!(it.collect::<Vec<_>>(), (1..n - 1).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn small_vec_drops_across_the_spill() {
+        let rc = Rc::new(());
+        let mut v: SmallVec<Rc<()>, 2> = (0..2).map(|_| rc.clone()).collect();
+        let w = v.clone();
+        v.push(rc.clone());
+        assert!(v.spilled());
+        assert_eq!(Rc::strong_count(&rc), 6);
+        drop(v);
+        drop(w);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn deque_is_first_in_first_out() {
+        let mut q: ArrayDeque<u32, 3> = ArrayDeque::new();
+        q.push_back(1);
+        q.push_back(2);
+        q.push_back(3);
+        assert!(q.is_full());
+        assert_eq!(
+            q.try_push_back(4),
+            Err(CapacityError {
+                element: 4,
+                capacity: 3
+            })
+        );
+        assert_eq!(q.pop_front(), Some(1));
+        // This one wraps round to the start of the array.
+        q.push_back(4);
+        assert_eq!(q.as_slices(), (&[2, 3][..], &[4][..]));
+        assert_eq!(q.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(q.pop_front(), Some(2));
+        assert_eq!(q.pop_front(), Some(3));
+        assert_eq!(q.pop_front(), Some(4));
+        assert_eq!(q.pop_front(), None);
+    }
+
+    #[test]
+    fn deque_pushes_and_pops_at_both_ends() {
+        let mut q: ArrayDeque<u32, 4> = ArrayDeque::new();
+        q.push_front(2);
+        q.push_front(1);
+        q.push_back(3);
+        assert_eq!(q.front(), Some(&1));
+        assert_eq!(q.back(), Some(&3));
+        assert_eq!(q[1], 2);
+        q[1] = 20;
+        *q.back_mut().unwrap() = 30;
+        assert_eq!(q.iter().rev().copied().collect::<Vec<_>>(), vec![30, 20, 1]);
+        assert_eq!(q.pop_back(), Some(30));
+        assert_eq!(q.pop_front(), Some(1));
+        assert_eq!(q.pop_back(), Some(20));
+        assert_eq!(q.pop_back(), None);
+        assert_eq!(q.back(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already full, with capacity 2")]
+    fn deque_push_front_past_capacity_panics() {
+        let mut q: ArrayDeque<u32, 2> = [1, 2].into_iter().collect();
+        q.push_front(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "index (is 2) should be < len (is 2)")]
+    fn deque_index_past_len_panics() {
+        let q: ArrayDeque<u32, 4> = [1, 2].into_iter().collect();
+        let _ = q[2];
+    }
+
+    #[test]
+    fn deque_unchecked_matches_checked() {
+        let mut q: ArrayDeque<u32, 4> = ArrayDeque::new();
+        unsafe {
+            q.push_back_unchecked(2);
+            q.push_front_unchecked(1);
+            assert_eq!(*q.get_unchecked(1), 2);
+            assert_eq!(q.pop_back_unchecked(), 2);
+            assert_eq!(q.pop_front_unchecked(), 1);
+        }
+        assert!(q.is_empty());
+    }
+
+    // Random pushes and pops at both ends should always agree with `VecDeque`.
+    #[test]
+    fn deque_agrees_with_vec_deque() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        use std::collections::VecDeque;
+
+        let mut rng = StdRng::seed_from_u64(22);
+        let mut q: ArrayDeque<u32, 5> = ArrayDeque::new();
+        let mut model = VecDeque::new();
+        for i in 0..1000 {
+            match rng.gen_range(0..4) {
+                0 if model.len() < 5 => {
+                    q.push_back(i);
+                    model.push_back(i);
+                }
+                1 if model.len() < 5 => {
+                    q.push_front(i);
+                    model.push_front(i);
+                }
+                2 => assert_eq!(q.pop_front(), model.pop_front()),
+                _ => assert_eq!(q.pop_back(), model.pop_back()),
+            }
+            assert_eq!(
+                q.iter().collect::<Vec<_>>(),
+                model.iter().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn deque_into_iter_from_both_ends() {
+        let mut q: ArrayDeque<u32, 4> = [2, 3, 4].into_iter().collect();
+        q.pop_front();
+        q.push_back(5);
+        q.push_back(6);
+        let mut it = q.clone().into_iter();
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.next(), Some(3));
+        assert_eq!(it.next_back(), Some(6));
+        assert_eq!(it.collect::<Vec<_>>(), vec![4, 5]);
+        for x in &mut q {
+            *x *= 10;
+        }
+        assert_eq!(q, [30, 40, 50, 60].into_iter().collect());
+    }
+
+    #[test]
+    fn deque_drops_only_the_elements() {
+        let rc = Rc::new(());
+        let mut q: ArrayDeque<Rc<()>, 4> = (0..4).map(|_| rc.clone()).collect();
+        // Wrap round, so the elements are in both halves.
+        drop(q.pop_front());
+        drop(q.pop_front());
+        q.push_back(rc.clone());
+        let copy = q.clone();
+        assert_eq!(Rc::strong_count(&rc), 7);
+
+        let mut it = q.into_iter();
+        drop(it.next());
+        drop(it);
+        assert_eq!(Rc::strong_count(&rc), 4);
+        drop(copy);
+        assert_eq!(Rc::strong_count(&rc), 1);
     }
 }